@@ -2,30 +2,37 @@ use std::time::{Duration, Instant};
 use std::collections::HashMap;
 use std::env;
 
-use wgpu::{BindGroupLayout, BindGroupLayoutDescriptor, Device, DeviceDescriptor, Features, InstanceDescriptor, Limits, Queue, Surface, SurfaceConfiguration, TextureUsages};
 use sdl2::{joystick::Joystick, JoystickSubsystem, GameControllerSubsystem, HapticSubsystem, controller::GameController};
-use glyphon::{Cache, Resolution, TextArea, Viewport};
+use glyphon::TextArea;
 
 use crate::engine::audio::audio::Audio;
 use crate::engine::physics::physics::{physics_handling, DebugPhysicsMessageType, PhysicsDataTransmission};
-use crate::engine::physics::physics_handler::{RenderMessage, PhysicsCommand};
+use crate::engine::physics::physics_handler::{PhysicsCommand, PhysicsStatus, WorldChanges};
+use crate::engine::physics::interpolation::PhysicsInterpolation;
+use crate::engine::physics::events::PhysicsEvent;
 use crate::engine::rendering::enviroment::skybox_renderer::SkyboxRender;
+use crate::engine::rendering::enviroment::terrain_renderer::TerrainRender;
 use crate::engine::rendering::enviroment::environment;
 use crate::engine::rendering::instance_management::{InstanceData, InstanceRaw, ModelDataInstance};
-use crate::engine::rendering::render_pipeline::depth_renderer::DepthRender;
 use crate::engine::rendering::camera::CameraRenderizable;
 use crate::engine::rendering::models::textures::Texture;
 use crate::engine::game_nodes::timing::Timing;
 use crate::engine::rendering::enviroment::light::Light;
-use crate::engine::rendering::models::model::{self, Mesh, Model, Vertex};
+use crate::engine::rendering::models::model::{self, Mesh, Vertex};
 use crate::engine::rendering::renderer::Renderer;
-use crate::engine::scene_manager::scene::{Scene, ScenePool, FrameContext, GameState, SceneManager};
+use crate::engine::scene_manager::scene::{FrameContext, GameState, SceneManager};
 use crate::engine::input::input::InputSubsystem;
 use crate::engine::rendering::ui::physics_rendering::RenderPhysics;
 use crate::engine::rendering::ui::rendering_utils;
 use crate::engine::rendering::ui::ui::Ui;
 use crate::resources;
 use crate::engine::window::window::{WindowManager, WindowSettings};
+use crate::debug_text;
+
+// Seconds the physics goes back per press of the rewind key
+const PHYSICS_REWIND_STEP: f32 = 1.0;
+// Where F9 saves the physics world and F10 loads it back from
+const QUICKSAVE_PATH: &str = "./saves/quicksave.ron";
 
 #[derive(Clone)]
 pub struct AppState {
@@ -56,6 +63,8 @@ pub struct App {
     // Configured per scene via resources::apply_environment (called from Scene::reset),
     // not loaded automatically - None means the scene just wants clear_color.
     pub skybox: Option<SkyboxRender>,
+    // The level's ground when it declares a terrain, set by resources::load_level
+    pub terrain: Option<TerrainRender>,
     pub clear_color: wgpu::Color,
     pub show_depth_map: bool,
     pub controller_subsystem: GameControllerSubsystem,
@@ -63,17 +72,22 @@ pub struct App {
     pub _haptic_subsystem: HapticSubsystem,
     // pub renderizable_instances: HashMap<String, HashMap<String, InstanceData>>,
     pub renderizable_instances: HashMap<String, InstanceData>,
+    // Game objects with physics despawned from the running level, a loaded physics snapshot can bring them back
+    pub despawned_instances: HashMap<String, InstanceData>,
     pub throttling: Throttling,
     pub game_models: HashMap<String, ModelDataInstance>,
     pub light: Light,
     pub time: Timing,
     pub scene_openned: Option<String>,
+    // Aircraft id picked in the plane selection scene (an assets/aircraft/<id>.ron
+    // file), read back by whichever scene spawns the player.
+    pub selected_aircraft: String,
     pub audio: Audio,
     pub render_physics: RenderPhysics,
 }
 
 impl App {
-    pub async fn new(title: &str, ext_width: Option<u32>, ext_height: Option<u32>) -> Result<App, String> {
+    pub async fn new(title: &str, _ext_width: Option<u32>, _ext_height: Option<u32>) -> Result<App, String> {
         // Window initialization
         
         let window_manager = WindowManager::new(WindowSettings {
@@ -148,17 +162,20 @@ impl App {
             ui,
             camera,
             skybox,
+            terrain: None,
             clear_color,
             show_depth_map: false,
             controller_subsystem,
             joystick_subsystem,
             renderizable_instances,
+            despawned_instances: HashMap::new(),
             throttling: Throttling { last_ui_update: Instant::now(), ui_update_interval: Duration::from_secs_f32(1.0/120.0), last_controller_update: Instant::now(), controller_update_interval: Duration::from_secs_f32(1.0/400.0) },
             _haptic_subsystem: haptic_subsystem,
             game_models,
             light,
             time,
             scene_openned: None,
+            selected_aircraft: "f16".to_owned(),
             audio: Audio::new(),
             render_physics,
         })
@@ -206,7 +223,7 @@ impl App {
         self.ui.ui_rendering.indices.clear();
         self.ui.ui_rendering.num_indices = 0;
 
-        for (_key, ui_node) in &mut self.ui.renderizable_elements {
+        for ui_node in self.ui.renderizable_elements.values_mut() {
             let (textareas_to_merge, _vertices_to_add, _indices_to_add) = ui_node.node_content_preparation(&self.window_manager.size, &mut self.ui.ui_rendering, &mut self.ui.text.font_system, self.time.delta_time);
             text_areas.extend(textareas_to_merge);
         }
@@ -244,6 +261,12 @@ impl App {
         let mut input_subsystem = InputSubsystem::new(include_str!("../settings/input.ron"));
 
         let mut debug_physics: Vec<DebugPhysicsMessageType> = Vec::new();
+        let mut physics_interpolation = PhysicsInterpolation::new();
+        // Last state the physics thread reported, None until it sends one
+        let mut physics_status: Option<PhysicsStatus> = None;
+        let mut physics_events: Vec<PhysicsEvent> = Vec::new();
+        // What a loaded physics snapshot changed, only on the frame it arrives
+        let mut world_changes: Option<WorldChanges>;
 
         loop {
             // Relevant subsystems update
@@ -267,6 +290,7 @@ impl App {
                 // wants a skybox has to call apply_environment itself in reset/new.
                 self.skybox = None;
                 self.clear_color = environment::DEFAULT_CLEAR_COLOR;
+                self.terrain = None;
 
                 // Scenes need &mut App to reset themselves, but the pool they're
                 // stored in lives on App too - take the scene out first so there's
@@ -282,6 +306,8 @@ impl App {
 
                     scene.reset(&mut self);
 
+                    physics_interpolation = PhysicsInterpolation::new();
+                    physics_status = None;
                     physics_data_channel = scene.physics(&self).map(|(level_path, physics_tick)| {
                         physics_handling(&self.renderer.device, &self.renderer.config, &self.camera, level_path, physics_tick)
                     });
@@ -314,11 +340,54 @@ impl App {
                         }
                     }
 
+                    // Slow motion / fast forward with F5/F6, halving or doubling the current scale
+                    let time_scale = physics_status.as_ref().map_or(1.0, |status| status.time_scale);
+                    if input_subsystem.is_just_pressed("physics_slower") {
+                        let _ = physics.request_data_tx.send(PhysicsCommand::SetTimeScale(time_scale * 0.5));
+                    }
+                    if input_subsystem.is_just_pressed("physics_faster") {
+                        let _ = physics.request_data_tx.send(PhysicsCommand::SetTimeScale(time_scale * 2.0));
+                    }
+
+                    // Single step while paused with F7
+                    if input_subsystem.is_just_pressed("physics_step") {
+                        let _ = physics.request_data_tx.send(PhysicsCommand::StepOnce);
+                    }
+
+                    // Rewind one second per press of F8. Only the rigidbodies go back (see RewindBuffer):
+                    // the ticks' state, broken joints and events already handed out stay in the present,
+                    // quickload (F10) is the way to get all of it back
+                    if input_subsystem.is_just_pressed("physics_rewind") {
+                        let _ = physics.request_data_tx.send(PhysicsCommand::Rewind(PHYSICS_REWIND_STEP));
+                    }
+
+                    // Quicksave and quickload the physics world with F9/F10
+                    if input_subsystem.is_just_pressed("quicksave") {
+                        let _ = physics.request_data_tx.send(PhysicsCommand::SaveSnapshot(QUICKSAVE_PATH.to_string()));
+                    }
+                    if input_subsystem.is_just_pressed("quickload") {
+                        let _ = physics.request_data_tx.send(PhysicsCommand::LoadSnapshot(QUICKSAVE_PATH.to_string()));
+                    }
+
+                    while let Ok(status) = physics.status_rx.try_recv() {
+                        physics_status = Some(status);
+                    }
+
+                    // Unlike the state above, every collision is handed to the scene exactly once
+                    physics_events.clear();
+                    while let Ok(events) = physics.events_rx.try_recv() {
+                        physics_events.extend(events);
+                    }
+
+                    // A loaded snapshot replaced the world, draw what's in it now
+                    world_changes = None;
+                    while let Ok(changes) = physics.world_rx.try_recv() {
+                        resources::reconcile_physics_world(&mut self, Some(&physics.request_data_tx), &changes);
+                        world_changes = Some(changes);
+                    }
+
                     // Recibimos los datos del otro thread
-                    let physics_data = match physics.physics_data_rx.try_recv() {
-                        Ok(data) => data,
-                        Err(_) => HashMap::new(),
-                    };
+                    let physics_data = physics.physics_data_rx.try_recv().unwrap_or_default();
 
                     // Drain all queued debug physics messages, keep only the latest
                     let mut got_new = false;
@@ -333,9 +402,32 @@ impl App {
                     physics_data
                 } else {
                     debug_physics.clear();
+                    physics_events.clear();
+                    world_changes = None;
                     HashMap::new()
                 };
 
+                if let Some(status) = &physics_status {
+                    debug_text!(
+                        "Physics: {} x{} t={:.2}s rewind {:.1}s",
+                        if status.paused { "PAUSED" } else { "RUNNING" },
+                        status.time_scale,
+                        status.time,
+                        status.rewind_available,
+                    );
+                    debug_text!("Physics origin: x {:.1}km z {:.1}km", status.origin.x / 1000.0, status.origin.z / 1000.0);
+                    for joint in &status.joints {
+                        debug_text!(
+                            "Joint {} ({}): {:.2} {:.0}N{}",
+                            joint.id,
+                            joint.kind,
+                            joint.position,
+                            joint.force,
+                            joint.break_force.map_or(String::new(), |break_force| format!(" / {:.0}N", break_force)),
+                        );
+                    }
+                }
+
                 // Toggle console independently with F3
                 if input_subsystem.is_just_pressed("toggle_console") {
                     crate::engine::tooling::debug_console::toggle_console();
@@ -347,18 +439,21 @@ impl App {
                 for message in &debug_physics {
                     match message {
                         DebugPhysicsMessageType::RenderizableLines(lines) => {
-                            self.render_physics.renderizable_lines.push(lines.clone());
+                            self.render_physics.renderizable_lines.push(*lines);
                         },
-                        DebugPhysicsMessageType::RenderizablePoint(point) => {
+                        DebugPhysicsMessageType::RenderizablePoint(_point) => {
                         },
                     }
                 }
 
-                // Apply physics data to transforms first with smoothing
+                // Apply physics data to transforms first, blended between the last two physics
+                // states so frames that don't line up with the physics steps don't stutter
+                physics_interpolation.push(&physics_data);
+                physics_interpolation.advance(self.time.delta_time * physics_status.as_ref().map_or(1.0, |status| status.time_scale));
                 for (_key, renderizable) in &mut self.renderizable_instances {
-                    if let Some(physics_data) = physics_data.get(&_key.to_string()) {
-                        renderizable.instance.transform.position = physics_data.translation;
-                        renderizable.instance.transform.rotation = nalgebra::Unit::new_normalize(physics_data.rotation);
+                    if let Some((translation, rotation)) = physics_interpolation.sample(_key) {
+                        renderizable.instance.transform.position = translation;
+                        renderizable.instance.transform.rotation = rotation;
                     }
                 }
 
@@ -371,8 +466,11 @@ impl App {
                         event_pump: &mut event_pump,
                         controller: &mut controller,
                         input_subsystem: &input_subsystem,
-                        plane_control_tx: physics_data_channel.as_ref().map(|physics| &physics.plane_control_tx),
+                        command_tx: physics_data_channel.as_ref().map(|physics| &physics.command_tx),
+                        physics_tx: physics_data_channel.as_ref().map(|physics| &physics.request_data_tx),
                         physics_data: &physics_data,
+                        physics_events: &physics_events,
+                        world_changes: world_changes.as_ref(),
                         debug_physics: &debug_physics,
                     };
                     scene.tick(&mut self, &mut ctx);
@@ -385,10 +483,10 @@ impl App {
                 let camera_position = self.camera.camera.position.coords;
                 let mut model_instances: HashMap<String, Vec<InstanceRaw>> = HashMap::new();
 
-                for (_key, renderizable) in &self.renderizable_instances {
+                for renderizable in self.renderizable_instances.values() {
                     model_instances
                         .entry(renderizable.model_ref.clone())
-                        .or_insert_with(Vec::new)
+                        .or_default()
                         .push(renderizable.instance.transform.to_raw(camera_position));
                 }
 
@@ -407,17 +505,18 @@ impl App {
                     // consumed alongside camera-relative world positions in the shaders.
                     let relative_light_position = sun.instance.transform.position - camera_position;
                     self.light.uniform.position = (relative_light_position.x, relative_light_position.y, relative_light_position.z).into();
-                    match &sun.instance.metadata.lighting {
-                        Some(lighting_data) => {
-                            self.light.uniform.color = lighting_data.color.into();
-                        },
-                        None => {},
+                    if let Some(lighting_data) = &sun.instance.metadata.lighting {
+                        self.light.uniform.color = lighting_data.color.into();
                     }
                 }
 
                 self.renderer.queue.write_buffer(&self.light.rendering_data.buffer, 0, bytemuck::cast_slice(&[self.light.uniform]));
                 // lighting update
 
+                if let Some(terrain) = &mut self.terrain {
+                    terrain.update(&self.renderer.queue, camera_position);
+                }
+
                 self.camera.uniform.update_view_proj(&self.camera.camera, &self.camera.projection);
                 self.renderer.queue.write_buffer(&self.camera.buffer, 0, bytemuck::cast_slice(&[self.camera.uniform]));
                 self.renderer.queue.write_buffer(&self.renderer.depth_render.near_far_buffer, 0, bytemuck::cast_slice(&[self.renderer.depth_render.near_far_uniform]));
@@ -450,12 +549,12 @@ impl App {
     }
 
     fn open_first_avalible_joystick(joystick_subsystem: &JoystickSubsystem) -> Option<Joystick> {
-        for index in 0..joystick_subsystem.num_joysticks().unwrap() {
-            let joy = joystick_subsystem.open(index).unwrap();
-            println!("{}: {}", index, joy.name());
-            return Some(joy)
+        if joystick_subsystem.num_joysticks().unwrap() == 0 {
+            return None
         }
-        None
+        let joy = joystick_subsystem.open(0).unwrap();
+        println!("{}: {}", 0, joy.name());
+        Some(joy)
     }
 
     
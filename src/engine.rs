@@ -8,4 +8,5 @@ pub mod scene_manager;
 pub mod tooling;
 pub mod primitive;
 pub mod rendering;
+pub mod terrain;
 pub mod utils;
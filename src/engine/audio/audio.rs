@@ -1,31 +1,37 @@
-use std::{fs::File, io::BufReader, time::Duration};
+use std::{fs::File, io::BufReader};
 
-use fs_extra::file;
-use rodio::{source::SineWave, Decoder, OutputStream, OutputStreamHandle, Sink, Source};
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
 
 // To add: a way to load all audio once the game starts
 
 pub struct Audio {
-    stream: OutputStream,
-    stream_handle: OutputStreamHandle,
+    // Never read, but the sink only plays while they're alive
+    _stream: OutputStream,
+    _stream_handle: OutputStreamHandle,
     sink: Sink
     // mixer_context: Sdl2MixerContext,
 }
 
+impl Default for Audio {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Audio {
     pub fn new() -> Self {
         let (stream, stream_handle) = OutputStream::try_default().unwrap();
         let sink = Sink::try_new(&stream_handle).unwrap();
 
         Self {
-            stream,
-            stream_handle,
+            _stream: stream,
+            _stream_handle: stream_handle,
             sink
             
         }
     }
 
-    pub fn update(game_time: f64) {
+    pub fn update(_game_time: f64) {
         
     }
 
@@ -28,16 +28,19 @@ pub struct Subtitle {
     duration: f32,
 }
 
+impl Default for Subtitle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Subtitle {
     pub fn new() -> Self {
         Self { show_time: None, duration: 0.0 }
     }
 
     pub fn update(&mut self, app: &mut App) {
-        let visible = match &self.show_time {
-            Some(t) if t.elapsed().as_secs_f32() < self.duration => true,
-            _ => false,
-        };
+        let visible = matches!(&self.show_time, Some(t) if t.elapsed().as_secs_f32() < self.duration);
 
         let Some(node) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "subtitles") else { return };
         let UiNodeContent::Text(label) = &mut node.content else { return };
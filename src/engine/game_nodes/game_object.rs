@@ -24,18 +24,94 @@ pub struct Lighting {
 #[derive(Debug, Deserialize, Clone)]
 pub enum ColliderType {
     Cuboid { half_extents: (f32, f32, f32), #[serde(default)] position: (f32, f32, f32) },
-    Ball { radius: f32 },
-    Cylinder { half_height: f32, radius: f32 },
+    Ball { radius: f32, #[serde(default)] position: (f32, f32, f32) },
+    // Upright (along y)
+    Cylinder { half_height: f32, radius: f32, #[serde(default)] position: (f32, f32, f32) },
+    // Rows run along z and columns along x, spread over scale_x meters in x with square cells,
+    // scale_y multiplies the heights
     HeightField { heights: Vec<Vec<f32>>, scale_x: f32, scale_y: f32 },
     HalfSpace { normal: Vector3<f32> },
+    // Geometry read from the object's glTF model (scaled like the model), all of its meshes or
+    // only the node called `mesh`. TriMesh is exact but hollow, better kept for static bodies.
+    TriMesh { #[serde(default)] mesh: Option<String> },
+    ConvexHull { #[serde(default)] mesh: Option<String> },
+    // Any of the above as a trigger volume: nothing collides with it, things going in and out
+    // of it raise TriggerEntered/TriggerExited events named `trigger` (see PhysicsEvent)
+    Sensor { trigger: String, collider: Box<ColliderType> },
+    // Any of the above with a surface of its own instead of the body's (see Physics::material),
+    // a sensor takes it inside: Sensor(collider: Material(...))
+    Material { material: ColliderMaterial, collider: Box<ColliderType> },
 }
 
+impl ColliderType {
+    /// The surface this collider was given, None for the body's.
+    pub fn material(&self) -> Option<&ColliderMaterial> {
+        match self {
+            ColliderType::Material { material, .. } => Some(material),
+            ColliderType::Sensor { collider, .. } => collider.material(),
+            _ => None,
+        }
+    }
+}
+
+fn default_angular_damping() -> f32 { 2.0 }
+fn default_gravity_scale() -> f32 { 1.0 }
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct RigidBodyData {
     pub is_static: bool,
     pub mass: f32,
     pub center_of_mass: Vector3<f32>,
     pub initial_velocity: Vector3<f32>,
+    #[serde(default)]
+    pub initial_angular_velocity: Vector3<f32>, // rad/s, around the world axes
+    #[serde(default)]
+    pub linear_damping: f32,
+    #[serde(default = "default_angular_damping")]
+    pub angular_damping: f32,
+    // Continuous collision detection, for anything fast enough to skip over a thin collider in one step
+    #[serde(default)]
+    pub ccd: bool,
+    #[serde(default = "default_gravity_scale")]
+    pub gravity_scale: f32,
+}
+
+fn default_friction() -> f32 { 0.5 }
+fn default_density() -> f32 { 1.0 }
+fn default_collision_group() -> u32 { u32::MAX }
+
+// Bitmasks: two colliders touch when each one's memberships share a bit with the other's filter
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct CollisionGroups {
+    #[serde(default = "default_collision_group")]
+    pub memberships: u32,
+    #[serde(default = "default_collision_group")]
+    pub filter: u32,
+}
+
+impl Default for CollisionGroups {
+    fn default() -> Self {
+        Self { memberships: default_collision_group(), filter: default_collision_group() }
+    }
+}
+
+// Surface of a collider, rapier's defaults when left out
+#[derive(Debug, Deserialize, Clone)]
+pub struct ColliderMaterial {
+    #[serde(default = "default_friction")]
+    pub friction: f32,
+    #[serde(default)]
+    pub restitution: f32,
+    #[serde(default)]
+    pub collision_groups: CollisionGroups,
+    #[serde(default = "default_density")]
+    pub density: f32, // kg/m³ the collider adds on top of the rigidbody's mass, sensors add none
+}
+
+impl Default for ColliderMaterial {
+    fn default() -> Self {
+        Self { friction: default_friction(), restitution: 0.0, collision_groups: CollisionGroups::default(), density: default_density() }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -43,6 +119,9 @@ pub struct Physics {
     pub rigidbody: RigidBodyData,
     #[serde(default)]
     pub colliders: Vec<ColliderType>,
+    // For the colliders without a Material of their own
+    #[serde(default)]
+    pub material: Option<ColliderMaterial>,
 }
 
 fn default_camera_fov() -> f32 { 60.0 }
@@ -1,9 +1,21 @@
 use serde::Deserialize;
 use super::game_object::GameObject;
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::joints::JointDefinition;
+use crate::engine::physics::physics_handler::PhysicsSettings;
+use crate::engine::terrain::heightmap::TerrainSettings;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Scene {
     pub id: String,
     pub description: String,
     pub children: Vec<GameObject>,
+    #[serde(default)]
+    pub atmosphere: Atmosphere,
+    #[serde(default)]
+    pub physics: PhysicsSettings,
+    #[serde(default)]
+    pub joints: Vec<JointDefinition>,
+    #[serde(default)]
+    pub terrain: Option<TerrainSettings>,
 }
\ No newline at end of file
@@ -9,6 +9,12 @@ pub struct Timing {
     fps: f32
 }
 
+impl Default for Timing {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Timing {
     pub fn new() -> Self {
         Self {
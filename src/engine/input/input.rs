@@ -24,19 +24,19 @@ struct InputSettings {
 }
 
 ///    # Input Subsystem
-
+///
 ///    The input subsystem is a centralized way of handling input, based on this we will be able to be more
 ///    flexible in the way we define inputs, what should they do and how to access them, also allowing their use
 ///    on multi thread applications.
-
+///
 ///    Here we have this functions:
 ///    - Update() -> This will be called every frame to update the state of the input
 ///    - IsPressed() -> This will return true if the key is pressed
 ///    - IsJustPressed() -> This will return true if the key is just pressed
 ///    - IsReleased() -> This will return true if the key is released
-
+///
 ///    The last 3 functions check for a label created on the Input folder in the settings/input.ron file
-
+///
 ///    The structure of the input.ron file is:
 /// 
 ///    ```json
@@ -53,7 +53,6 @@ struct InputSettings {
 ///        ]
 ///    }
 ///    ```
-
 // TODO: Add a axis "method" this will let me add axis dfrom joysticks or take keyboard input and turn it into a value that goes from -1 to 1
 pub struct InputSubsystem {
     pub keys: HashMap<String, Pressable>,
@@ -1,12 +1,8 @@
-use crate::engine::input::pressable::Pressable;
-
 pub struct Mouse {
     x: i32,
     y: i32,
     rel_x: i32,
     rel_y: i32,
-    l_click: Pressable,
-    r_click: Pressable,
     x_sensitivity: f32,
     y_sensitivity: f32,
     raw_x: i32,
@@ -17,7 +13,6 @@ pub struct Mouse {
 /// Mouse
 /// Raw x and y are the raw values from the mouse, defining their position on the screen
 /// X and y are the values that are used for camera handling and other mouse based movements
-
 impl Mouse {
     pub fn new(x_sensitivity: f32, y_sensitivity: f32) -> Self {
         Self {
@@ -25,8 +20,6 @@ impl Mouse {
             rel_y: 0,
             x: 0,
             y: 0,
-            l_click: Pressable::new(None),
-            r_click: Pressable::new(None),
             x_sensitivity,
             y_sensitivity,
             raw_x: 0,
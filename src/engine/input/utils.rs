@@ -22,5 +22,5 @@ pub fn to_axis(minimal_input: bool, maximal_input: bool) -> f32 {
         return 1.0;
     }
 
-    return 0.0;
+    0.0
 }
\ No newline at end of file
@@ -1,3 +1,15 @@
 pub mod physics_resources;
 pub mod physics_handler;
 pub mod physics;
+pub mod atmosphere;
+pub mod wind;
+pub mod commands;
+pub mod interpolation;
+pub mod rewind;
+pub mod events;
+pub mod snapshot;
+pub mod joints;
+pub mod floating_origin;
+pub mod metadata;
+#[cfg(test)]
+pub mod test_world;
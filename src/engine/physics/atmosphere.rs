@@ -0,0 +1,144 @@
+use serde::Deserialize;
+
+use crate::engine::physics::wind::Wind;
+
+// International Standard Atmosphere (ISA) sea level values and constants
+pub const SEA_LEVEL_TEMPERATURE: f32 = 288.15; // K
+pub const SEA_LEVEL_PRESSURE: f32 = 101_325.0; // Pa
+pub const SEA_LEVEL_DENSITY: f32 = 1.225; // kg/m³
+const GAS_CONSTANT: f32 = 287.053; // J/(kg·K), dry air
+const HEAT_CAPACITY_RATIO: f32 = 1.4;
+const GRAVITY: f32 = 9.80665;
+
+/// ISA layers up to 32km: (base altitude in m, base temperature in K, lapse rate in K/m).
+/// Above the last base the stratosphere layer is simply extended.
+const LAYERS: [(f32, f32, f32); 3] = [
+    (0.0, 288.15, -0.0065),     // Troposphere
+    (11_000.0, 216.65, 0.0),    // Tropopause
+    (20_000.0, 216.65, 0.001),  // Stratosphere
+];
+
+/// The state of the air at a given altitude.
+#[derive(Debug, Clone, Copy)]
+pub struct AirData {
+    pub temperature: f32,    // K
+    pub pressure: f32,       // Pa
+    pub density: f32,        // kg/m³
+    pub speed_of_sound: f32, // m/s
+}
+
+impl AirData {
+    /// Density relative to ISA sea level, the usual way engine thrust lapse is expressed.
+    pub fn density_ratio(&self) -> f32 {
+        self.density / SEA_LEVEL_DENSITY
+    }
+
+    pub fn mach(&self, speed: f32) -> f32 {
+        speed / self.speed_of_sound
+    }
+}
+
+/// # Atmosphere
+///
+/// Air properties as a function of altitude following the International Standard
+/// Atmosphere, so lift, drag and thrust fall off as a plane climbs instead of every
+/// altitude flying like sea level.
+///
+/// ## Level file:
+/// A scene can declare a hotter or colder day than standard, the pressure profile is
+/// kept and only the temperature (and so density and speed of sound) is shifted:
+/// ```ron
+/// atmosphere: ( temperature_offset: 15.0 ), // ISA+15
+/// ```
+/// and the wind blowing through it (see `Wind`), calm air if not declared.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Atmosphere {
+    #[serde(default)]
+    pub temperature_offset: f32, // K (or °C) added to the ISA temperature
+    #[serde(default)]
+    pub wind: Wind,
+}
+
+impl Atmosphere {
+    pub fn sample(&self, altitude: f32) -> AirData {
+        let altitude = altitude.max(0.0);
+
+        // Walk the layers, carrying the pressure at each base up to the next one
+        let mut base_pressure = SEA_LEVEL_PRESSURE;
+        let mut layer = LAYERS[0];
+        for next in LAYERS.iter().skip(1) {
+            if altitude < next.0 {
+                break;
+            }
+            base_pressure = Self::layer_pressure(layer, base_pressure, next.0);
+            layer = *next;
+        }
+
+        let standard_temperature = layer.1 + layer.2 * (altitude - layer.0);
+        let pressure = Self::layer_pressure(layer, base_pressure, altitude);
+        let temperature = standard_temperature + self.temperature_offset;
+        let density = pressure / (GAS_CONSTANT * temperature);
+        let speed_of_sound = (HEAT_CAPACITY_RATIO * GAS_CONSTANT * temperature).sqrt();
+
+        AirData { temperature, pressure, density, speed_of_sound }
+    }
+
+    fn layer_pressure((base_altitude, base_temperature, lapse_rate): (f32, f32, f32), base_pressure: f32, altitude: f32) -> f32 {
+        let height = altitude - base_altitude;
+
+        if lapse_rate == 0.0 {
+            base_pressure * (-GRAVITY * height / (GAS_CONSTANT * base_temperature)).exp()
+        } else {
+            let temperature = base_temperature + lapse_rate * height;
+            base_pressure * (temperature / base_temperature).powf(-GRAVITY / (lapse_rate * GAS_CONSTANT))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(value: f32, expected: f32, tolerance: f32) -> bool {
+        (value - expected).abs() <= expected.abs() * tolerance
+    }
+
+    #[test]
+    fn matches_isa_tables() {
+        let atmosphere = Atmosphere::default();
+
+        // (geopotential altitude, temperature, pressure, density, speed of sound) from the ISA tables
+        let table = [
+            (0.0, 288.15, 101_325.0, 1.225, 340.29),
+            (6_000.0, 249.15, 47_181.0, 0.6601, 316.43),
+            (11_000.0, 216.65, 22_632.0, 0.3639, 295.07),
+            (15_000.0, 216.65, 12_045.0, 0.1937, 295.07),
+            (25_000.0, 221.65, 2_511.0, 0.03947, 298.45),
+        ];
+
+        for (altitude, temperature, pressure, density, speed_of_sound) in table {
+            let air = atmosphere.sample(altitude);
+            assert!(close(air.temperature, temperature, 0.001), "temperature at {}m: {}", altitude, air.temperature);
+            assert!(close(air.pressure, pressure, 0.005), "pressure at {}m: {}", altitude, air.pressure);
+            assert!(close(air.density, density, 0.005), "density at {}m: {}", altitude, air.density);
+            assert!(close(air.speed_of_sound, speed_of_sound, 0.001), "speed of sound at {}m: {}", altitude, air.speed_of_sound);
+        }
+    }
+
+    #[test]
+    fn temperature_offset_keeps_pressure_and_thins_the_air() {
+        let standard = Atmosphere::default().sample(3_000.0);
+        let hot_day = Atmosphere { temperature_offset: 20.0, ..Default::default() }.sample(3_000.0);
+
+        assert_eq!(hot_day.pressure, standard.pressure);
+        assert!(close(hot_day.temperature, standard.temperature + 20.0, 0.0001));
+        assert!(hot_day.density < standard.density);
+        assert!(hot_day.speed_of_sound > standard.speed_of_sound);
+    }
+
+    #[test]
+    fn below_sea_level_is_clamped() {
+        let air = Atmosphere::default().sample(-50.0);
+        assert_eq!(air.density, Atmosphere::default().sample(0.0).density);
+    }
+}
@@ -0,0 +1,143 @@
+use std::any::Any;
+use std::collections::HashMap;
+
+/// A payload addressed to one entity of the physics world (the key it has in
+/// `Physics::physics_elements`, e.g. "player"). The engine never looks inside the
+/// payload, any `'static + Send` type works - a plain struct like `PlaneControls`,
+/// or a `Box<dyn SomeTrait + Send>` when several kinds of command share one receiver.
+pub struct EntityCommand {
+    pub entity: String,
+    pub payload: Box<dyn Any + Send>,
+    pub state: bool, // only the newest one matters, see EntityCommand::state
+}
+
+impl EntityCommand {
+    pub fn new<T: Any + Send>(entity: impl Into<String>, payload: T) -> Self {
+        Self { entity: entity.into(), payload: Box::new(payload), state: false }
+    }
+
+    /// A command that's a state (like the controls) rather than something that happened:
+    /// it replaces whatever `T` the entity was sent before that no step took yet, so they
+    /// don't pile up while the physics is paused.
+    pub fn state<T: Any + Send>(entity: impl Into<String>, payload: T) -> Self {
+        Self { state: true, ..Self::new(entity, payload) }
+    }
+}
+
+/// # Command Queue
+///
+/// Commands that reached the physics thread since the last step, grouped by entity.
+/// Each `PhysicsTick` takes the ones for the entities it drives, by id and type.
+///
+/// ## Contents:
+/// - **Order**: commands for one entity keep the order they were sent in.
+/// - **States**: a command sent with `EntityCommand::state` replaces the older ones of its
+///   type for the entity, only the newest is kept.
+/// - **Types**: `take`/`latest` only remove payloads of the asked type, so several
+///   ticks can drive the same entity with different command types.
+/// - **Lifetime**: whatever no tick took is dropped once the step is done (see
+///   `clear`), a command is never delivered twice.
+#[derive(Default)]
+pub struct CommandQueue {
+    commands: HashMap<String, Vec<Box<dyn Any + Send>>>,
+}
+
+impl CommandQueue {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, command: EntityCommand) {
+        let payloads = self.commands.entry(command.entity).or_default();
+        if command.state {
+            let kind = (*command.payload).type_id();
+            payloads.retain(|payload| (**payload).type_id() != kind);
+        }
+        payloads.push(command.payload);
+    }
+
+    /// Removes and returns every `T` sent to `entity`, oldest first.
+    pub fn take<T: Any>(&mut self, entity: &str) -> Vec<T> {
+        let Some(payloads) = self.commands.get_mut(entity) else {
+            return Vec::new();
+        };
+
+        let mut taken = Vec::new();
+        let mut kept = Vec::new();
+        for payload in payloads.drain(..) {
+            match payload.downcast::<T>() {
+                Ok(command) => taken.push(*command),
+                Err(payload) => kept.push(payload),
+            }
+        }
+        *payloads = kept;
+
+        taken
+    }
+
+    /// Removes and returns every `T` sent to any entity, along with who it was for, e.g. for
+    /// a tick that takes on new entities as they're addressed (see MissileLaunch).
+    pub fn take_all<T: Any>(&mut self) -> Vec<(String, T)> {
+        let entities: Vec<String> = self.commands.keys().cloned().collect();
+        entities.into_iter()
+            .flat_map(|entity| self.take::<T>(&entity).into_iter().map(move |command| (entity.clone(), command)))
+            .collect()
+    }
+
+    /// Removes every `T` sent to `entity` and returns the newest one, for commands that
+    /// are a state (like the controls) where only the last one matters.
+    pub fn latest<T: Any>(&mut self, entity: &str) -> Option<T> {
+        self.take(entity).pop()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.values().all(|payloads| payloads.is_empty())
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_are_taken_by_entity_and_type() {
+        let mut queue = CommandQueue::new();
+        queue.push(EntityCommand::new("player", 1.0_f32));
+        queue.push(EntityCommand::new("player", "fire"));
+        queue.push(EntityCommand::new("player", 2.0_f32));
+        queue.push(EntityCommand::new("missile_1", 3.0_f32));
+
+        assert_eq!(queue.latest::<f32>("player"), Some(2.0));
+        assert_eq!(queue.latest::<f32>("player"), None);
+        assert_eq!(queue.take::<&str>("player"), vec!["fire"]);
+        assert_eq!(queue.take::<f32>("missile_1"), vec![3.0]);
+        assert!(queue.is_empty());
+
+        queue.push(EntityCommand::new("missile_1", "launch"));
+        queue.push(EntityCommand::new("missile_2", "launch"));
+        queue.push(EntityCommand::new("missile_2", 4.0_f32));
+        let mut launches = queue.take_all::<&str>();
+        launches.sort();
+        assert_eq!(launches, vec![("missile_1".to_string(), "launch"), ("missile_2".to_string(), "launch")]);
+        assert_eq!(queue.take::<f32>("missile_2"), vec![4.0]);
+    }
+
+    #[test]
+    fn states_keep_only_the_newest_until_taken() {
+        let mut queue = CommandQueue::new();
+        for throttle in 0..1000 {
+            queue.push(EntityCommand::state("player", throttle as f32));
+        }
+        queue.push(EntityCommand::new("player", "fire"));
+        queue.push(EntityCommand::new("player", "fire"));
+        queue.push(EntityCommand::state("missile_1", 0.5_f32));
+
+        assert_eq!(queue.take::<f32>("player"), vec![999.0]);
+        assert_eq!(queue.take::<&str>("player"), vec!["fire", "fire"]);
+        assert_eq!(queue.take::<f32>("missile_1"), vec![0.5]);
+    }
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use nalgebra::Vector3;
+use rapier3d::prelude::{ColliderHandle, ColliderSet, CollisionEvent, ContactPair, EventHandler, Real, RigidBodyHandle, RigidBodySet};
+
+use crate::engine::physics::floating_origin::FloatingOrigin;
+use crate::engine::physics::physics_handler::PhysicsData;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PhysicsEventKind {
+    CollisionStarted,
+    CollisionStopped,
+    ContactForce { force: f32 }, // N, sum of the force magnitudes over all contact points
+    TriggerEntered { trigger: String }, // the other entity went into a sensor collider called `trigger`
+    TriggerExited { trigger: String },
+    JointBroken { joint: String }, // pulled past its break force and removed, the entities are its two bodies
+}
+
+/// Something that happened between two colliders during a physics step, with the
+/// colliders already resolved to the entities (keys of `physics_elements`) they belong to.
+/// For trigger events the first entity is the one the sensor belongs to.
+#[derive(Debug, Clone)]
+pub struct PhysicsEvent {
+    pub kind: PhysicsEventKind,
+    pub entities: [Option<String>; 2], // None for colliders that aren't part of any level element
+    pub impact_speed: f32,             // m/s the two were closing at along the contact normal, 0 for ContactForce
+    pub contact_point: Option<Vector3<f32>>, // deepest contact in the level (the physics world for the ticks, see Physics::deliver_events), None once they're apart
+    pub time: f64,                     // simulated seconds of the step it happened in
+}
+
+impl PhysicsEvent {
+    pub fn involves(&self, entity: &str) -> bool {
+        self.entities.iter().any(|id| id.as_deref() == Some(entity))
+    }
+
+    /// The entity on the other side of the contact from `entity`.
+    pub fn other(&self, entity: &str) -> Option<&str> {
+        match &self.entities {
+            [Some(first), second] if first == entity => second.as_deref(),
+            [first, Some(second)] if second == entity => first.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Name of the trigger crossed, None for contacts between solid colliders.
+    pub fn trigger(&self) -> Option<&str> {
+        match &self.kind {
+            PhysicsEventKind::TriggerEntered { trigger } | PhysicsEventKind::TriggerExited { trigger } => Some(trigger),
+            _ => None,
+        }
+    }
+}
+
+struct RawEvent {
+    kind: PhysicsEventKind,
+    colliders: [ColliderHandle; 2],
+    impact_speed: f32,
+    contact_point: Option<Vector3<f32>>,
+}
+
+/// # Physics Event Collector
+///
+/// rapier's `EventHandler` for `Physics::step`: keeps the collision and contact force
+/// events raised during the step, `drain` then hands them out with entity ids instead
+/// of collider handles.
+///
+/// Only colliders built with `ActiveEvents` raise anything (the level loader turns them
+/// on, see physics_resources).
+///
+/// Triggers are per entity: a plane made of three colliders enters a sensor once, when
+/// the first of them goes in, and leaves it when the last one is out.
+#[derive(Default)]
+pub struct PhysicsEventCollector {
+    events: Mutex<Vec<RawEvent>>,
+    overlaps: HashMap<(ColliderHandle, Option<String>), usize>, // colliders of each entity inside each sensor
+}
+
+impl PhysicsEventCollector {
+    /// The events raised since the last drain, their contact points put in the level by `origin`.
+    pub fn drain(&mut self, time: f64, origin: &FloatingOrigin, collider_set: &ColliderSet, physics_elements: &HashMap<String, Option<PhysicsData>>) -> Vec<PhysicsEvent> {
+        let raw_events: Vec<RawEvent> = match self.events.lock() {
+            Ok(mut events) => events.drain(..).collect(),
+            Err(_) => return Vec::new(),
+        };
+        if raw_events.is_empty() {
+            return Vec::new();
+        }
+
+        let entities: HashMap<RigidBodyHandle, (&String, &PhysicsData)> = physics_elements.iter()
+            .filter_map(|(key, data)| data.as_ref().map(|data| (data.rigidbody_handle, (key, data))))
+            .collect();
+        let element_of = |handle: ColliderHandle| collider_set.get(handle)
+            .and_then(|collider| collider.parent())
+            .and_then(|parent| entities.get(&parent));
+        let entity_of = |handle: ColliderHandle| element_of(handle).map(|(key, _)| (*key).clone());
+        let trigger_of = |handle: ColliderHandle| element_of(handle).and_then(|(_, data)| data.triggers.get(&handle)).cloned();
+
+        raw_events.into_iter().filter_map(|event| {
+            let [first, second] = event.colliders;
+            let trigger = trigger_of(first).map(|trigger| (trigger, [first, second]))
+                .or_else(|| trigger_of(second).map(|trigger| (trigger, [second, first])));
+
+            let (kind, colliders) = match (event.kind, trigger) {
+                (PhysicsEventKind::CollisionStarted, Some((trigger, colliders))) => (PhysicsEventKind::TriggerEntered { trigger }, colliders),
+                (PhysicsEventKind::CollisionStopped, Some((trigger, colliders))) => (PhysicsEventKind::TriggerExited { trigger }, colliders),
+                (kind, _) => (kind, [first, second]),
+            };
+
+            let entities = [entity_of(colliders[0]), entity_of(colliders[1])];
+            let first_or_last = match &kind {
+                PhysicsEventKind::TriggerEntered { .. } => {
+                    let inside = self.overlaps.entry((colliders[0], entities[1].clone())).or_default();
+                    *inside += 1;
+                    *inside == 1
+                },
+                PhysicsEventKind::TriggerExited { .. } => {
+                    let inside = self.overlaps.entry((colliders[0], entities[1].clone())).or_default();
+                    *inside = inside.saturating_sub(1);
+                    *inside == 0
+                },
+                _ => true,
+            };
+
+            first_or_last.then_some(PhysicsEvent {
+                entities,
+                kind,
+                impact_speed: event.impact_speed,
+                contact_point: event.contact_point.map(|point| origin.to_world(&point)),
+                time,
+            })
+        }).collect()
+    }
+
+    fn push(&self, event: RawEvent) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event);
+        }
+    }
+
+    /// Deepest contact point of the pair and how fast the bodies close in along its normal.
+    fn contact(bodies: &RigidBodySet, colliders: &ColliderSet, contact_pair: &ContactPair) -> Option<(Vector3<f32>, f32)> {
+        let (manifold, contact) = contact_pair.find_deepest_contact()?;
+        let collider1 = colliders.get(contact_pair.collider1)?;
+        let point = collider1.position() * contact.local_p1;
+
+        let velocity_of = |body: Option<RigidBodyHandle>| body
+            .and_then(|handle| bodies.get(handle))
+            .map(|body| body.velocity_at_point(&point))
+            .unwrap_or_else(Vector3::zeros);
+        let relative_velocity = velocity_of(manifold.data.rigid_body1) - velocity_of(manifold.data.rigid_body2);
+
+        Some((point.coords, relative_velocity.dot(&manifold.data.normal).abs()))
+    }
+}
+
+impl EventHandler for PhysicsEventCollector {
+    fn handle_collision_event(&self, bodies: &RigidBodySet, colliders: &ColliderSet, event: CollisionEvent, contact_pair: Option<&ContactPair>) {
+        let kind = if event.started() { PhysicsEventKind::CollisionStarted } else { PhysicsEventKind::CollisionStopped };
+        let contact = contact_pair.and_then(|pair| Self::contact(bodies, colliders, pair));
+
+        self.push(RawEvent {
+            kind,
+            colliders: [event.collider1(), event.collider2()],
+            impact_speed: contact.map_or(0.0, |(_, speed)| speed),
+            contact_point: contact.map(|(point, _)| point),
+        });
+    }
+
+    // Raised after the solver, the bodies aren't closing in anymore: the force is what tells how hard it was
+    fn handle_contact_force_event(&self, _dt: Real, bodies: &RigidBodySet, colliders: &ColliderSet, contact_pair: &ContactPair, total_force_magnitude: Real) {
+        let contact = Self::contact(bodies, colliders, contact_pair);
+
+        self.push(RawEvent {
+            kind: PhysicsEventKind::ContactForce { force: total_force_magnitude },
+            colliders: [contact_pair.collider1, contact_pair.collider2],
+            impact_speed: 0.0,
+            contact_point: contact.map(|(point, _)| point),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::game_nodes::game_object::{ColliderType, RigidBodyData};
+    use crate::engine::physics::physics_handler::Physics;
+    use crate::engine::physics::test_world::{body, physics_object};
+
+    #[test]
+    fn flying_through_a_sensor_reports_entering_and_leaving_its_trigger() {
+        let mut physics = Physics::new();
+        let airspace = ColliderType::Sensor { trigger: "airspace".to_string(), collider: Box::new(ColliderType::Ball { radius: 100.0, position: (0.0, 0.0, 0.0) }) };
+        physics.spawn(&physics_object("tower", Vector3::new(0.0, 500.0, 0.0), body(true, Vector3::zeros(), false), airspace)).unwrap();
+        // Straight through it at 100m/s, 50m short of it
+        let ball = ColliderType::Ball { radius: 1.0, position: (0.0, 0.0, 0.0) };
+        physics.spawn(&physics_object("player", Vector3::new(0.0, 500.0, -150.0), RigidBodyData { gravity_scale: 0.0, ..body(false, Vector3::new(0.0, 0.0, 100.0), false) }, ball)).unwrap();
+
+        let mut triggers = Vec::new();
+        for _ in 0..(3.0 / physics.integration_parameters.dt) as usize {
+            physics.step();
+            triggers.extend(physics.events.iter().filter(|event| event.trigger().is_some()).cloned());
+        }
+
+        // Nothing collides with a sensor, only crossing it is reported
+        assert_eq!(triggers.len(), 2, "{:?}", triggers);
+        assert_eq!(triggers[0].kind, PhysicsEventKind::TriggerEntered { trigger: "airspace".to_string() });
+        assert_eq!(triggers[1].kind, PhysicsEventKind::TriggerExited { trigger: "airspace".to_string() });
+        assert_eq!(triggers[0].entities, [Some("tower".to_string()), Some("player".to_string())]);
+        assert!((0.4..0.6).contains(&triggers[0].time) && (2.4..2.6).contains(&triggers[1].time), "{:?}", triggers);
+    }
+}
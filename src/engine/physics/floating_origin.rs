@@ -0,0 +1,130 @@
+use nalgebra::{Point3, Vector3};
+use rapier3d::prelude::{ColliderSet, Isometry, RigidBodySet};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::physics::physics::DebugPhysicsMessageType;
+
+/// # Floating Origin
+///
+/// rapier keeps positions in f32, which 100km out only resolves about a centimeter: the
+/// wing forces land on a jittery grid and the plane shakes. The physics world is kept
+/// around the player instead, and `offset` is where its origin is in the level.
+///
+/// ## Contents:
+/// - **Rebasing**: once the followed entity strays `rebase_distance` from the origin along
+///   the ground, every body is moved back by its x and z (see `Physics::follow_origin`).
+///   Heights are never shifted, y stays the altitude for the atmosphere and the HUD.
+/// - **World coordinates**: everything leaving the physics thread (render messages, events,
+///   debug lines) goes through `to_world`, and the positions sent to it (teleports, spawns)
+///   through `to_local`, so the main thread never sees a rebase.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FloatingOrigin {
+    pub offset: Vector3<f64>, // level position of the physics world's origin, y is always 0
+}
+
+impl FloatingOrigin {
+    /// Level position of a point of the physics world.
+    pub fn to_world(&self, local: &Vector3<f32>) -> Vector3<f32> {
+        (local.cast::<f64>() + self.offset).cast()
+    }
+
+    /// Physics world position of a point of the level.
+    pub fn to_local(&self, world: &Vector3<f32>) -> Vector3<f32> {
+        (world.cast::<f64>() - self.offset).cast()
+    }
+
+    /// The same debug line or point in level coordinates.
+    pub fn debug_to_world(&self, message: &DebugPhysicsMessageType) -> DebugPhysicsMessageType {
+        match message {
+            DebugPhysicsMessageType::RenderizableLines([start, end]) => {
+                let mut line = [*start, *end];
+                for vertex in &mut line {
+                    vertex.position = self.to_world(&vertex.position.into()).into();
+                }
+                DebugPhysicsMessageType::RenderizableLines(line)
+            },
+            DebugPhysicsMessageType::RenderizablePoint(point) => DebugPhysicsMessageType::RenderizablePoint(Point3::from(self.to_world(&point.coords))),
+        }
+    }
+
+    /// How far the world has to move back to put `position` over the origin, None while it's
+    /// closer than `rebase_distance` (0 never rebases).
+    pub fn shift_for(position: &Vector3<f32>, rebase_distance: f32) -> Option<Vector3<f32>> {
+        let shift = Vector3::new(position.x, 0.0, position.z);
+        (rebase_distance > 0.0 && shift.norm() >= rebase_distance).then_some(shift)
+    }
+
+    /// Moves every rigidbody and the colliders on them by `-shift`, keeping their velocities
+    /// and whether they're asleep. The query pipeline has to be updated after this.
+    pub fn rebase(&mut self, shift: Vector3<f32>, rigidbody_set: &mut RigidBodySet, collider_set: &mut ColliderSet) {
+        for (_, rigidbody) in rigidbody_set.iter_mut() {
+            let position = rigidbody.position();
+            rigidbody.set_position(Isometry::from_parts((position.translation.vector - shift).into(), position.rotation), false);
+        }
+        rigidbody_set.propagate_modified_body_positions_to_colliders(collider_set);
+
+        // Colliders with no rigidbody don't follow any
+        for (_, collider) in collider_set.iter_mut().filter(|(_, collider)| collider.parent().is_none()) {
+            collider.set_translation(collider.translation() - shift);
+        }
+
+        self.offset += shift.cast::<f64>();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
+    use crate::engine::game_nodes::game_object::{ColliderType, RigidBodyData};
+    use crate::engine::physics::physics_handler::Physics;
+    use crate::engine::physics::test_world::{add_terrain, body, hill, physics_object};
+
+    #[test]
+    fn rebasing_moves_the_world_but_not_where_things_are_in_the_level() {
+        let mut rigidbody_set = RigidBodySet::new();
+        let mut collider_set = ColliderSet::new();
+        let player = rigidbody_set.insert(RigidBodyBuilder::dynamic().translation(Vector3::new(12000.0, 800.0, -9000.0)).linvel(Vector3::new(200.0, 0.0, 0.0)));
+        let attached = collider_set.insert_with_parent(ColliderBuilder::ball(1.0), player, &mut rigidbody_set);
+        let free = collider_set.insert(ColliderBuilder::ball(1.0).translation(Vector3::new(100.0, 5.0, 100.0)));
+        let mut origin = FloatingOrigin::default();
+
+        assert_eq!(FloatingOrigin::shift_for(&Vector3::new(6000.0, 20000.0, 0.0), 10000.0), None, "heights don't count");
+        assert_eq!(FloatingOrigin::shift_for(rigidbody_set[player].translation(), 0.0), None);
+        let shift = FloatingOrigin::shift_for(rigidbody_set[player].translation(), 10000.0).unwrap();
+        origin.rebase(shift, &mut rigidbody_set, &mut collider_set);
+
+        assert_eq!(*rigidbody_set[player].translation(), Vector3::new(0.0, 800.0, 0.0));
+        assert_eq!(*collider_set[attached].translation(), Vector3::new(0.0, 800.0, 0.0));
+        assert_eq!(rigidbody_set[player].linvel().x, 200.0);
+        assert_eq!(origin.to_world(collider_set[free].translation()), Vector3::new(100.0, 5.0, 100.0));
+        assert_eq!(origin.to_world(rigidbody_set[player].translation()), Vector3::new(12000.0, 800.0, -9000.0));
+        assert_eq!(origin.to_local(&Vector3::new(12000.0, 0.0, -9000.0)), Vector3::zeros());
+    }
+
+    #[test]
+    fn flying_away_from_the_origin_moves_the_world_back_under_the_player() {
+        let mut physics = Physics::new();
+        add_terrain(&mut physics, &hill());
+        // 100m short of the 10km rebase distance
+        let ball = ColliderType::Ball { radius: 1.0, position: (0.0, 0.0, 0.0) };
+        physics.spawn(&physics_object("player", Vector3::new(0.0, 2000.0, 9900.0), RigidBodyData { gravity_scale: 0.0, ..body(false, Vector3::new(0.0, 0.0, 200.0), false) }, ball)).unwrap();
+        let player = physics.physics_elements["player"].as_ref().unwrap().rigidbody_handle;
+
+        let mut last_position = physics.origin.to_world(physics.rigidbody_set[player].translation());
+        for _ in 0..(1.0 / physics.integration_parameters.dt) as usize {
+            physics.step();
+            // Where the player is in the level doesn't jump when the world is moved
+            let position = physics.origin.to_world(physics.rigidbody_set[player].translation());
+            assert!((position - last_position).norm() < 4.0, "jumped from {:?} to {:?}", last_position, position);
+            last_position = position;
+        }
+
+        let offset = physics.origin.offset;
+        assert!(offset.x == 0.0 && offset.y == 0.0 && offset.z > 10_000.0, "origin at {:?}", offset);
+        assert!(physics.rigidbody_set[player].translation().z.abs() < 200.0);
+        // The ground moved back with it
+        let terrain = physics.physics_elements["terrain"].as_ref().unwrap().rigidbody_handle;
+        assert_eq!(physics.rigidbody_set[terrain].translation().z, -offset.z as f32);
+    }
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::engine::physics::physics_handler::RenderMessage;
+
+#[derive(Debug, Clone, Copy)]
+struct BodyState {
+    translation: Vector3<f32>,
+    rotation: UnitQuaternion<f32>,
+}
+
+/// # Physics Interpolation
+///
+/// The render side of the fixed step: frames and physics steps don't line up, so drawing
+/// the newest state as is makes the models (and the camera following them) stutter.
+/// Instead the last two states received are kept and blended at a render clock that runs
+/// one update behind the physics.
+///
+/// ## Contents:
+/// - **push**: a new batch of `RenderMessage`s, ignored if it has the same `time` as the last
+///   one. An older one (the physics was rewound) is jumped to instead of blended.
+/// - **advance**: moves the render clock by the frame's delta time (times the physics time scale),
+///   kept between the two states so it catches up on its own after a hitch and stops when the
+///   physics is paused.
+/// - **sample**: the blended translation and rotation of one element.
+#[derive(Default)]
+pub struct PhysicsInterpolation {
+    previous: HashMap<String, BodyState>,
+    current: HashMap<String, BodyState>,
+    previous_time: f64,
+    current_time: f64,
+    render_time: f64,
+}
+
+impl PhysicsInterpolation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, physics_data: &HashMap<String, RenderMessage>) {
+        let Some(time) = physics_data.values().map(|message| message.time).reduce(f64::max) else {
+            return;
+        };
+        if !self.current.is_empty() && time == self.current_time {
+            return;
+        }
+
+        let states = physics_data.iter().map(|(key, message)| (key.clone(), BodyState {
+            translation: message.translation,
+            rotation: UnitQuaternion::new_normalize(message.rotation),
+        })).collect();
+
+        if self.current.is_empty() || time < self.current_time {
+            // First state (or the physics was rewound), nothing to blend from
+            self.previous = states;
+            self.current = self.previous.clone();
+            self.previous_time = time;
+            self.render_time = time;
+        } else {
+            self.previous = std::mem::replace(&mut self.current, states);
+            self.previous_time = self.current_time;
+        }
+        self.current_time = time;
+    }
+
+    pub fn advance(&mut self, delta_time: f32) {
+        self.render_time = (self.render_time + delta_time as f64).clamp(self.previous_time, self.current_time);
+    }
+
+    /// How far the render clock is from the previous state (0) to the current one (1).
+    pub fn alpha(&self) -> f32 {
+        let span = self.current_time - self.previous_time;
+        if span > 0.0 { ((self.render_time - self.previous_time) / span) as f32 } else { 1.0 }
+    }
+
+    pub fn sample(&self, key: &str) -> Option<(Vector3<f32>, UnitQuaternion<f32>)> {
+        let current = self.current.get(key)?;
+        let previous = self.previous.get(key).unwrap_or(current);
+        let alpha = self.alpha();
+
+        Some((
+            previous.translation.lerp(&current.translation, alpha),
+            previous.rotation.slerp(&current.rotation, alpha),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::physics::metadata::Metadata;
+
+    fn message(time: f64, x: f32) -> HashMap<String, RenderMessage> {
+        HashMap::from([("player".to_string(), RenderMessage {
+            time,
+            translation: Vector3::new(x, 0.0, 0.0),
+            rotation: UnitQuaternion::identity().into_inner(),
+            linvel: Vector3::zeros(),
+            metadata: Metadata::new(),
+        })])
+    }
+
+    #[test]
+    fn blends_between_the_last_two_states() {
+        let mut interpolation = PhysicsInterpolation::new();
+        interpolation.push(&message(1.0, 0.0));
+        interpolation.push(&message(1.1, 10.0));
+
+        interpolation.advance(0.05);
+        let (translation, _) = interpolation.sample("player").unwrap();
+        assert!((translation.x - 5.0).abs() < 1.0e-3);
+
+        // Never runs past the newest state
+        interpolation.advance(1.0);
+        assert_eq!(interpolation.sample("player").unwrap().0.x, 10.0);
+
+        // A rewound state is jumped to, not blended towards
+        interpolation.push(&message(0.5, -10.0));
+        assert_eq!(interpolation.sample("player").unwrap().0.x, -10.0);
+    }
+}
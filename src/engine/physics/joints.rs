@@ -0,0 +1,247 @@
+use nalgebra::{Point3, Unit, Vector3};
+use rapier3d::prelude::{FixedJointBuilder, GenericJoint, ImpulseJoint, ImpulseJointHandle, IntegrationParameters, PrismaticJointBuilder, RevoluteJointBuilder, RigidBodySet, RopeJointBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::physics::physics::DebugPhysicsMessageType;
+use crate::engine::primitive::manual_vertex::ManualVertex;
+
+// Colors of the lines drawn between the two anchors of every joint
+const JOINT_COLOR: [f32; 3] = [1.0, 0.8, 0.0];
+const STRAINED_JOINT_COLOR: [f32; 3] = [1.0, 0.2, 0.0];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum JointKind {
+    // Welds the two bodies together, e.g. an external store until it's released
+    Fixed,
+    // Hinge around `axis` (body1's local space), `limits` in degrees
+    Revolute { axis: Vector3<f32>, #[serde(default)] limits: Option<(f32, f32)> },
+    // Slides along `axis` (body1's local space), `limits` in meters
+    Prismatic { axis: Vector3<f32>, #[serde(default)] limits: Option<(f32, f32)> },
+    // Keeps the anchors at most `length` meters apart and nothing else, a cable or a tow line
+    Rope { length: f32 },
+}
+
+impl JointKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            JointKind::Fixed => "Fixed",
+            JointKind::Revolute { .. } => "Revolute",
+            JointKind::Prismatic { .. } => "Prismatic",
+            JointKind::Rope { .. } => "Rope",
+        }
+    }
+}
+
+/// A joint between two objects of the level, from the scene's `joints` list. The anchors
+/// are in meters in each body's own space.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JointDefinition {
+    pub id: String,
+    pub body1: String,
+    pub body2: String,
+    #[serde(default)]
+    pub anchor1: Vector3<f32>,
+    #[serde(default)]
+    pub anchor2: Vector3<f32>,
+    pub kind: JointKind,
+    // N the joint can take before it comes apart (a cable snapping, a store shearing off), None never
+    #[serde(default)]
+    pub break_force: Option<f32>,
+}
+
+/// A joint living in the world's `ImpulseJointSet`, under its id in `Physics::joints`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JointData {
+    pub handle: ImpulseJointHandle,
+    pub body1: String,
+    pub body2: String,
+    pub kind: JointKind,
+    pub break_force: Option<f32>,
+}
+
+/// How a joint is doing, for the debug console.
+#[derive(Debug, Clone)]
+pub struct JointStatus {
+    pub id: String,
+    pub kind: &'static str,
+    pub position: f32, // degrees for Revolute, meters for the rest (along the axis for Prismatic, between the anchors otherwise)
+    pub force: f32,    // N holding the bodies together last step
+    pub break_force: Option<f32>,
+}
+
+/// rapier's joint for a definition, checked so a typo in the level doesn't turn into a
+/// joint the solver can't work with.
+pub fn build_joint(definition: &JointDefinition) -> Result<GenericJoint, String> {
+    if definition.body1 == definition.body2 {
+        return Err(format!("Joint '{}' attaches '{}' to itself", definition.id, definition.body1));
+    }
+    if let Some(break_force) = definition.break_force {
+        if break_force <= 0.0 {
+            return Err(format!("Joint '{}' break force must be positive, got {}", definition.id, break_force));
+        }
+    }
+
+    let axis = |axis: &Vector3<f32>| Unit::try_new(*axis, 1.0e-6).ok_or_else(|| format!("Joint '{}' axis can't be zero", definition.id));
+    let limits = |limits: &Option<(f32, f32)>| match limits {
+        Some((min, max)) if min > max => Err(format!("Joint '{}' limits go from {} to {}", definition.id, min, max)),
+        _ => Ok(*limits),
+    };
+    let anchor1 = Point3::from(definition.anchor1);
+    let anchor2 = Point3::from(definition.anchor2);
+
+    let joint: GenericJoint = match &definition.kind {
+        JointKind::Fixed => FixedJointBuilder::new().local_anchor1(anchor1).local_anchor2(anchor2).into(),
+        JointKind::Revolute { axis: revolute_axis, limits: revolute_limits } => {
+            let mut builder = RevoluteJointBuilder::new(axis(revolute_axis)?).local_anchor1(anchor1).local_anchor2(anchor2);
+            if let Some((min, max)) = limits(revolute_limits)? {
+                builder = builder.limits([min.to_radians(), max.to_radians()]);
+            }
+            builder.into()
+        },
+        JointKind::Prismatic { axis: prismatic_axis, limits: prismatic_limits } => {
+            let mut builder = PrismaticJointBuilder::new(axis(prismatic_axis)?).local_anchor1(anchor1).local_anchor2(anchor2);
+            if let Some((min, max)) = limits(prismatic_limits)? {
+                builder = builder.limits([min, max]);
+            }
+            builder.into()
+        },
+        JointKind::Rope { length } => {
+            if *length <= 0.0 {
+                return Err(format!("Rope '{}' length must be positive, got {}", definition.id, length));
+            }
+            RopeJointBuilder::new(*length).local_anchor1(anchor1).local_anchor2(anchor2).into()
+        },
+    };
+
+    Ok(joint)
+}
+
+// World position of both anchors
+fn anchors(joint: &ImpulseJoint, rigidbody_set: &RigidBodySet) -> Option<(Point3<f32>, Point3<f32>)> {
+    let body1 = rigidbody_set.get(joint.body1)?;
+    let body2 = rigidbody_set.get(joint.body2)?;
+    Some((body1.position() * joint.data.local_anchor1(), body2.position() * joint.data.local_anchor2()))
+}
+
+/// Force (N) the joint put into its bodies during the last step.
+pub fn joint_force(joint: &ImpulseJoint, integration_parameters: &IntegrationParameters) -> f32 {
+    // The impulses are those of the solver's last substep
+    let delta_time = integration_parameters.dt / integration_parameters.num_solver_iterations.get() as f32;
+    if delta_time <= 0.0 {
+        return 0.0;
+    }
+    // Locked axes and limits are solved apart (a rope is only a distance limit), the push is both
+    let linear = |axis: usize| joint.impulses[axis] + joint.data.limits[axis].impulse;
+    Vector3::new(linear(0), linear(1), linear(2)).norm() / delta_time
+}
+
+pub fn joint_status(id: &str, joint_data: &JointData, joint: &ImpulseJoint, rigidbody_set: &RigidBodySet, integration_parameters: &IntegrationParameters) -> JointStatus {
+    let position = match (&joint_data.kind, anchors(joint, rigidbody_set)) {
+        (JointKind::Revolute { .. }, _) => {
+            match (joint.data.as_revolute(), rigidbody_set.get(joint.body1), rigidbody_set.get(joint.body2)) {
+                (Some(revolute), Some(body1), Some(body2)) => revolute.angle(body1.rotation(), body2.rotation()).to_degrees(),
+                _ => 0.0,
+            }
+        },
+        (JointKind::Prismatic { .. }, Some((anchor1, anchor2))) => match rigidbody_set.get(joint.body1) {
+            Some(body1) => (anchor2 - anchor1).dot(&(body1.rotation() * joint.data.local_axis1())),
+            None => 0.0,
+        },
+        (_, Some((anchor1, anchor2))) => (anchor2 - anchor1).norm(),
+        (_, None) => 0.0,
+    };
+
+    JointStatus {
+        id: id.to_string(),
+        kind: joint_data.kind.label(),
+        position,
+        force: joint_force(joint, integration_parameters),
+        break_force: joint_data.break_force,
+    }
+}
+
+/// A line between the anchors, going red as the joint nears its break force.
+pub fn joint_debug_line(joint_data: &JointData, joint: &ImpulseJoint, rigidbody_set: &RigidBodySet, integration_parameters: &IntegrationParameters) -> Option<DebugPhysicsMessageType> {
+    let (anchor1, anchor2) = anchors(joint, rigidbody_set)?;
+    let strained = joint_data.break_force.is_some_and(|break_force| joint_force(joint, integration_parameters) > break_force * 0.75);
+    let color = if strained { STRAINED_JOINT_COLOR } else { JOINT_COLOR };
+
+    Some(DebugPhysicsMessageType::RenderizableLines([
+        ManualVertex { position: anchor1.into(), color },
+        ManualVertex { position: anchor2.into(), color },
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::game_nodes::game_object::{ColliderType, RigidBodyData};
+    use crate::engine::physics::events::{PhysicsEvent, PhysicsEventKind};
+    use crate::engine::physics::physics_handler::Physics;
+    use crate::engine::physics::test_world::{body, physics_object};
+
+    #[test]
+    fn invalid_joints_are_load_errors() {
+        let hinge = JointDefinition {
+            id: "hook".to_string(),
+            body1: "player".to_string(),
+            body2: "tailhook".to_string(),
+            anchor1: Vector3::new(0.0, -1.0, -7.0),
+            anchor2: Vector3::zeros(),
+            kind: JointKind::Revolute { axis: Vector3::x(), limits: Some((-60.0, 0.0)) },
+            break_force: None,
+        };
+
+        assert!(build_joint(&hinge).is_ok());
+        assert!(build_joint(&JointDefinition { body2: "player".to_string(), ..hinge.clone() }).is_err());
+        assert!(build_joint(&JointDefinition { kind: JointKind::Revolute { axis: Vector3::zeros(), limits: None }, ..hinge.clone() }).is_err());
+        assert!(build_joint(&JointDefinition { kind: JointKind::Prismatic { axis: Vector3::y(), limits: Some((1.0, -1.0)) }, ..hinge.clone() }).is_err());
+        assert!(build_joint(&JointDefinition { kind: JointKind::Rope { length: 0.0 }, ..hinge.clone() }).is_err());
+        assert!(build_joint(&JointDefinition { break_force: Some(-1.0), ..hinge }).is_err());
+    }
+
+    #[test]
+    fn a_rope_holds_its_load_until_it_breaks() {
+        let mut physics = Physics::new();
+        let hook = ColliderType::Ball { radius: 0.5, position: (0.0, 0.0, 0.0) };
+        physics.spawn(&physics_object("crane", Vector3::new(0.0, 500.0, 1000.0), body(true, Vector3::zeros(), false), hook.clone())).unwrap();
+        physics.spawn(&physics_object("load", Vector3::new(3.0, 495.0, 1000.0), RigidBodyData { linear_damping: 1.0, ..body(false, Vector3::zeros(), false) }, hook.clone())).unwrap();
+        let rope = |id: &str, break_force: Option<f32>| JointDefinition {
+            id: id.to_string(),
+            body1: "crane".to_string(),
+            body2: "load".to_string(),
+            anchor1: Vector3::zeros(),
+            anchor2: Vector3::zeros(),
+            kind: JointKind::Rope { length: 10.0 },
+            break_force,
+        };
+
+        physics.add_joints(&[rope("cable", None)]).unwrap();
+        assert!(physics.add_joints(&[rope("cable", None)]).is_err(), "ids are unique");
+        assert!(physics.add_joints(&[JointDefinition { body2: "nothing".to_string(), ..rope("other", None) }]).is_err());
+        for _ in 0..(5.0 / physics.integration_parameters.dt) as usize {
+            physics.step();
+        }
+
+        // Hanging still at the end of the rope, holding up its 100kg
+        let status = &physics.joint_statuses()[0];
+        assert!((status.position - 10.0).abs() < 0.1, "{}m of rope", status.position);
+        assert!((status.force - 981.0).abs() < 50.0, "{}N", status.force);
+
+        // A second load hung right at the end of a cable too weak for it
+        physics.spawn(&physics_object("heavy_load", Vector3::new(0.0, 490.0, 1000.0), body(false, Vector3::zeros(), false), hook)).unwrap();
+        physics.add_joints(&[JointDefinition { body2: "heavy_load".to_string(), ..rope("weak_cable", Some(500.0)) }]).unwrap();
+        let mut broken: Vec<PhysicsEvent> = Vec::new();
+        for _ in 0..10 {
+            physics.step();
+            broken.extend(physics.events.iter().filter(|event| matches!(event.kind, PhysicsEventKind::JointBroken { .. })).cloned());
+        }
+        assert_eq!(broken.len(), 1, "the 500N cable snaps, the other holds");
+        assert_eq!(broken[0].kind, PhysicsEventKind::JointBroken { joint: "weak_cable".to_string() });
+        assert!(broken[0].involves("heavy_load"));
+        assert!(!physics.joints.contains_key("weak_cable"));
+
+        physics.despawn("load").unwrap();
+        assert!(physics.joints.is_empty(), "the cable goes with the load");
+    }
+}
@@ -0,0 +1,68 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// # Metadata
+///
+/// What the ticks report about an entity besides its pose (engine state, debug shapes...),
+/// sent to the main thread with its `RenderMessage`. Like `EntityCommand` the engine never
+/// looks inside a value, any `'static + Send + Sync` type works and is read back by type.
+///
+/// ## Contents:
+/// - **Keys**: `<namespace>/<name>`, e.g. "plane/engine". Each tick writes under a namespace
+///   of its own, so several can report on the same entity.
+/// - **Lifetime**: a value stays until it's replaced or its namespace is cleared (see
+///   `clear_namespace`), e.g. a tick that only reports something while debugging clears
+///   its namespace before reporting again.
+/// - **Cloning**: the values are shared, sending the metadata every frame copies no data.
+#[derive(Clone, Default)]
+pub struct Metadata {
+    values: HashMap<String, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Metadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert<T: Any + Send + Sync>(&mut self, key: impl Into<String>, value: T) {
+        self.values.insert(key.into(), Arc::new(value));
+    }
+
+    /// The value under `key`, None if there isn't one or it isn't a `T`.
+    pub fn get<T: Any>(&self, key: &str) -> Option<&T> {
+        self.values.get(key)?.downcast_ref::<T>()
+    }
+
+    /// Removes every value under `<namespace>/`.
+    pub fn clear_namespace(&mut self, namespace: &str) {
+        let prefix = format!("{}/", namespace);
+        self.values.retain(|key, _| !key.starts_with(&prefix));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_are_read_back_by_key_and_type() {
+        let mut metadata = Metadata::new();
+        metadata.insert("plane/engine", 0.5_f32);
+        metadata.insert("plane/stall", vec![true, false]);
+        metadata.insert("gun/status", 511_u32);
+
+        assert_eq!(metadata.get::<f32>("plane/engine"), Some(&0.5));
+        assert_eq!(metadata.get::<u32>("plane/engine"), None);
+        assert_eq!(metadata.get::<Vec<bool>>("plane/stall"), Some(&vec![true, false]));
+
+        // Clearing one namespace leaves the others alone
+        metadata.clear_namespace("plane");
+        assert_eq!(metadata.get::<f32>("plane/engine"), None);
+        assert_eq!(metadata.get::<u32>("gun/status"), Some(&511));
+    }
+}
@@ -7,9 +7,10 @@ use nalgebra::Point3;
 
 use crate::engine::rendering::ui::physics_rendering::RenderPhysics;
 use crate::engine::rendering::camera::CameraRenderizable;
-use crate::engine::physics::physics_handler::{Physics, RenderMessage, PhysicsCommand, PhysicsTick};
-use crate::engine::physics::physics_resources::load_physics_from_level;
-use crate::game::play::plane::plane::PlaneControls;
+use crate::engine::physics::physics_handler::{Physics, RenderMessage, PhysicsCommand, PhysicsStatus, PhysicsTick, WorldChanges};
+use crate::engine::physics::physics_resources::{load_atmosphere_from_level, load_joints_from_level, load_physics_from_level, load_physics_settings_from_level};
+use crate::engine::physics::commands::EntityCommand;
+use crate::engine::physics::events::PhysicsEvent;
 use crate::engine::primitive::manual_vertex::ManualVertex;
 
 #[derive(Clone)]
@@ -21,8 +22,11 @@ pub enum DebugPhysicsMessageType {
 pub struct PhysicsDataTransmission {
     pub physics_data_rx: Receiver<HashMap<String, RenderMessage>>,
     pub request_data_tx: Sender<PhysicsCommand>,
-    pub plane_control_tx: Sender<PlaneControls>,
+    pub command_tx: Sender<EntityCommand>,
     pub debug_physics_rx: Receiver<Vec<DebugPhysicsMessageType>>,
+    pub status_rx: Receiver<PhysicsStatus>,
+    pub events_rx: Receiver<Vec<PhysicsEvent>>,
+    pub world_rx: Receiver<WorldChanges>,
 }
 
 // Always starts the physics thread - callers only call this when a scene's
@@ -32,11 +36,14 @@ pub fn physics_handling(device: &Device, config: &SurfaceConfiguration, camera:
     let (physics_data_tx, physics_data_rx) = channel::<HashMap<String, RenderMessage>>();
     let (request_data_tx, request_data_rx) = channel::<PhysicsCommand>();
 
-    let (plane_control_tx, plane_control_rx) = channel::<PlaneControls>();
+    let (command_tx, command_rx) = channel::<EntityCommand>();
     
     let (debug_physics_tx, debug_physics_rx) = channel::<Vec<DebugPhysicsMessageType>>();
+    let (status_tx, status_rx) = channel::<PhysicsStatus>();
+    let (events_tx, events_rx) = channel::<Vec<PhysicsEvent>>();
+    let (world_tx, world_rx) = channel::<WorldChanges>();
 
-    let render_physics = RenderPhysics::new(&device, &config, &camera);
+    let _render_physics = RenderPhysics::new(device, config, camera);
 
     thread::spawn(move || {
         // Set a custom panic hook to print detailed error info
@@ -54,15 +61,25 @@ pub fn physics_handling(device: &Device, config: &SurfaceConfiguration, camera:
         }));
 
         let mut physics = Physics::new();
-        load_physics_from_level(level_path, &mut physics.collider_set, &mut physics.rigidbody_set, &mut physics.physics_elements);
-        physics.physics_thread(physics_data_tx, request_data_rx, plane_control_rx, debug_physics_tx, physics_tick);
+        physics.apply_settings(&load_physics_settings_from_level(level_path.clone()));
+        physics.atmosphere = load_atmosphere_from_level(level_path.clone());
+        if let Err(error) = load_physics_from_level(level_path.clone(), &mut physics.collider_set, &mut physics.rigidbody_set, &mut physics.physics_elements) {
+            eprintln!("Error loading the level physics:\n{}", error);
+        }
+        if let Err(error) = physics.add_joints(&load_joints_from_level(level_path)) {
+            eprintln!("Error loading the level joints:\n{}", error);
+        }
+        physics.physics_thread(physics_data_tx, request_data_rx, command_rx, debug_physics_tx, status_tx, events_tx, world_tx, physics_tick);
     });
 
-    return PhysicsDataTransmission {
+    PhysicsDataTransmission {
         physics_data_rx, // Physics data for representation
         request_data_tx, // Transmisor to requesat data from the physics thread
-        plane_control_tx, // Transmisor to send plane controls to the physics thread
+        command_tx, // Transmisor to send entity commands (e.g. plane controls) to the physics thread
         debug_physics_rx, // Receiver to receive debug physics messages
-    };
+        status_rx, // Receiver for the physics thread state (pause, time scale...)
+        events_rx, // Receiver for the collisions since the last request
+        world_rx, // Receiver for the entities a loaded snapshot changed
+    }
 }
 
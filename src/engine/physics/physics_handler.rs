@@ -1,48 +1,28 @@
 use rapier3d::prelude::{CCDSolver, ColliderSet, CollisionPipeline, DefaultBroadPhase, ImpulseJointSet, IntegrationParameters, IslandManager, MultibodyJointSet, NarrowPhase, PhysicsPipeline, QueryPipeline, RigidBodySet};
-use nalgebra:: {Quaternion, Vector3};
+use nalgebra:: {Quaternion, UnitQuaternion, Vector3};
 use std::collections::HashMap;
-use rapier3d::prelude::{ColliderHandle, RigidBodyHandle};
+use rapier3d::prelude::{ColliderHandle, Isometry, RigidBodyHandle};
 use std::sync::mpsc::{Sender, Receiver};
-use std::thread;
 use std::time::{Duration, Instant};
-use crate::game::play::plane::plane::{Plane, PlaneControls};
-use crate::game::play::plane::physics::wheels::wheel::WheelData;
 use serde::{Deserialize, Serialize};
 use crate::engine::physics::physics::DebugPhysicsMessageType;
-
-#[derive(Debug, Clone)]
-pub struct ColliderDebugData {
-    pub half_extents: Vector3<f32>,
-    pub local_offset: Vector3<f32>,
-}
-
-#[derive(Debug, Clone)]
-pub struct WingDebugData {
-    pub pressure_center: Vector3<f32>,
-    pub last_lift_force: Vector3<f32>,
-}
-
-#[derive(Debug, Clone)]
-pub struct SuspensionDebugData {
-    pub local_origin: Vector3<f32>,
-    pub local_wheel: Vector3<f32>,
-}
-
-#[derive(Debug, Clone)]
-pub enum MetadataType {
-    Translation(Vector3<f32>),
-    Rotation(Quaternion<f32>),
-    Wheels(HashMap<String, WheelData>),
-    Colliders(Vec<ColliderDebugData>),
-    Wings(Vec<WingDebugData>),
-    Suspensions(Vec<SuspensionDebugData>),
-}
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::commands::{CommandQueue, EntityCommand};
+use crate::engine::physics::metadata::Metadata;
+use crate::engine::physics::rewind::RewindBuffer;
+use crate::engine::physics::floating_origin::FloatingOrigin;
+use crate::engine::physics::snapshot::PhysicsSnapshot;
+use crate::engine::physics::events::{PhysicsEvent, PhysicsEventCollector, PhysicsEventKind};
+use crate::engine::physics::joints::{build_joint, joint_debug_line, joint_force, joint_status, JointData, JointDefinition, JointStatus};
+use crate::engine::physics::physics_resources::build_physics_element;
+use crate::engine::game_nodes::game_object::GameObject;
 
 pub struct RenderMessage {
-    pub translation: Vector3<f32>,
+    pub time: f64, // simulated seconds the state is from, see PhysicsInterpolation
+    pub translation: Vector3<f32>, // in the level, not the physics world (see FloatingOrigin)
     pub rotation: Quaternion<f32>,
     pub linvel: Vector3<f32>,
-    pub metadata: HashMap<String, MetadataType>
+    pub metadata: Metadata,
 }
 
 #[derive(Debug)]
@@ -51,26 +31,66 @@ pub enum PhysicsCommand {
     Shutdown,         // Main thread signals shutdown
     ToggleDebug,      // Toggle debug rendering
     TogglePause,      // Toggle physics pause
+    SetTimeScale(f32), // Simulated seconds per real second (slow motion below 1, fast forward above)
+    StepOnce,         // Advance a single fixed step, only while paused
+    Rewind(f32),      // Put the rigidbodies back this many seconds (see RewindBuffer)
+    Spawn(Box<GameObject>), // Add a rigidbody and its colliders to the running world (see Physics::spawn), placed in the level
+    Despawn(String),  // Remove an entity and everything attached to it
+    Teleport { entity: String, position: Vector3<f32>, rotation: UnitQuaternion<f32>, linvel: Vector3<f32> }, // position in the level
+    SaveSnapshot(String), // Write the whole world and the ticks' state to this file (see PhysicsSnapshot)
+    LoadSnapshot(String), // Replace the world with the one saved in this file
+}
+
+/// Entities a physics snapshot brought in and took out when it replaced the world (see
+/// PhysicsCommand::LoadSnapshot), for the main thread to draw the same ones.
+#[derive(Debug, Clone, Default)]
+pub struct WorldChanges {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// What the physics thread is doing, sent along with every batch of render messages
+/// for the debug console.
+#[derive(Debug, Clone)]
+pub struct PhysicsStatus {
+    pub time: f64,            // simulated seconds
+    pub paused: bool,
+    pub time_scale: f32,
+    pub rewind_available: f32, // seconds that can be rewound
+    pub joints: Vec<JointStatus>,
+    pub origin: Vector3<f64>, // level position of the physics world's origin, see FloatingOrigin
 }
 
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PhysicsData {
     pub rigidbody_handle: RigidBodyHandle,
     pub collider_handles: Vec<ColliderHandle>,
-    pub metadata: HashMap<String, MetadataType>
+    pub triggers: HashMap<ColliderHandle, String>, // sensor colliders and the trigger they raise events as
+    #[serde(skip)] // rebuilt by the ticks every step
+    pub metadata: Metadata,
 }
 
 /// Per-tick physics behavior, supplied by whichever scene wants physics (see
 /// Scene::physics) - this generic engine module doesn't know or care what kind of
 /// gameplay object is being simulated, only that something implements this.
+/// Each implementation picks the entities it drives out of `physics_elements` and
+/// their commands out of `commands` by id, so several of them can share one world
+/// (see PhysicsTickGroup). The colliders are theirs to change as well, e.g. to disable
+/// the one of a part that broke off. `time` is the world's clock (Physics::time) at the
+/// start of the step, the one thing every tick goes by so they can't drift apart or from
+/// the world after a rewind or a load.
 pub trait PhysicsTick {
+    #[allow(clippy::too_many_arguments)]
     fn tick(
         &mut self,
-        controls: &PlaneControls,
-        collider_set: &ColliderSet,
+        commands: &mut CommandQueue,
+        collider_set: &mut ColliderSet,
         rigidbody_set: &mut RigidBodySet,
         query_pipeline: &QueryPipeline,
+        atmosphere: &Atmosphere,
         physics_elements: &mut HashMap<String, Option<PhysicsData>>,
         debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>,
+        time: f64,
         delta_time: f32,
     );
 
@@ -79,6 +99,68 @@ pub trait PhysicsTick {
 
     /// Debug lines to forward to the render thread, refreshed each tick that sends data.
     fn debug_lines(&self) -> &[DebugPhysicsMessageType];
+
+    /// What this tick integrates on its own besides the rigidbodies (controls, trim, fuel...)
+    /// for a `PhysicsSnapshot`, under keys of its own (e.g. "<entity>/gun"). Nothing by default.
+    fn save_state(&self) -> HashMap<String, String> {
+        HashMap::new()
+    }
+
+    /// Puts back what `save_state` returned, skipping the entities this tick doesn't drive.
+    fn load_state(&mut self, _states: &HashMap<String, String>) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Several `PhysicsTick`s run one after the other against the same world each step,
+/// e.g. the player's plane, an AI plane and a missile, each with its own logic. Their saved
+/// states are kept apart as "<index>/<key>", two ticks using the same keys don't clash.
+#[derive(Default)]
+pub struct PhysicsTickGroup {
+    pub ticks: Vec<Box<dyn PhysicsTick + Send>>,
+    debug_lines: Vec<DebugPhysicsMessageType>,
+}
+
+impl PhysicsTickGroup {
+    pub fn new(ticks: Vec<Box<dyn PhysicsTick + Send>>) -> Self {
+        Self { ticks, debug_lines: Vec::new() }
+    }
+}
+
+impl PhysicsTick for PhysicsTickGroup {
+    fn tick(&mut self, commands: &mut CommandQueue, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, atmosphere: &Atmosphere, physics_elements: &mut HashMap<String, Option<PhysicsData>>, debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, time: f64, delta_time: f32) {
+        self.debug_lines.clear();
+        for physics_tick in self.ticks.iter_mut() {
+            physics_tick.tick(commands, collider_set, rigidbody_set, query_pipeline, atmosphere, physics_elements, debug_physics_tx, time, delta_time);
+            self.debug_lines.extend_from_slice(physics_tick.debug_lines());
+        }
+    }
+
+    fn toggle_debug_rendering(&mut self) {
+        for physics_tick in self.ticks.iter_mut() {
+            physics_tick.toggle_debug_rendering();
+        }
+    }
+
+    fn debug_lines(&self) -> &[DebugPhysicsMessageType] {
+        &self.debug_lines
+    }
+
+    fn save_state(&self) -> HashMap<String, String> {
+        self.ticks.iter().enumerate()
+            .flat_map(|(index, physics_tick)| physics_tick.save_state().into_iter().map(move |(key, state)| (format!("{}/{}", index, key), state)))
+            .collect()
+    }
+
+    fn load_state(&mut self, states: &HashMap<String, String>) -> Result<(), String> {
+        self.ticks.iter_mut().enumerate().try_for_each(|(index, physics_tick)| {
+            let prefix = format!("{}/", index);
+            let states = states.iter()
+                .filter_map(|(key, state)| Some((key.strip_prefix(&prefix)?.to_string(), state.clone())))
+                .collect();
+            physics_tick.load_state(&states)
+        })
+    }
 }
 
 pub struct Physics {
@@ -86,119 +168,462 @@ pub struct Physics {
     pub colission_pipeline: CollisionPipeline,
     pub query_pipeline: QueryPipeline,
     pub gravity: Vector3<f32>,
+    pub atmosphere: Atmosphere,
+
+    // Pipeline state carried between steps
+    pub integration_parameters: IntegrationParameters,
+    pub island_manager: IslandManager,
+    pub broad_phase: DefaultBroadPhase,
+    pub narrow_phase: NarrowPhase,
+    pub impulse_joint_set: ImpulseJointSet,
+    pub multibody_joint_set: MultibodyJointSet,
+    pub ccd_solver: CCDSolver,
     
     // Thread-safe physics data
     pub rigidbody_set: RigidBodySet, 
     pub collider_set: ColliderSet,
 
     pub physics_elements: HashMap<String, Option<PhysicsData>>,
+    // The level's joints by id, see add_joints
+    pub joints: HashMap<String, JointData>,
     
     // Delta time tracking
     pub delta_time: f32,
     pub last_physics_time: Instant,
+    // Simulated seconds, advanced by every step
+    pub time: f64,
+    pub max_substeps: u32,
+    pub rewind_buffer: RewindBuffer,
+
+    // Where the physics world sits in the level, moved along with the entity it follows
+    pub origin: FloatingOrigin,
+    pub origin_entity: String,
+    pub rebase_distance: f32,
+
+    // Collisions and contact forces of the last step
+    pub events: Vec<PhysicsEvent>,
+    event_collector: PhysicsEventCollector,
+    // Events of every step since the main thread last asked for data
+    unsent_events: Vec<PhysicsEvent>,
+}
+
+/// Level-wide settings of the physics thread, from the level's `physics` block.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PhysicsSettings {
+    #[serde(default = "default_tick_rate")]
+    pub tick_rate: f32, // steps per second
+    #[serde(default = "default_max_substeps")]
+    pub max_substeps: u32, // most steps run to catch up after a hitch before time is dropped
+    #[serde(default = "default_rewind_seconds")]
+    pub rewind_seconds: f32, // history kept for PhysicsCommand::Rewind
+    #[serde(default = "default_origin_entity")]
+    pub origin_entity: String, // entity the physics world's origin follows (see FloatingOrigin)
+    #[serde(default = "default_rebase_distance")]
+    pub rebase_distance: f32, // m it strays along the ground before the world is moved back under it, 0 never
+}
+
+fn default_tick_rate() -> f32 { 1.0 / Physics::FIXED_TIMESTEP }
+fn default_max_substeps() -> u32 { 8 }
+fn default_rewind_seconds() -> f32 { 10.0 }
+fn default_origin_entity() -> String { "player".to_string() }
+fn default_rebase_distance() -> f32 { 10_000.0 }
+
+impl Default for PhysicsSettings {
+    fn default() -> Self {
+        Self {
+            tick_rate: default_tick_rate(),
+            max_substeps: default_max_substeps(),
+            rewind_seconds: default_rewind_seconds(),
+            origin_entity: default_origin_entity(),
+            rebase_distance: default_rebase_distance(),
+        }
+    }
+}
+
+// Time scales PhysicsCommand::SetTimeScale is clamped to
+const MIN_TIME_SCALE: f32 = 1.0 / 16.0;
+const MAX_TIME_SCALE: f32 = 4.0;
+
+// Longest the thread sleeps while paused before looking at the commands again
+const PAUSED_WAIT: Duration = Duration::from_millis(50);
+
+impl Default for Physics {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Physics {
+    pub const FIXED_TIMESTEP: f32 = 1.0 / 120.0; // Default fixed timestep (120 steps per second), levels can pick their own
+
     pub fn new() -> Self {
         // Physics data
-        let physics = Physics {
+        Physics {
             physics_pipeline: PhysicsPipeline::new(),
             colission_pipeline: CollisionPipeline::new(),
             query_pipeline: QueryPipeline::new(),
             gravity: Vector3::new(0.0, -9.81, 0.0),
+            atmosphere: Atmosphere::default(),
+            integration_parameters: IntegrationParameters { dt: Self::FIXED_TIMESTEP, ..Default::default() },
+            island_manager: IslandManager::new(),
+            broad_phase: DefaultBroadPhase::new(),
+            narrow_phase: NarrowPhase::new(),
+            impulse_joint_set: ImpulseJointSet::new(),
+            multibody_joint_set: MultibodyJointSet::new(),
+            ccd_solver: CCDSolver::new(),
             rigidbody_set: RigidBodySet::new(),
             collider_set: ColliderSet::new(),
             physics_elements: HashMap::new(),
+            joints: HashMap::new(),
             delta_time: 0.0,
             last_physics_time: Instant::now(),
+            time: 0.0,
+            max_substeps: default_max_substeps(),
+            rewind_buffer: RewindBuffer::new(0),
+            origin: FloatingOrigin::default(),
+            origin_entity: default_origin_entity(),
+            rebase_distance: default_rebase_distance(),
+            events: Vec::new(),
+            event_collector: PhysicsEventCollector::default(),
+            unsent_events: Vec::new(),
+        }
+    }
+
+    /// Advances the world by one `integration_parameters.dt`, with no clock or channel
+    /// involved - the physics thread calls this from its accumulator loop, and anything
+    /// that wants to drive the world by hand (e.g. a headless simulation) can too.
+    /// The world is rebased first if the entity the origin follows strayed too far (see
+    /// `follow_origin`). The collisions the step raised are left in `events`, with their
+    /// contact points in the level like everything else leaving the physics world.
+    pub fn step(&mut self) {
+        self.follow_origin();
+        let physics_hooks = ();
+
+        self.physics_pipeline.step(
+            &self.gravity,
+            &self.integration_parameters,
+            &mut self.island_manager,
+            &mut self.broad_phase,
+            &mut self.narrow_phase,
+            &mut self.rigidbody_set,
+            &mut self.collider_set,
+            &mut self.impulse_joint_set,
+            &mut self.multibody_joint_set,
+            &mut self.ccd_solver,
+            Some(&mut self.query_pipeline),
+            &physics_hooks,
+            &self.event_collector,
+        );
+        self.time += self.integration_parameters.dt as f64;
+        self.events = self.event_collector.drain(self.time, &self.origin, &self.collider_set, &self.physics_elements);
+        self.break_joints();
+    }
+
+    /// Addresses the last step's events to the entities they involve, as commands, so their
+    /// ticks can react to them (e.g. damage from a collision) on the next step. The ticks
+    /// work in the physics world, the contact points are put back in it.
+    pub fn deliver_events(&self, commands: &mut CommandQueue) {
+        for event in &self.events {
+            let event = PhysicsEvent { contact_point: event.contact_point.map(|point| self.origin.to_local(&point)), ..event.clone() };
+            for entity in event.entities.iter().flatten() {
+                commands.push(EntityCommand::new(entity.clone(), event.clone()));
+            }
+        }
+    }
+
+    /// Moves the world back under `origin_entity` once it's `rebase_distance` from the origin.
+    pub fn follow_origin(&mut self) {
+        let Some(Some(physics_data)) = self.physics_elements.get(&self.origin_entity) else { return };
+        let Some(rigidbody) = self.rigidbody_set.get(physics_data.rigidbody_handle) else { return };
+
+        if let Some(shift) = FloatingOrigin::shift_for(rigidbody.translation(), self.rebase_distance) {
+            self.rebase(shift);
+        }
+    }
+
+    /// Moves everything in the physics world by `-shift`, the level stays where it was.
+    pub fn rebase(&mut self, shift: Vector3<f32>) {
+        self.origin.rebase(shift, &mut self.rigidbody_set, &mut self.collider_set);
+        // The ticks cast rays (wheels) before the next step gets to update it
+        self.query_pipeline.update(&self.collider_set);
+        self.rewind_buffer.shift(shift);
+        self.atmosphere.wind.origin = self.origin.offset.cast();
+    }
+
+    /// Joins the level objects the definitions name. A joint that can't be built is left
+    /// out and the rest are still added, the Err lists every one that failed (one per line).
+    pub fn add_joints(&mut self, definitions: &[JointDefinition]) -> Result<(), String> {
+        let mut errors: Vec<String> = Vec::new();
+
+        for definition in definitions {
+            let body = |entity: &str| match self.physics_elements.get(entity) {
+                Some(Some(physics_data)) => Ok(physics_data.rigidbody_handle),
+                Some(None) => Err(format!("Joint '{}': '{}' has no physics", definition.id, entity)),
+                None => Err(format!("Joint '{}': there is no '{}'", definition.id, entity)),
+            };
+            let built = if self.joints.contains_key(&definition.id) {
+                Err(format!("Joint '{}' already exists", definition.id))
+            } else {
+                body(&definition.body1).and_then(|body1| Ok((body1, body(&definition.body2)?, build_joint(definition)?)))
+            };
+
+            match built {
+                Ok((body1, body2, joint)) => {
+                    let handle = self.impulse_joint_set.insert(body1, body2, joint, true);
+                    self.joints.insert(definition.id.clone(), JointData {
+                        handle,
+                        body1: definition.body1.clone(),
+                        body2: definition.body2.clone(),
+                        kind: definition.kind.clone(),
+                        break_force: definition.break_force,
+                    });
+                },
+                Err(error) => errors.push(error),
+            }
+        }
+
+        if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+    }
+
+    // Removes the joints pulled past their break force last step, each raises a JointBroken
+    fn break_joints(&mut self) {
+        let broken: Vec<String> = self.joints.iter()
+            .filter(|(_, joint_data)| match (joint_data.break_force, self.impulse_joint_set.get(joint_data.handle)) {
+                (Some(break_force), Some(joint)) => joint_force(joint, &self.integration_parameters) > break_force,
+                _ => false,
+            })
+            .map(|(id, _)| id.clone())
+            .collect();
+
+        for id in broken {
+            if let Some(joint_data) = self.joints.remove(&id) {
+                self.impulse_joint_set.remove(joint_data.handle, true);
+                self.events.push(PhysicsEvent {
+                    kind: PhysicsEventKind::JointBroken { joint: id },
+                    entities: [Some(joint_data.body1), Some(joint_data.body2)],
+                    impact_speed: 0.0,
+                    contact_point: None,
+                    time: self.time,
+                });
+            }
+        }
+    }
+
+    /// Every joint's position and load, sorted by id so the console doesn't shuffle them.
+    pub fn joint_statuses(&self) -> Vec<JointStatus> {
+        let mut statuses: Vec<JointStatus> = self.joints.iter()
+            .filter_map(|(id, joint_data)| {
+                let joint = self.impulse_joint_set.get(joint_data.handle)?;
+                Some(joint_status(id, joint_data, joint, &self.rigidbody_set, &self.integration_parameters))
+            })
+            .collect();
+        statuses.sort_by(|a, b| a.id.cmp(&b.id));
+        statuses
+    }
+
+    /// Sets the fixed step the world advances by, the ticks get the same delta time.
+    pub fn apply_settings(&mut self, settings: &PhysicsSettings) {
+        self.integration_parameters.dt = 1.0 / settings.tick_rate.max(1.0);
+        self.max_substeps = settings.max_substeps.max(1);
+        self.rewind_buffer = RewindBuffer::new((settings.rewind_seconds.max(0.0) * settings.tick_rate.max(1.0)) as usize);
+        self.origin_entity = settings.origin_entity.clone();
+        self.rebase_distance = settings.rebase_distance;
+    }
+
+    /// Runs the ticks and one physics step, then records the result for rewinding and
+    /// hands its events out.
+    fn fixed_step(&mut self, physics_tick: &mut Box<dyn PhysicsTick + Send>, commands: &mut CommandQueue, debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>) {
+        // Wall clock time between steps, for whoever wants to know how far behind the thread runs
+        let current_time = Instant::now();
+        self.delta_time = current_time.duration_since(self.last_physics_time).as_secs_f32();
+        self.last_physics_time = current_time;
+
+        // Apply forces before each physics step, ticks integrate state over time (spool, fuel)
+        // so they must run exactly once per step and with the step's own delta time
+        physics_tick.tick(commands, &mut self.collider_set, &mut self.rigidbody_set, &self.query_pipeline, &self.atmosphere, &mut self.physics_elements, debug_physics_tx, self.time, self.integration_parameters.dt);
+        // Delivered once: whatever no tick took this step is for an entity nobody drives
+        commands.clear();
+
+        self.step();
+        self.rewind_buffer.record(self.time, &self.rigidbody_set);
+        self.deliver_events(commands);
+        self.unsent_events.extend(self.events.iter().cloned());
+    }
+
+    /// Puts the rigidbodies back `seconds` (as far as the rewind buffer goes), the
+    /// simulated time goes back with them.
+    pub fn rewind(&mut self, seconds: f32) {
+        if let Some(time) = self.rewind_buffer.rewind(seconds, &mut self.rigidbody_set) {
+            self.time = time;
+        }
+    }
+
+    /// Adds a game object to the running world under its id, built like the level's own
+    /// (see build_physics_element). Nothing is added if the id is taken or any of its
+    /// colliders is invalid.
+    pub fn spawn(&mut self, game_object: &GameObject) -> Result<(), String> {
+        if self.physics_elements.contains_key(&game_object.id) {
+            return Err(format!("'{}' already exists", game_object.id));
+        }
+
+        let mut errors = Vec::new();
+        let physics_data = build_physics_element(game_object, &mut self.collider_set, &mut self.rigidbody_set, &mut errors);
+        if !errors.is_empty() {
+            if let Some(physics_data) = physics_data {
+                self.remove_rigidbody(physics_data.rigidbody_handle);
+            }
+            return Err(errors.join("\n"));
+        }
+
+        self.physics_elements.insert(game_object.id.clone(), physics_data);
+        Ok(())
+    }
+
+    /// Removes an entity, with its rigidbody and colliders.
+    pub fn despawn(&mut self, entity: &str) -> Result<(), String> {
+        match self.physics_elements.remove(entity) {
+            Some(Some(physics_data)) => {
+                // Its joints go with it
+                self.remove_rigidbody(physics_data.rigidbody_handle);
+                let impulse_joint_set = &self.impulse_joint_set;
+                self.joints.retain(|_, joint_data| impulse_joint_set.get(joint_data.handle).is_some());
+                Ok(())
+            },
+            Some(None) => Ok(()),
+            None => Err(format!("There is no '{}' to despawn", entity)),
+        }
+    }
+
+    /// Moves an entity somewhere else in the physics world with a new velocity and no
+    /// rotation rate, e.g. to respawn it. The interpolation blends into the new place over
+    /// a single step.
+    pub fn teleport(&mut self, entity: &str, position: Vector3<f32>, rotation: UnitQuaternion<f32>, linvel: Vector3<f32>) -> Result<(), String> {
+        let handle = match self.physics_elements.get(entity) {
+            Some(Some(physics_data)) => physics_data.rigidbody_handle,
+            Some(None) => return Err(format!("'{}' has no physics", entity)),
+            None => return Err(format!("There is no '{}' to teleport", entity)),
         };
 
-        physics
+        let rigidbody = self.rigidbody_set.get_mut(handle).ok_or_else(|| format!("'{}' lost its rigidbody", entity))?;
+        rigidbody.set_position(Isometry::from_parts(position.into(), rotation), true);
+        rigidbody.set_linvel(linvel, true);
+        rigidbody.set_angvel(Vector3::zeros(), true);
+        Ok(())
+    }
+
+    /// The whole world as it is now, with whatever state `physics_tick` keeps of its own.
+    pub fn snapshot(&self, physics_tick: &dyn PhysicsTick) -> PhysicsSnapshot {
+        PhysicsSnapshot {
+            time: self.time,
+            origin: self.origin.clone(),
+            gravity: self.gravity,
+            integration_parameters: self.integration_parameters,
+            island_manager: self.island_manager.clone(),
+            broad_phase: self.broad_phase.clone(),
+            narrow_phase: self.narrow_phase.clone(),
+            rigidbody_set: self.rigidbody_set.clone(),
+            collider_set: self.collider_set.clone(),
+            impulse_joint_set: self.impulse_joint_set.clone(),
+            multibody_joint_set: self.multibody_joint_set.clone(),
+            ccd_solver: self.ccd_solver.clone(),
+            physics_elements: self.physics_elements.clone(),
+            joints: self.joints.clone(),
+            tick_states: physics_tick.save_state(),
+        }
     }
 
-    pub fn physics_thread(&mut self, tx: Sender<HashMap<String, RenderMessage>>, rx: Receiver<PhysicsCommand>, plane_control_rx: Receiver<PlaneControls>, debug_physics_tx: Sender<Vec<DebugPhysicsMessageType>>, mut physics_tick: Box<dyn PhysicsTick + Send>) {
-        const FIXED_TIMESTEP: f32 = 1.0 / 120.0; // Fixed timestep for 120 FPS for more responsive physics
+    /// Replaces the world with a snapshot, already read in full (see PhysicsSnapshot::load).
+    /// The rewind history is from another timeline, so it's dropped, and the atmosphere and
+    /// settings stay the level's. Returns the entities that came and went with it.
+    pub fn restore(&mut self, snapshot: PhysicsSnapshot, physics_tick: &mut dyn PhysicsTick) -> WorldChanges {
+        let changes = WorldChanges {
+            added: snapshot.physics_elements.keys().filter(|entity| !self.physics_elements.contains_key(*entity)).cloned().collect(),
+            removed: self.physics_elements.keys().filter(|entity| !snapshot.physics_elements.contains_key(*entity)).cloned().collect(),
+        };
+
+        self.time = snapshot.time;
+        self.origin = snapshot.origin;
+        self.atmosphere.wind.origin = self.origin.offset.cast();
+        self.gravity = snapshot.gravity;
+        self.integration_parameters = snapshot.integration_parameters;
+        self.island_manager = snapshot.island_manager;
+        self.broad_phase = snapshot.broad_phase;
+        self.narrow_phase = snapshot.narrow_phase;
+        self.rigidbody_set = snapshot.rigidbody_set;
+        self.collider_set = snapshot.collider_set;
+        self.impulse_joint_set = snapshot.impulse_joint_set;
+        self.multibody_joint_set = snapshot.multibody_joint_set;
+        self.ccd_solver = snapshot.ccd_solver;
+        self.physics_elements = snapshot.physics_elements;
+        self.joints = snapshot.joints;
+
+        self.query_pipeline.update(&self.collider_set);
+        self.rewind_buffer.clear();
+        self.event_collector = PhysicsEventCollector::default();
+        self.events.clear();
+
+        // Only once the world is the snapshot's, whatever state the ticks take back is about it.
+        // One that can't be read keeps what it had, the world is loaded all the same.
+        if let Err(error) = physics_tick.load_state(&snapshot.tick_states) {
+            eprintln!("The loaded physics kept some of the logic's own state: {}", error);
+        }
+        changes
+    }
+
+    fn remove_rigidbody(&mut self, handle: RigidBodyHandle) {
+        self.rigidbody_set.remove(handle, &mut self.island_manager, &mut self.collider_set, &mut self.impulse_joint_set, &mut self.multibody_joint_set, true);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn physics_thread(&mut self, tx: Sender<HashMap<String, RenderMessage>>, rx: Receiver<PhysicsCommand>, command_rx: Receiver<EntityCommand>, debug_physics_tx: Sender<Vec<DebugPhysicsMessageType>>, status_tx: Sender<PhysicsStatus>, events_tx: Sender<Vec<PhysicsEvent>>, world_tx: Sender<WorldChanges>, mut physics_tick: Box<dyn PhysicsTick + Send>) {
+        let mut fixed_timestep = self.integration_parameters.dt;
         let mut accumulator = 0.0;
         let mut last_update = Instant::now();
         let mut should_send_data = false;
 
-        let integration_parameters = IntegrationParameters { dt: FIXED_TIMESTEP, ..Default::default() };
-        let mut island_manager = IslandManager::new();
-        let mut broad_phase = DefaultBroadPhase::new();
-        let mut narrow_phase = NarrowPhase::new();
-        let mut impulse_joint_set = ImpulseJointSet::new();
-        let mut multibody_joint_set = MultibodyJointSet::new();
-        let mut ccd_solver = CCDSolver::new();
-        let physics_hooks = ();
-        let event_handler = ();
-
-        let mut plane_controls: PlaneControls = PlaneControls::new();
+        let mut commands = CommandQueue::new();
         let mut paused = false;
+        let mut time_scale: f32 = 1.0;
         let mut shutdown = false;
 
         loop {
-            match plane_control_rx.try_recv() {
-                Ok(plane_control) => {
-                    plane_controls = plane_control;
-                },
-                Err(_) => {
-                    // No message available, continuing with physics
-                }
+            // Queue every command sent since the last pass, they wait there until a step runs (the
+            // states only the newest, see EntityCommand::state)
+            while let Ok(command) = command_rx.try_recv() {
+                commands.push(command);
             }
 
             let now = Instant::now();
             let elapsed = now.duration_since(last_update).as_secs_f32();
-            accumulator += elapsed;
+            // Falling further behind than max_substeps (a hitch, a breakpoint) drops the
+            // extra time instead of trying to catch up on it (spiral of death)
+            accumulator = (accumulator + elapsed * time_scale).min(fixed_timestep * self.max_substeps as f32);
             last_update = now;
 
-            // Apply forces before physics step (only if not paused)
-            if !paused {
-                physics_tick.tick(&plane_controls, &self.collider_set, &mut self.rigidbody_set, &self.query_pipeline, &mut self.physics_elements, &debug_physics_tx, self.delta_time);
-            }
-
             // Step the physics pipeline with fixed timestep (only if not paused)
-            while accumulator >= FIXED_TIMESTEP && !paused {
-                // Calculate delta time for this physics step
-                let current_time = Instant::now();
-                self.delta_time = current_time.duration_since(self.last_physics_time).as_secs_f32();
-                self.last_physics_time = current_time;
-                
-                // Clamp delta time to prevent spiral of death
-                let clamped_delta_time = self.delta_time.min(FIXED_TIMESTEP * 2.0);
-                
-                self.physics_pipeline.step(
-                    &self.gravity,
-                    &integration_parameters,
-                    &mut island_manager,
-                    &mut broad_phase,
-                    &mut narrow_phase,
-                    &mut self.rigidbody_set,
-                    &mut self.collider_set,
-                    &mut impulse_joint_set,
-                    &mut multibody_joint_set,
-                    &mut ccd_solver,
-                    Some(&mut self.query_pipeline),
-                    &physics_hooks,
-                    &event_handler,
-                );
-
-                accumulator -= FIXED_TIMESTEP;
+            while accumulator >= fixed_timestep && !paused {
+                self.fixed_step(&mut physics_tick, &mut commands, &debug_physics_tx);
+                accumulator -= fixed_timestep;
             }
 
-            loop {
-                match rx.try_recv() {
-                    Ok(PhysicsCommand::RequestData) => {
+            // Sleep until the next step is due, any control command wakes the thread up early
+            let wait = if paused { PAUSED_WAIT } else { Duration::from_secs_f32((fixed_timestep - accumulator).max(0.0) / time_scale) };
+            let mut next_command = rx.recv_timeout(wait).ok();
+
+            while let Some(command) = next_command.take().or_else(|| rx.try_recv().ok()) {
+                match command {
+                    PhysicsCommand::RequestData => {
                         should_send_data = true;
                     },
-                    Ok(PhysicsCommand::Shutdown) => {
+                    PhysicsCommand::Shutdown => {
                         println!("Physics thread received shutdown command");
                         shutdown = true;
                         break;
                     },
-                    Ok(PhysicsCommand::ToggleDebug) => {
+                    PhysicsCommand::ToggleDebug => {
                         physics_tick.toggle_debug_rendering();
                     },
-                    Ok(PhysicsCommand::TogglePause) => {
+                    PhysicsCommand::TogglePause => {
                         paused = !paused;
                         if !paused {
                             accumulator = 0.0;
@@ -207,9 +632,55 @@ impl Physics {
                         }
                         println!("Physics {}", if paused { "PAUSED" } else { "RESUMED" });
                     },
-                    Err(_) => {
-                        break;
-                    }
+                    PhysicsCommand::SetTimeScale(scale) => {
+                        time_scale = scale.clamp(MIN_TIME_SCALE, MAX_TIME_SCALE);
+                        println!("Physics time scale x{}", time_scale);
+                    },
+                    PhysicsCommand::StepOnce => {
+                        if paused {
+                            self.fixed_step(&mut physics_tick, &mut commands, &debug_physics_tx);
+                        }
+                    },
+                    PhysicsCommand::Rewind(seconds) => {
+                        self.rewind(seconds);
+                        accumulator = 0.0;
+                    },
+                    PhysicsCommand::Spawn(mut game_object) => {
+                        game_object.transform.position = self.origin.to_local(&game_object.transform.position);
+                        if let Err(error) = self.spawn(&game_object) {
+                            eprintln!("Couldn't spawn '{}': {}", game_object.id, error);
+                        }
+                    },
+                    PhysicsCommand::Despawn(entity) => {
+                        if let Err(error) = self.despawn(&entity) {
+                            eprintln!("{}", error);
+                        }
+                    },
+                    PhysicsCommand::Teleport { entity, position, rotation, linvel } => {
+                        if let Err(error) = self.teleport(&entity, self.origin.to_local(&position), rotation, linvel) {
+                            eprintln!("{}", error);
+                        }
+                    },
+                    PhysicsCommand::SaveSnapshot(path) => {
+                        match self.snapshot(physics_tick.as_ref()).save(&path) {
+                            Ok(()) => println!("Physics saved to {}", path),
+                            Err(error) => eprintln!("Couldn't save the physics: {}", error),
+                        }
+                    },
+                    PhysicsCommand::LoadSnapshot(path) => {
+                        match PhysicsSnapshot::load(&path).map(|snapshot| self.restore(snapshot, physics_tick.as_mut())) {
+                            Ok(changes) => {
+                                println!("Physics loaded from {}", path);
+                                if let Err(e) = world_tx.send(changes) {
+                                    println!("Failed to send the loaded world: {}", e);
+                                }
+                            },
+                            Err(error) => eprintln!("Couldn't load the physics: {}", error),
+                        }
+                        // The snapshot brings its own step along
+                        fixed_timestep = self.integration_parameters.dt;
+                        accumulator = 0.0;
+                    },
                 }
             }
 
@@ -221,14 +692,11 @@ impl Physics {
                 let mut new_render_messages: HashMap<String, RenderMessage> = HashMap::new();
 
                 for (key, physics_data) in &self.physics_elements {
-                    match physics_data {
-                        Some(physics_data) => {
-                            let metadata = physics_data.metadata.clone();
-                            let rb = self.rigidbody_set.get(physics_data.rigidbody_handle).unwrap();
-
-                            new_render_messages.insert(key.clone(), RenderMessage { translation: *rb.translation(), rotation: rb.rotation().into_inner(), linvel: *rb.linvel(), metadata: metadata });
-                        },
-                        None => {},
+                    if let Some(physics_data) = physics_data {
+                        let metadata = physics_data.metadata.clone();
+                        let rb = self.rigidbody_set.get(physics_data.rigidbody_handle).unwrap();
+
+                        new_render_messages.insert(key.clone(), RenderMessage { time: self.time, translation: self.origin.to_world(rb.translation()), rotation: rb.rotation().into_inner(), linvel: *rb.linvel(), metadata });
                     }
                 }
 
@@ -237,9 +705,23 @@ impl Physics {
                     break;
                 }
 
-                if let Err(e) = debug_physics_tx.send(physics_tick.debug_lines().to_vec()) {
+                let mut debug_lines = physics_tick.debug_lines().to_vec();
+                debug_lines.extend(self.joints.values().filter_map(|joint_data| {
+                    joint_debug_line(joint_data, self.impulse_joint_set.get(joint_data.handle)?, &self.rigidbody_set, &self.integration_parameters)
+                }));
+                let debug_lines = debug_lines.iter().map(|message| self.origin.debug_to_world(message)).collect();
+                if let Err(e) = debug_physics_tx.send(debug_lines) {
                     println!("Failed to send debug physics messages: {}", e);
                 }
+
+                let status = PhysicsStatus { time: self.time, paused, time_scale, rewind_available: self.rewind_buffer.available(), joints: self.joint_statuses(), origin: self.origin.offset };
+                if let Err(e) = status_tx.send(status) {
+                    println!("Failed to send physics status: {}", e);
+                }
+
+                if let Err(e) = events_tx.send(std::mem::take(&mut self.unsent_events)) {
+                    println!("Failed to send physics events: {}", e);
+                }
                 
                 should_send_data = false; // Reset flag after sending
             }
@@ -256,4 +738,101 @@ impl Physics {
         self.delta_time = 0.0;
         self.last_physics_time = Instant::now();
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::game_nodes::game_object::{self, ColliderType, MetaData, RigidBodyData, Transform};
+    use crate::engine::physics::test_world::{body, physics_object};
+
+    // Keeps whatever it's given under the same key as every other one
+    struct Counter(String);
+
+    impl PhysicsTick for Counter {
+        fn tick(&mut self, _commands: &mut CommandQueue, _collider_set: &mut ColliderSet, _rigidbody_set: &mut RigidBodySet, _query_pipeline: &QueryPipeline, _atmosphere: &Atmosphere, _physics_elements: &mut HashMap<String, Option<PhysicsData>>, _debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, _time: f64, _delta_time: f32) {}
+        fn toggle_debug_rendering(&mut self) {}
+        fn debug_lines(&self) -> &[DebugPhysicsMessageType] { &[] }
+
+        fn save_state(&self) -> HashMap<String, String> {
+            HashMap::from([("player/count".to_string(), self.0.clone())])
+        }
+
+        fn load_state(&mut self, states: &HashMap<String, String>) -> Result<(), String> {
+            self.0 = states.get("player/count").cloned().ok_or("Nothing saved")?;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn ticks_in_a_group_keep_their_states_apart() {
+        let group = PhysicsTickGroup::new(vec![Box::new(Counter("1".to_string())), Box::new(Counter("2".to_string()))]);
+        let states = group.save_state();
+        assert_eq!(states.len(), 2);
+
+        let mut loaded = PhysicsTickGroup::new(vec![Box::new(Counter(String::new())), Box::new(Counter(String::new()))]);
+        loaded.load_state(&states).unwrap();
+        assert_eq!(loaded.save_state(), states);
+    }
+
+    #[test]
+    fn ccd_keeps_fast_bodies_from_tunneling_through_thin_walls() {
+        // 400m/s covers over 3m a step, the wall is 10cm thick
+        let far_side = |ccd: bool| {
+            let mut physics = Physics::new();
+            let wall = ColliderType::Cuboid { half_extents: (5.0, 5.0, 0.05), position: (0.0, 0.0, 0.0) };
+            physics.spawn(&physics_object("wall", Vector3::new(0.0, 500.0, 1000.0), body(true, Vector3::zeros(), false), wall)).unwrap();
+            let bullet = ColliderType::Ball { radius: 0.1, position: (0.0, 0.0, 0.0) };
+            physics.spawn(&physics_object("bullet", Vector3::new(0.0, 500.0, 990.0), body(false, Vector3::new(0.0, 0.0, 400.0), ccd), bullet)).unwrap();
+            for _ in 0..12 {
+                physics.step();
+            }
+
+            let handle = physics.physics_elements["bullet"].as_ref().unwrap().rigidbody_handle;
+            physics.rigidbody_set[handle].translation().z > 1000.0
+        };
+
+        assert!(far_side(false), "the wall should be thin enough to miss without CCD");
+        assert!(!far_side(true));
+    }
+
+    #[test]
+    fn bodies_spawned_at_runtime_simulate_until_despawned() {
+        let crate_box = |id: &str| GameObject {
+            id: id.to_string(),
+            model: String::new(),
+            transform: Transform { position: Vector3::new(0.0, 100.0, 500.0), rotation: UnitQuaternion::identity(), scale: Vector3::new(1.0, 1.0, 1.0) },
+            children: vec![],
+            metadata: MetaData {
+                physics: Some(game_object::Physics {
+                    rigidbody: RigidBodyData { is_static: false, mass: 100.0, center_of_mass: Vector3::zeros(), initial_velocity: Vector3::zeros(), initial_angular_velocity: Vector3::zeros(), linear_damping: 0.0, angular_damping: 2.0, ccd: false, gravity_scale: 1.0 },
+                    colliders: vec![ColliderType::Cuboid { half_extents: (1.0, 1.0, 1.0), position: (0.0, 0.0, 0.0) }],
+                    material: None,
+                }),
+                cameras: None,
+                lighting: None,
+            },
+        };
+        let mut physics = Physics::new();
+
+        physics.spawn(&crate_box("crate")).unwrap();
+        assert!(physics.spawn(&crate_box("crate")).is_err(), "ids are unique");
+        for _ in 0..(1.0 / physics.integration_parameters.dt) as usize {
+            physics.step();
+        }
+        let handle = physics.physics_elements["crate"].as_ref().unwrap().rigidbody_handle;
+        // About 5m down after a second of free fall
+        assert!((physics.rigidbody_set[handle].translation().y - 95.1).abs() < 0.5);
+
+        physics.teleport("crate", Vector3::new(10.0, 3.0, 0.0), UnitQuaternion::identity(), Vector3::new(0.0, 0.0, 5.0)).unwrap();
+        assert_eq!(physics.rigidbody_set[handle].translation().x, 10.0);
+        assert_eq!(physics.rigidbody_set[handle].linvel().z, 5.0);
+
+        let colliders = physics.collider_set.len();
+        physics.despawn("crate").unwrap();
+        assert!(!physics.physics_elements.contains_key("crate"));
+        assert!(physics.rigidbody_set.get(handle).is_none());
+        assert_eq!(physics.collider_set.len(), colliders - 1);
+        assert!(physics.despawn("crate").is_err());
+        physics.step();
+    }
+}
@@ -1,12 +1,20 @@
 use std::collections::HashMap;
-use nalgebra::{vector, Unit, Vector3};
+use nalgebra::{vector, DMatrix, Matrix4, Point3, Unit, Vector3};
 use rapier3d::prelude::*;
 
-use crate::engine::physics::physics_handler::PhysicsData;
+use crate::engine::physics::physics_handler::{PhysicsData, PhysicsSettings};
+use crate::engine::physics::metadata::Metadata;
 use crate::engine::game_nodes::game_object::GameObject;
-use crate::resources::load_instances;
+use crate::resources::{load_instances, load_scene};
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::joints::JointDefinition;
+use crate::engine::terrain::heightmap::{Heightmap, TerrainSettings};
 use crate::engine::game_nodes::game_object;
 
+// Total contact force (N) above which a contact raises a ContactForce event, well over what
+// a plane resting on its belly puts on the ground so only hard hits get reported
+const CONTACT_FORCE_EVENT_THRESHOLD: f32 = 500_000.0;
+
 /*
 todo:
     - Make a new way of saving physics data for each loaded element in the level, this will be saved on the 
@@ -32,14 +40,19 @@ fn compute_principal_inertia(mass: f32, center_of_mass: Vector3<f32>, colliders:
         position: (f32, f32, f32),
     }
 
-    let boxes: Vec<BoxPart> = colliders.iter().filter_map(|collider| match collider {
-        game_object::ColliderType::Cuboid { half_extents, position } => Some(BoxPart {
-            volume: 8.0 * half_extents.0 * half_extents.1 * half_extents.2,
-            half_extents: *half_extents,
-            position: *position,
-        }),
-        _ => None,
-    }).collect();
+    fn box_part(collider: &game_object::ColliderType) -> Option<BoxPart> {
+        match collider {
+            game_object::ColliderType::Cuboid { half_extents, position } => Some(BoxPart {
+                volume: 8.0 * half_extents.0 * half_extents.1 * half_extents.2,
+                half_extents: *half_extents,
+                position: *position,
+            }),
+            game_object::ColliderType::Material { collider, .. } => box_part(collider),
+            _ => None,
+        }
+    }
+
+    let boxes: Vec<BoxPart> = colliders.iter().filter_map(box_part).collect();
 
     let total_volume: f32 = boxes.iter().map(|b| b.volume).sum();
     if total_volume <= 0.0 {
@@ -71,13 +84,16 @@ fn compute_principal_inertia(mass: f32, center_of_mass: Vector3<f32>, colliders:
     inertia
 }
 
-pub fn load_physics_from_level(mut level_path: String, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, physics_handlers: &mut HashMap<String, Option<PhysicsData>>) {
+/// Builds the level's rigidbodies and colliders, and its terrain's as the "terrain" entity. A
+/// collider that can't be built is left out and the rest of the level still loads, the Err
+/// lists every one that failed (one per line).
+pub fn load_physics_from_level(mut level_path: String, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, physics_handlers: &mut HashMap<String, Option<PhysicsData>>) -> Result<(), String> {
 
     level_path += "/data.ron";
 
-    let mut physics_data: HashMap<String, PhysicsData> = HashMap::new();
+    let mut errors: Vec<String> = Vec::new();
 
-    let instances_data_to_load = load_instances(level_path);
+    let instances_data_to_load = load_instances(level_path.clone());
     match instances_data_to_load {
         Some(instances) => {
             
@@ -105,55 +121,464 @@ pub fn load_physics_from_level(mut level_path: String, collider_set: &mut Collid
 
                 for (i, instance_data) in model_instances.iter().enumerate() {
                     // Physics
-                    let mut physics_data: Option<PhysicsData> = None;
-
-                    if let Some(physics_obj_data) = &instance_data.metadata.physics {
-                        let mut rigid_body = if physics_obj_data.rigidbody.is_static {
-                            RigidBodyBuilder::fixed().additional_mass(physics_obj_data.rigidbody.mass).translation(vector![instance_data.transform.position.x, instance_data.transform.position.y, instance_data.transform.position.z]).build()
-                        } else {
-                            let principal_inertia = compute_principal_inertia(
-                                physics_obj_data.rigidbody.mass,
-                                physics_obj_data.rigidbody.center_of_mass,
-                                &physics_obj_data.colliders,
-                            );
-
-                            RigidBodyBuilder::dynamic()
-                            .additional_mass_properties(rapier3d::prelude::MassProperties::new(physics_obj_data.rigidbody.center_of_mass.into(), physics_obj_data.rigidbody.mass, principal_inertia))
-                            .translation(instance_data.transform.position)
-                            .angular_damping(2.0)
-                            .build()
-                        };
-
-                        rigid_body.set_linvel(physics_obj_data.rigidbody.initial_velocity, true);
-                        let rigidbody_handle = rigidbody_set.insert(rigid_body);
-
-                        // Create colliders
-                        let mut collider_handles: Vec<ColliderHandle> = Vec::new();
-
-                        for collider_data in &physics_obj_data.colliders {
-                            let collider = match collider_data {
-                                game_object::ColliderType::Cuboid { half_extents, position } => {
-                                    ColliderBuilder::cuboid(half_extents.0, half_extents.1, half_extents.2)
-                                        .translation(vector![position.0, position.1, position.2])
-                                        .build()
-                                },
-                                game_object::ColliderType::HalfSpace { normal } => {
-                                    ColliderBuilder::halfspace(Unit::new_normalize(*normal)).build()
-                                },
-                                _ => continue,
-                            };
-                            let handle = collider_set.insert_with_parent(collider, rigidbody_handle, rigidbody_set);
-                            collider_handles.push(handle);
-                        }
-
-                        physics_data = Some(PhysicsData { rigidbody_handle, collider_handles, metadata: HashMap::new() });
-                    };
+                    let physics_data = build_physics_element(instance_data, collider_set, rigidbody_set, &mut errors);
 
                     // println!("loaded data: {}", ids[i]);
                     physics_handlers.insert(ids[i].clone(), physics_data);
                 }
             }
         },
-        None => eprintln!("The instance data was not correctly loaded"),
+        None => errors.push("The instance data was not correctly loaded".to_string()),
+    }
+
+    if let Some(terrain) = load_scene(level_path).and_then(|scene| scene.terrain) {
+        match build_terrain_element(&terrain, collider_set, rigidbody_set) {
+            Ok(physics_data) => { physics_handlers.insert("terrain".to_string(), Some(physics_data)); },
+            Err(error) => errors.push(format!("'terrain': {}", error)),
+        }
+    }
+
+    if errors.is_empty() { Ok(()) } else { Err(errors.join("\n")) }
+}
+
+/// Inserts the rigidbody and colliders of one game object (None if it has no physics), at its
+/// transform's position and rotation. The level loader uses it for every object and
+/// `Physics::spawn` for those added at runtime. Colliders that can't be built are left out
+/// and pushed to `errors`.
+pub fn build_physics_element(instance_data: &GameObject, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, errors: &mut Vec<String>) -> Option<PhysicsData> {
+    let physics_obj_data = instance_data.metadata.physics.as_ref()?;
+    let body = &physics_obj_data.rigidbody;
+    let default_material = game_object::ColliderMaterial::default();
+    let body_material = physics_obj_data.material.as_ref().unwrap_or(&default_material);
+    let position = Isometry::from_parts(instance_data.transform.position.into(), instance_data.transform.rotation);
+
+    if let Err(error) = check_body_parameters(body, body_material) {
+        // Still built, with rapier clamping what it can, so the object doesn't vanish from the level
+        errors.push(format!("'{}': {}", instance_data.id, error));
+    }
+
+    let mut rigid_body = if physics_obj_data.rigidbody.is_static {
+        RigidBodyBuilder::fixed().additional_mass(physics_obj_data.rigidbody.mass).position(position).build()
+    } else {
+        let principal_inertia = compute_principal_inertia(
+            physics_obj_data.rigidbody.mass,
+            physics_obj_data.rigidbody.center_of_mass,
+            &physics_obj_data.colliders,
+        );
+
+        RigidBodyBuilder::dynamic()
+        .additional_mass_properties(rapier3d::prelude::MassProperties::new(physics_obj_data.rigidbody.center_of_mass.into(), physics_obj_data.rigidbody.mass, principal_inertia))
+        .position(position)
+        .linear_damping(body.linear_damping.max(0.0))
+        .angular_damping(body.angular_damping.max(0.0))
+        .gravity_scale(body.gravity_scale)
+        .ccd_enabled(body.ccd)
+        .build()
+    };
+
+    rigid_body.set_linvel(body.initial_velocity, true);
+    rigid_body.set_angvel(body.initial_angular_velocity, true);
+    let rigidbody_handle = rigidbody_set.insert(rigid_body);
+
+    // Create colliders
+    let mut collider_handles: Vec<ColliderHandle> = Vec::new();
+    let mut triggers: HashMap<ColliderHandle, String> = HashMap::new();
+
+    for collider_data in &physics_obj_data.colliders {
+        let mut collider = match build_collider(collider_data, &instance_data.model, instance_data.transform.scale) {
+            Ok(collider) => collider,
+            Err(error) => {
+                errors.push(format!("'{}': {}", instance_data.id, error));
+                continue;
+            },
+        };
+        let material = match collider_data.material() {
+            Some(material) => {
+                if let Err(error) = check_material(material) {
+                    errors.push(format!("'{}': {}", instance_data.id, error));
+                }
+                material
+            },
+            None => body_material,
+        };
+        collider.set_friction(material.friction.max(0.0));
+        collider.set_restitution(material.restitution.clamp(0.0, 1.0));
+        if !collider.is_sensor() {
+            collider.set_density(material.density.max(0.0));
+        }
+        collider.set_collision_groups(InteractionGroups::new(
+            Group::from_bits_truncate(material.collision_groups.memberships),
+            Group::from_bits_truncate(material.collision_groups.filter),
+        ));
+        // Every collider reports its collisions and hard contacts (see PhysicsEventCollector),
+        // sensors only the things going in and out
+        if collider.is_sensor() {
+            collider.set_active_events(ActiveEvents::COLLISION_EVENTS);
+        } else {
+            collider.set_active_events(ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS);
+            collider.set_contact_force_event_threshold(CONTACT_FORCE_EVENT_THRESHOLD);
+        }
+        let handle = collider_set.insert_with_parent(collider, rigidbody_handle, rigidbody_set);
+        collider_handles.push(handle);
+
+        if let game_object::ColliderType::Sensor { trigger, .. } = collider_data {
+            triggers.insert(handle, trigger.clone());
+        }
+    }
+
+    Some(PhysicsData { rigidbody_handle, collider_handles, triggers, metadata: Metadata::new() })
+}
+
+/// The terrain's heightfield on a fixed body, from the same heightmap samples as its render mesh.
+pub fn build_terrain_element(terrain: &TerrainSettings, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet) -> Result<PhysicsData, String> {
+    let heightmap = Heightmap::load(terrain)?;
+    Ok(build_heightfield_element(&heightmap, collider_set, rigidbody_set))
+}
+
+/// A heightmap as a heightfield collider on a fixed body, centered on the origin.
+pub fn build_heightfield_element(heightmap: &Heightmap, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet) -> PhysicsData {
+    let rigidbody_handle = rigidbody_set.insert(RigidBodyBuilder::fixed().build());
+
+    // Heights already in meters, and reporting hits like every other collider of the level
+    let collider = ColliderBuilder::heightfield(heightmap.matrix(), vector![heightmap.size, 1.0, heightmap.size])
+        .active_events(ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS)
+        .contact_force_event_threshold(CONTACT_FORCE_EVENT_THRESHOLD)
+        .build();
+    let collider_handle = collider_set.insert_with_parent(collider, rigidbody_handle, rigidbody_set);
+
+    PhysicsData { rigidbody_handle, collider_handles: vec![collider_handle], triggers: HashMap::new(), metadata: Metadata::new() }
+}
+
+/// Damping, friction and restitution that make no physical sense, as one message.
+fn check_body_parameters(body: &game_object::RigidBodyData, material: &game_object::ColliderMaterial) -> Result<(), String> {
+    let mut problems: Vec<String> = Vec::new();
+    if body.linear_damping < 0.0 || body.angular_damping < 0.0 {
+        problems.push(format!("damping can't be negative, got {} linear and {} angular", body.linear_damping, body.angular_damping));
+    }
+    problems.extend(check_material(material).err());
+
+    if problems.is_empty() { Ok(()) } else { Err(problems.join(", ")) }
+}
+
+/// Friction, restitution and density that make no physical sense, as one message.
+fn check_material(material: &game_object::ColliderMaterial) -> Result<(), String> {
+    let mut problems: Vec<String> = Vec::new();
+    if material.friction < 0.0 {
+        problems.push(format!("friction can't be negative, got {}", material.friction));
+    }
+    if !(0.0..=1.0).contains(&material.restitution) {
+        problems.push(format!("restitution goes from 0 to 1, got {}", material.restitution));
+    }
+    if material.density < 0.0 {
+        problems.push(format!("density can't be negative, got {}", material.density));
+    }
+
+    if problems.is_empty() { Ok(()) } else { Err(problems.join(", ")) }
+}
+
+fn position_of(position: &(f32, f32, f32)) -> Vector3<f32> {
+    vector![position.0, position.1, position.2]
+}
+
+/// One collider of a level object (`model` and `scale` are the object's, for the mesh
+/// derived ones), checked so a typo in the level doesn't quietly turn
+/// into a shape rapier can't work with.
+fn build_collider(collider_data: &game_object::ColliderType, model: &str, scale: Vector3<f32>) -> Result<Collider, String> {
+    let collider = match collider_data {
+        game_object::ColliderType::Cuboid { half_extents, position } => {
+            if half_extents.0 <= 0.0 || half_extents.1 <= 0.0 || half_extents.2 <= 0.0 {
+                return Err(format!("Cuboid half extents must be positive, got {:?}", half_extents));
+            }
+            ColliderBuilder::cuboid(half_extents.0, half_extents.1, half_extents.2)
+                .translation(position_of(position))
+                .build()
+        },
+        game_object::ColliderType::Ball { radius, position } => {
+            if *radius <= 0.0 {
+                return Err(format!("Ball radius must be positive, got {}", radius));
+            }
+            ColliderBuilder::ball(*radius).translation(position_of(position)).build()
+        },
+        game_object::ColliderType::Cylinder { half_height, radius, position } => {
+            if *half_height <= 0.0 || *radius <= 0.0 {
+                return Err(format!("Cylinder half height and radius must be positive, got {} and {}", half_height, radius));
+            }
+            ColliderBuilder::cylinder(*half_height, *radius).translation(position_of(position)).build()
+        },
+        game_object::ColliderType::HeightField { heights, scale_x, scale_y } => {
+            let rows = heights.len();
+            let columns = heights.first().map_or(0, |row| row.len());
+            if rows < 2 || columns < 2 {
+                return Err(format!("HeightField needs at least 2x2 heights, got {}x{}", rows, columns));
+            }
+            if let Some(row) = heights.iter().position(|row| row.len() != columns) {
+                return Err(format!("HeightField row {} has {} heights instead of {}", row, heights[row].len(), columns));
+            }
+            if *scale_x <= 0.0 {
+                return Err(format!("HeightField scale_x must be positive, got {}", scale_x));
+            }
+
+            let matrix = DMatrix::from_fn(rows, columns, |row, column| heights[row][column]);
+            let scale_z = scale_x * (rows - 1) as f32 / (columns - 1) as f32;
+            ColliderBuilder::heightfield(matrix, vector![*scale_x, *scale_y, scale_z]).build()
+        },
+        game_object::ColliderType::HalfSpace { normal } => {
+            match Unit::try_new(*normal, 1.0e-6) {
+                Some(normal) => ColliderBuilder::halfspace(normal).build(),
+                None => return Err("HalfSpace normal can't be zero".to_string()),
+            }
+        },
+        game_object::ColliderType::TriMesh { mesh } => {
+            let (vertices, indices) = load_model_geometry(model, mesh.as_deref(), scale)?;
+            ColliderBuilder::trimesh(vertices, indices)
+                .map_err(|error| format!("TriMesh from '{}' is invalid: {:?}", model, error))?
+                .build()
+        },
+        game_object::ColliderType::ConvexHull { mesh } => {
+            let (vertices, _) = load_model_geometry(model, mesh.as_deref(), scale)?;
+            ColliderBuilder::convex_hull(&vertices)
+                .ok_or_else(|| format!("ConvexHull from '{}' is degenerate (flat or too few points)", model))?
+                .build()
+        },
+        game_object::ColliderType::Sensor { trigger, collider } => {
+            if trigger.is_empty() {
+                return Err("Sensor needs a trigger name".to_string());
+            }
+            if let game_object::ColliderType::Sensor { .. } = **collider {
+                return Err(format!("Sensor '{}' can't wrap another Sensor", trigger));
+            }
+            let mut sensor = build_collider(collider, model, scale)?;
+            sensor.set_sensor(true);
+            // A trigger volume around a plane mustn't add to its mass
+            sensor.set_density(0.0);
+            sensor
+        },
+        game_object::ColliderType::Material { collider, .. } => {
+            // Applied by build_physics_element, only the shape is built here
+            match **collider {
+                game_object::ColliderType::Sensor { .. } => return Err("Material goes inside the Sensor, not around it".to_string()),
+                game_object::ColliderType::Material { .. } => return Err("Material can't wrap another Material".to_string()),
+                _ => build_collider(collider, model, scale)?,
+            }
+        },
+    };
+
+    Ok(collider)
+}
+
+// Vertices and the triangles between them, as indices into the vertices
+type ModelGeometry = (Vec<Point3<f32>>, Vec<[u32; 3]>);
+
+/// Triangles of a glTF model (from the `res` folder, like the rendered models) in the
+/// object's local space: every node's transform applied and then the object's scale.
+/// With `mesh` only the node with that name is read.
+pub fn load_model_geometry(model: &str, mesh: Option<&str>, scale: Vector3<f32>) -> Result<ModelGeometry, String> {
+    let path = std::path::Path::new(env!("OUT_DIR")).join("res").join(model);
+    // Only the buffers, the textures aren't needed for collision
+    let gltf = gltf::Gltf::open(&path).map_err(|error| format!("Couldn't read '{}': {}", model, error))?;
+    let buffers = gltf::import_buffers(&gltf.document, path.parent(), gltf.blob.clone())
+        .map_err(|error| format!("Couldn't read the buffers of '{}': {}", model, error))?;
+    let document = gltf.document;
+
+    let mut vertices: Vec<Point3<f32>> = Vec::new();
+    let mut indices: Vec<[u32; 3]> = Vec::new();
+    let scale = Matrix4::new_nonuniform_scaling(&scale);
+
+    let mut nodes: Vec<(gltf::Node, Matrix4<f32>)> = document.scenes()
+        .flat_map(|scene| scene.nodes())
+        .map(|node| (node, scale))
+        .collect();
+
+    while let Some((node, parent_transform)) = nodes.pop() {
+        let transform = parent_transform * Matrix4::from(node.transform().matrix());
+        nodes.extend(node.children().map(|child| (child, transform)));
+
+        let Some(node_mesh) = node.mesh() else { continue };
+        if mesh.is_some() && node.name() != mesh {
+            continue;
+        }
+
+        for primitive in node_mesh.primitives() {
+            let reader = primitive.reader(|buffer| buffers.get(buffer.index()).map(|data| &data.0[..]));
+            let Some(positions) = reader.read_positions() else { continue };
+
+            let first = vertices.len() as u32;
+            let count = positions.len() as u32;
+            vertices.extend(positions.map(|position| transform.transform_point(&Point3::from(position))));
+
+            match reader.read_indices() {
+                Some(primitive_indices) => {
+                    let primitive_indices: Vec<u32> = primitive_indices.into_u32().collect();
+                    indices.extend(primitive_indices.chunks_exact(3).map(|triangle| [first + triangle[0], first + triangle[1], first + triangle[2]]));
+                },
+                None => indices.extend((0..count / 3).map(|triangle| [first + triangle * 3, first + triangle * 3 + 1, first + triangle * 3 + 2])),
+            }
+        }
+    }
+
+    if vertices.is_empty() {
+        return Err(match mesh {
+            Some(mesh) => format!("'{}' has no mesh called '{}'", model, mesh),
+            None => format!("'{}' has no mesh geometry", model),
+        });
+    }
+
+    Ok((vertices, indices))
+}
+
+/// The level's atmosphere settings, ISA standard day if the scene doesn't declare any.
+pub fn load_atmosphere_from_level(mut level_path: String) -> Atmosphere {
+    level_path += "/data.ron";
+
+    match load_scene(level_path) {
+        Some(scene) => scene.atmosphere,
+        None => Atmosphere::default(),
+    }
+}
+
+/// The level's physics thread settings (tick rate...), the defaults if the scene doesn't declare any.
+pub fn load_physics_settings_from_level(mut level_path: String) -> PhysicsSettings {
+    level_path += "/data.ron";
+
+    match load_scene(level_path) {
+        Some(scene) => scene.physics,
+        None => PhysicsSettings::default(),
+    }
+}
+
+/// The joints between the level's objects, none if the scene doesn't declare any.
+pub fn load_joints_from_level(mut level_path: String) -> Vec<JointDefinition> {
+    level_path += "/data.ron";
+
+    match load_scene(level_path) {
+        Some(scene) => scene.joints,
+        None => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::physics::events::PhysicsEventKind;
+    use crate::engine::physics::physics_handler::Physics;
+    use crate::engine::physics::test_world::{add_terrain, body, hill, physics_object};
+
+    #[test]
+    fn invalid_colliders_are_load_errors() {
+        let scale = Vector3::new(1.0, 1.0, 1.0);
+        let ragged = game_object::ColliderType::HeightField { heights: vec![vec![0.0, 1.0], vec![0.0]], scale_x: 10.0, scale_y: 1.0 };
+
+        assert!(build_collider(&game_object::ColliderType::Ball { radius: 0.0, position: (0.0, 0.0, 0.0) }, "", scale).is_err());
+        assert!(build_collider(&ragged, "", scale).is_err());
+        assert!(build_collider(&game_object::ColliderType::TriMesh { mesh: None }, "missing/missing.gltf", scale).is_err());
+        assert!(build_collider(&game_object::ColliderType::ConvexHull { mesh: Some("not_a_node".to_string()) }, "F16/f16.gltf", scale).is_err());
+
+        let ball = game_object::ColliderType::Ball { radius: 1.0, position: (0.0, 0.0, 0.0) };
+        let sensor = game_object::ColliderType::Sensor { trigger: "ring".to_string(), collider: Box::new(ball.clone()) };
+        assert!(build_collider(&sensor, "", scale).unwrap().is_sensor());
+        assert!(build_collider(&game_object::ColliderType::Sensor { trigger: "rings".to_string(), collider: Box::new(sensor.clone()) }, "", scale).is_err());
+        assert!(build_collider(&game_object::ColliderType::Material { material: game_object::ColliderMaterial::default(), collider: Box::new(sensor) }, "", scale).is_err());
+    }
+
+    #[test]
+    fn colliders_with_a_material_of_their_own_ignore_the_body_one() {
+        let ice = game_object::ColliderMaterial { friction: 0.0, restitution: 0.9, ..game_object::ColliderMaterial::default() };
+        let ball = game_object::ColliderType::Ball { radius: 1.0, position: (0.0, 0.0, 0.0) };
+        let body = game_object::RigidBodyData {
+            is_static: true,
+            mass: 1.0,
+            center_of_mass: Vector3::zeros(),
+            initial_velocity: Vector3::zeros(),
+            initial_angular_velocity: Vector3::zeros(),
+            linear_damping: 0.0,
+            angular_damping: 2.0,
+            ccd: false,
+            gravity_scale: 1.0,
+        };
+        let game_object = GameObject {
+            id: "rink".to_string(),
+            model: String::new(),
+            transform: game_object::Transform { position: Vector3::zeros(), rotation: nalgebra::UnitQuaternion::identity(), scale: Vector3::new(1.0, 1.0, 1.0) },
+            children: Vec::new(),
+            metadata: game_object::MetaData {
+                physics: Some(game_object::Physics {
+                    rigidbody: body,
+                    colliders: vec![ball.clone(), game_object::ColliderType::Material { material: ice, collider: Box::new(ball) }],
+                    material: Some(game_object::ColliderMaterial { friction: 1.0, ..game_object::ColliderMaterial::default() }),
+                }),
+                cameras: None,
+                lighting: None,
+            },
+        };
+
+        let (mut collider_set, mut rigidbody_set, mut errors) = (ColliderSet::new(), RigidBodySet::new(), Vec::new());
+        let physics_data = build_physics_element(&game_object, &mut collider_set, &mut rigidbody_set, &mut errors).unwrap();
+        assert!(errors.is_empty(), "{:?}", errors);
+
+        let surfaces: Vec<(f32, f32)> = physics_data.collider_handles.iter().map(|handle| (collider_set[*handle].friction(), collider_set[*handle].restitution())).collect();
+        assert_eq!(surfaces, vec![(1.0, 0.0), (0.0, 0.9)]);
+    }
+
+    #[test]
+    fn unphysical_body_parameters_are_load_errors() {
+        let body = game_object::RigidBodyData {
+            is_static: false,
+            mass: 1.0,
+            center_of_mass: Vector3::zeros(),
+            initial_velocity: Vector3::zeros(),
+            initial_angular_velocity: Vector3::zeros(),
+            linear_damping: 0.0,
+            angular_damping: 2.0,
+            ccd: false,
+            gravity_scale: 1.0,
+        };
+        let material = game_object::ColliderMaterial::default();
+
+        assert!(check_body_parameters(&body, &material).is_ok());
+        assert!(check_body_parameters(&game_object::RigidBodyData { linear_damping: -1.0, ..body.clone() }, &material).is_err());
+        assert!(check_body_parameters(&body, &game_object::ColliderMaterial { friction: -0.5, ..material.clone() }).is_err());
+        assert!(check_body_parameters(&body, &game_object::ColliderMaterial { restitution: 1.5, ..material.clone() }).is_err());
+        assert!(check_body_parameters(&body, &game_object::ColliderMaterial { density: -1.0, ..material }).is_err());
+    }
+
+    #[test]
+    fn mesh_colliders_follow_the_model_and_its_scale() {
+        let (vertices, indices) = load_model_geometry("F16/f16.gltf", None, Vector3::new(1.0, 1.0, 1.0)).unwrap();
+        let (scaled, _) = load_model_geometry("F16/f16.gltf", None, Vector3::new(14.0, 14.0, 14.0)).unwrap();
+        assert!(!indices.is_empty());
+        assert!(indices.iter().flatten().all(|index| (*index as usize) < vertices.len()));
+        assert!((scaled[0].coords - vertices[0].coords * 14.0).norm() < 1.0e-3);
+
+        // The main frame node runs from z -0.485 to 1.550 in the model
+        let hull = build_collider(&game_object::ColliderType::ConvexHull { mesh: Some("MainFrame".to_string()) }, "F16/f16.gltf", Vector3::new(14.0, 14.0, 14.0)).unwrap();
+        let extents = hull.compute_aabb().extents();
+        assert!((extents.z - 2.035 * 14.0).abs() < 0.1, "{}m long", extents.z);
+        assert!(build_collider(&game_object::ColliderType::TriMesh { mesh: None }, "F16/f16.gltf", Vector3::new(14.0, 14.0, 14.0)).is_ok());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn flying_into_a_hill_crashes_into_the_terrain() {
+        let heightmap = hill();
+        let mut physics = Physics::new();
+        add_terrain(&mut physics, &heightmap);
+        // At the hill's side from 300m up, where it's 525m from the peak
+        let ball = game_object::ColliderType::Ball { radius: 1.0, position: (0.0, 0.0, 0.0) };
+        let level = game_object::RigidBodyData { gravity_scale: 0.0, ..body(false, Vector3::new(0.0, 0.0, 200.0), false) };
+        physics.spawn(&physics_object("player", Vector3::new(0.0, 300.0, -1500.0), level, ball)).unwrap();
+
+        let mut impact = None;
+        for _ in 0..(10.0 / physics.integration_parameters.dt) as usize {
+            physics.step();
+            impact = physics.events.iter().find(|event| event.kind == PhysicsEventKind::CollisionStarted).cloned();
+            if impact.is_some() {
+                break;
+            }
+        }
+
+        let impact = impact.expect("the player flew through the hill");
+        assert_eq!(impact.other("player"), Some("terrain"));
+        // Where the heightmap (and so the render mesh) has the ground
+        let point = impact.contact_point.unwrap();
+        assert!((point.z + 525.0).abs() < 10.0 && (heightmap.height_at(point.x, point.z) - point.y).abs() < 1.0, "hit at {:?}", point);
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::VecDeque;
+
+use nalgebra::{Isometry3, Vector3};
+use rapier3d::prelude::{RigidBodyHandle, RigidBodySet};
+
+#[derive(Debug, Clone)]
+struct BodySnapshot {
+    handle: RigidBodyHandle,
+    position: Isometry3<f32>,
+    linvel: Vector3<f32>,
+    angvel: Vector3<f32>,
+}
+
+#[derive(Debug, Clone)]
+struct WorldSnapshot {
+    time: f64,
+    bodies: Vec<BodySnapshot>,
+}
+
+/// # Rewind Buffer
+///
+/// Ring buffer with the state of every dynamic rigidbody after each of the last steps,
+/// so the world can be put back a few seconds to retry a maneuver while tuning.
+///
+/// Only the rigidbodies go back: whatever the ticks integrate on their own (fuel,
+/// engine spool, surface positions...) carries on from where it was, joints that broke
+/// stay broken and the events of the rewound steps were already handed out. A
+/// `PhysicsSnapshot` is what brings all of it back.
+pub struct RewindBuffer {
+    snapshots: VecDeque<WorldSnapshot>,
+    capacity: usize,
+}
+
+impl RewindBuffer {
+    pub fn new(capacity: usize) -> Self {
+        Self { snapshots: VecDeque::with_capacity(capacity), capacity }
+    }
+
+    /// Stores the world as it is at `time`, dropping the oldest snapshot once full.
+    pub fn record(&mut self, time: f64, rigidbody_set: &RigidBodySet) {
+        if self.capacity == 0 {
+            return;
+        }
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        let bodies = rigidbody_set.iter()
+            .filter(|(_, rigidbody)| rigidbody.is_dynamic())
+            .map(|(handle, rigidbody)| BodySnapshot {
+                handle,
+                position: *rigidbody.position(),
+                linvel: *rigidbody.linvel(),
+                angvel: *rigidbody.angvel(),
+            })
+            .collect();
+
+        self.snapshots.push_back(WorldSnapshot { time, bodies });
+    }
+
+    pub fn clear(&mut self) {
+        self.snapshots.clear();
+    }
+
+    /// Moves everything recorded by `-shift`, like the world was when the origin was rebased.
+    pub fn shift(&mut self, shift: Vector3<f32>) {
+        for body in self.snapshots.iter_mut().flat_map(|snapshot| snapshot.bodies.iter_mut()) {
+            body.position.translation.vector -= shift;
+        }
+    }
+
+    /// Seconds of history available to rewind over.
+    pub fn available(&self) -> f32 {
+        match (self.snapshots.front(), self.snapshots.back()) {
+            (Some(oldest), Some(newest)) => (newest.time - oldest.time) as f32,
+            _ => 0.0,
+        }
+    }
+
+    /// Puts the rigidbodies back to the newest snapshot at least `seconds` older than the
+    /// last one (or the oldest one kept), forgetting everything after it. Returns the time
+    /// the world is now at, None if there's nothing recorded.
+    pub fn rewind(&mut self, seconds: f32, rigidbody_set: &mut RigidBodySet) -> Option<f64> {
+        let target = self.snapshots.back()?.time - seconds as f64;
+        while self.snapshots.len() > 1 && self.snapshots.back().is_some_and(|snapshot| snapshot.time > target) {
+            self.snapshots.pop_back();
+        }
+
+        let snapshot = self.snapshots.back()?;
+        for body in &snapshot.bodies {
+            if let Some(rigidbody) = rigidbody_set.get_mut(body.handle) {
+                rigidbody.set_position(body.position, true);
+                rigidbody.set_linvel(body.linvel, true);
+                rigidbody.set_angvel(body.angvel, true);
+            }
+        }
+
+        Some(snapshot.time)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rapier3d::prelude::RigidBodyBuilder;
+
+    #[test]
+    fn rewinds_to_an_older_snapshot_and_forgets_the_rest() {
+        let mut rigidbody_set = RigidBodySet::new();
+        let handle = rigidbody_set.insert(RigidBodyBuilder::dynamic().build());
+        let mut buffer = RewindBuffer::new(3);
+
+        for step in 0..5 {
+            rigidbody_set[handle].set_translation(Vector3::new(step as f32, 0.0, 0.0), true);
+            buffer.record(step as f64, &rigidbody_set);
+        }
+        // Only the last 3 steps (2, 3 and 4) are kept
+        assert_eq!(buffer.available(), 2.0);
+
+        assert_eq!(buffer.rewind(1.5, &mut rigidbody_set), Some(2.0));
+        assert_eq!(rigidbody_set[handle].translation().x, 2.0);
+        assert_eq!(buffer.available(), 0.0);
+    }
+}
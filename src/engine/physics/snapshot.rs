@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+use std::path::Path;
+
+use nalgebra::Vector3;
+use rapier3d::prelude::{CCDSolver, ColliderSet, DefaultBroadPhase, ImpulseJointSet, IntegrationParameters, IslandManager, MultibodyJointSet, NarrowPhase, RigidBodySet};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::physics::floating_origin::FloatingOrigin;
+use crate::engine::physics::joints::JointData;
+use crate::engine::physics::physics_handler::PhysicsData;
+
+/// # Physics Snapshot
+///
+/// Everything needed to carry on a simulation from one exact step: rapier's sets and
+/// pipeline state (contacts, islands, broad phase...) plus what the ticks integrate on
+/// their own, see `Physics::snapshot` and `Physics::restore`.
+///
+/// ## Contents:
+/// - **Saving**: written as RON, like the rest of the game's files. A quicksave and a file
+///   attached to a bug report are the same thing.
+/// - **Determinism**: a restored world steps exactly like the one that was saved, the
+///   headless `FlightSimulation` can load one to replay a bug.
+/// - **Not included**: the atmosphere and the physics settings come from the level, and
+///   the rewind history is left behind.
+#[derive(Serialize, Deserialize)]
+pub struct PhysicsSnapshot {
+    pub time: f64,
+    #[serde(default)] // snapshots from before the origin could move were all at the level's
+    pub origin: FloatingOrigin,
+    pub gravity: Vector3<f32>,
+    pub integration_parameters: IntegrationParameters,
+    pub island_manager: IslandManager,
+    pub broad_phase: DefaultBroadPhase,
+    pub narrow_phase: NarrowPhase,
+    pub rigidbody_set: RigidBodySet,
+    pub collider_set: ColliderSet,
+    pub impulse_joint_set: ImpulseJointSet,
+    pub multibody_joint_set: MultibodyJointSet,
+    pub ccd_solver: CCDSolver,
+    pub physics_elements: HashMap<String, Option<PhysicsData>>,
+    #[serde(default)] // snapshots from before levels had joints
+    pub joints: HashMap<String, JointData>,
+    pub tick_states: HashMap<String, String>, // PhysicsTick::save_state
+}
+
+impl PhysicsSnapshot {
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        if let Some(folder) = Path::new(path).parent() {
+            std::fs::create_dir_all(folder).map_err(|error| format!("Couldn't create '{}': {}", folder.display(), error))?;
+        }
+
+        let contents = ron::to_string(self).map_err(|error| format!("Couldn't serialize the physics: {}", error))?;
+        std::fs::write(path, contents).map_err(|error| format!("Couldn't write '{}': {}", path, error))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path).map_err(|error| format!("Couldn't read '{}': {}", path, error))?;
+        ron::from_str(&contents).map_err(|error| format!("'{}' isn't a physics snapshot: {}", path, error))
+    }
+}
@@ -0,0 +1,66 @@
+use image::{DynamicImage, ImageBuffer, Luma};
+use nalgebra::{UnitQuaternion, Vector3};
+
+use crate::engine::game_nodes::game_object::{ColliderType, GameObject, MetaData, Physics, RigidBodyData, Transform};
+use crate::engine::physics::physics_handler;
+use crate::engine::physics::physics_resources::build_heightfield_element;
+use crate::engine::terrain::heightmap::{Heightmap, TerrainSettings};
+
+// What the engine's physics tests build their worlds from, with no level or model to load
+
+/// A game object with only physics, one collider that doesn't need a model.
+pub fn physics_object(id: &str, position: Vector3<f32>, rigidbody: RigidBodyData, collider: ColliderType) -> GameObject {
+    GameObject {
+        id: id.to_string(),
+        model: String::new(),
+        transform: Transform { position, rotation: UnitQuaternion::identity(), scale: Vector3::new(1.0, 1.0, 1.0) },
+        children: vec![],
+        metadata: MetaData {
+            physics: Some(Physics { rigidbody, colliders: vec![collider], material: None }),
+            cameras: None,
+            lighting: None,
+        },
+    }
+}
+
+/// 100kg with no damping, the rest as a level leaves it by default.
+pub fn body(is_static: bool, initial_velocity: Vector3<f32>, ccd: bool) -> RigidBodyData {
+    RigidBodyData {
+        is_static,
+        mass: 100.0,
+        center_of_mass: Vector3::zeros(),
+        initial_velocity,
+        initial_angular_velocity: Vector3::zeros(),
+        linear_damping: 0.0,
+        angular_damping: 0.0,
+        ccd,
+        gravity_scale: 1.0,
+    }
+}
+
+/// A 1000m hill in the middle of 4km of flat ground, 33 samples (125m) a side.
+pub fn hill() -> Heightmap {
+    let settings = TerrainSettings {
+        heightmap: "hill".to_string(),
+        size: 4000.0,
+        min_height: 0.0,
+        max_height: 1000.0,
+        chunk_cells: 32,
+        lod_distances: vec![],
+        splat_map: None,
+        layers: vec![],
+        layer_tiling: 20.0,
+    };
+    // A cone 1500m across, its peak on the center sample
+    let image = ImageBuffer::from_fn(33, 33, |x, y| {
+        let distance = ((x as f32 - 16.0).powi(2) + (y as f32 - 16.0).powi(2)).sqrt() * 125.0;
+        Luma([((1.0 - distance / 750.0).max(0.0) * u16::MAX as f32) as u16])
+    });
+    Heightmap::from_image(&DynamicImage::ImageLuma16(image), &settings).unwrap()
+}
+
+/// Adds `heightmap` as the world's "terrain", like a level with a `terrain` block has.
+pub fn add_terrain(physics: &mut physics_handler::Physics, heightmap: &Heightmap) {
+    let terrain = build_heightfield_element(heightmap, &mut physics.collider_set, &mut physics.rigidbody_set);
+    physics.physics_elements.insert("terrain".to_string(), Some(terrain));
+}
@@ -0,0 +1,297 @@
+use nalgebra::Vector3;
+use serde::Deserialize;
+
+// Exponent of the power law the wind slows down with towards the ground inside the boundary layer
+const BOUNDARY_LAYER_EXPONENT: f32 = 1.0 / 7.0;
+// Octaves of noise summed for the turbulence, each one half the size and strength of the last
+const TURBULENCE_OCTAVES: usize = 3;
+// RMS of the summed octaves, measured, so `intensity` comes out as the RMS of each component
+const TURBULENCE_NOISE_RMS: f32 = 0.31;
+// Edge vectors of a cube, the gradients Perlin's improved noise picks from
+const GRADIENTS: [[f32; 3]; 12] = [
+    [1.0, 1.0, 0.0], [-1.0, 1.0, 0.0], [1.0, -1.0, 0.0], [-1.0, -1.0, 0.0],
+    [1.0, 0.0, 1.0], [-1.0, 0.0, 1.0], [1.0, 0.0, -1.0], [-1.0, 0.0, -1.0],
+    [0.0, 1.0, 1.0], [0.0, -1.0, 1.0], [0.0, 1.0, -1.0], [0.0, -1.0, -1.0],
+];
+
+fn default_turbulence_scale() -> f32 { 300.0 }
+fn default_gust_falloff() -> f32 { 50.0 }
+
+/// Wind at one altitude of a shear profile.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WindLayer {
+    pub altitude: f32,          // m
+    pub velocity: Vector3<f32>, // m/s
+}
+
+/// Random eddies frozen in the air mass and carried along with the mean wind, so a plane
+/// flying through them feels faster changes the faster it goes (Taylor's hypothesis, the
+/// same assumption the Dryden spectra make).
+#[derive(Debug, Deserialize, Clone)]
+pub struct Turbulence {
+    pub intensity: f32, // m/s, RMS of each component
+    #[serde(default = "default_turbulence_scale")]
+    pub scale: f32,     // m, size of the biggest eddies
+    #[serde(default)]
+    pub seed: u32,
+}
+
+/// Localized air movement declared by the level.
+#[derive(Debug, Deserialize, Clone)]
+pub enum AirVolume {
+    /// Box of air moving at `velocity` on top of the wind, fading out over `falloff` meters
+    /// inside its faces. With a `period` it blows as a repeating 1-cosine gust instead of steadily.
+    Gust {
+        center: Vector3<f32>,
+        half_extents: Vector3<f32>,
+        velocity: Vector3<f32>,
+        #[serde(default = "default_gust_falloff")]
+        falloff: f32,
+        #[serde(default)]
+        period: Option<f32>, // s
+    },
+    /// Rising column from the ground up to `top`, strongest in the middle and with a ring of
+    /// sinking air around `radius` where the air that went up comes back down.
+    Thermal {
+        center: Vector3<f32>, // only x and z are used, thermals start at the ground
+        radius: f32,          // m, where the updraft turns into sink
+        top: f32,             // m, altitude the thermal dies out at
+        updraft: f32,         // m/s in the core
+    },
+}
+
+/// # Wind
+///
+/// The air mass's own velocity, so the aero forces can use airspeed instead of
+/// the speed over the ground.
+///
+/// ## Contents:
+/// - **Steady wind**: `velocity`, or a shear profile of `layers` interpolated by altitude (which replaces it).
+/// - **Boundary layer**: below `boundary_layer` meters the wind slows down towards the ground (1/7 power law).
+/// - **Turbulence**: Perlin noise eddies carried with the wind.
+/// - **Volumes**: gusts and thermals placed in the level.
+///
+/// ## Level file:
+/// ```ron
+/// atmosphere: (
+///     wind: (
+///         velocity: (5.0, 0.0, 0.0),
+///         layers: [ (altitude: 0.0, velocity: (5.0, 0.0, 0.0)), (altitude: 6000.0, velocity: (25.0, 0.0, 10.0)) ],
+///         boundary_layer: 300.0,
+///         turbulence: Some(( intensity: 1.5, scale: 300.0 )),
+///         volumes: [
+///             Gust( center: (0.0, 6000.0, 3000.0), half_extents: (500.0, 200.0, 100.0), velocity: (0.0, 10.0, 0.0), period: Some(4.0) ),
+///             Thermal( center: (2000.0, 0.0, 0.0), radius: 200.0, top: 2500.0, updraft: 4.0 ),
+///         ],
+///     ),
+/// ),
+/// ```
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Wind {
+    #[serde(default)]
+    pub velocity: Vector3<f32>, // m/s, the way the air moves (not where it comes from)
+    #[serde(default)]
+    pub layers: Vec<WindLayer>, // sorted by altitude
+    #[serde(default)]
+    pub boundary_layer: f32,    // m, 0 for the same wind down to the ground
+    #[serde(default)]
+    pub turbulence: Option<Turbulence>,
+    #[serde(default)]
+    pub volumes: Vec<AirVolume>,
+    // Level position of the physics world's origin, the air is placed in the level (see FloatingOrigin)
+    #[serde(skip)]
+    pub origin: Vector3<f32>,
+}
+
+impl Wind {
+    /// Air velocity at `position` of the physics world, `time` seconds into the simulation.
+    pub fn sample(&self, position: &Vector3<f32>, time: f32) -> Vector3<f32> {
+        let position = &(position + self.origin);
+        let steady = self.steady(position.y);
+
+        let turbulence = match &self.turbulence {
+            Some(turbulence) => turbulence.sample(&(position - steady * time)),
+            None => Vector3::zeros(),
+        };
+
+        let volumes: Vector3<f32> = self.volumes.iter().map(|volume| volume.sample(position, time)).sum();
+
+        steady + turbulence + volumes
+    }
+
+    /// Mean wind at an altitude, shear and boundary layer included.
+    pub fn steady(&self, altitude: f32) -> Vector3<f32> {
+        let wind = match (self.layers.first(), self.layers.last()) {
+            (Some(first), _) if altitude <= first.altitude => first.velocity,
+            (_, Some(last)) if altitude >= last.altitude => last.velocity,
+            (Some(_), Some(_)) => {
+                let upper = self.layers.iter().position(|layer| layer.altitude > altitude).unwrap_or(self.layers.len() - 1);
+                let (below, above) = (&self.layers[upper - 1], &self.layers[upper]);
+                let t = (altitude - below.altitude) / (above.altitude - below.altitude);
+                below.velocity.lerp(&above.velocity, t)
+            },
+            _ => self.velocity,
+        };
+
+        if self.boundary_layer > 0.0 && altitude < self.boundary_layer {
+            wind * (altitude.max(0.0) / self.boundary_layer).powf(BOUNDARY_LAYER_EXPONENT)
+        } else {
+            wind
+        }
+    }
+}
+
+impl Turbulence {
+    fn sample(&self, position: &Vector3<f32>) -> Vector3<f32> {
+        let point = position / self.scale.max(f32::EPSILON);
+
+        Vector3::new(
+            fractal_noise(&point, self.seed.wrapping_mul(3)),
+            fractal_noise(&point, self.seed.wrapping_mul(3).wrapping_add(1)),
+            fractal_noise(&point, self.seed.wrapping_mul(3).wrapping_add(2)),
+        ) * (self.intensity / TURBULENCE_NOISE_RMS)
+    }
+}
+
+impl AirVolume {
+    fn sample(&self, position: &Vector3<f32>, time: f32) -> Vector3<f32> {
+        match self {
+            AirVolume::Gust { center, half_extents, velocity, falloff, period } => {
+                let offset = position - center;
+                let mut strength = 1.0;
+                for axis in 0..3 {
+                    let inside = half_extents[axis] - offset[axis].abs();
+                    if inside <= 0.0 {
+                        return Vector3::zeros();
+                    }
+                    strength *= smoothstep((inside / falloff.max(f32::EPSILON)).min(1.0));
+                }
+
+                if let Some(period) = period.filter(|period| *period > 0.0) {
+                    strength *= 0.5 * (1.0 - (2.0 * std::f32::consts::PI * time / period).cos());
+                }
+
+                velocity * strength
+            },
+            AirVolume::Thermal { center, radius, top, updraft } => {
+                if position.y < 0.0 || position.y > *top {
+                    return Vector3::zeros();
+                }
+
+                let distance_sq = (position.x - center.x).powi(2) + (position.z - center.z).powi(2);
+                let r_sq = distance_sq / radius.max(f32::EPSILON).powi(2);
+                // Dies out over the top fifth of the column
+                let fade = ((top - position.y) / (0.2 * top)).min(1.0);
+
+                Vector3::new(0.0, updraft * (1.0 - r_sq) * (-r_sq).exp() * fade, 0.0)
+            },
+        }
+    }
+}
+
+fn smoothstep(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Lattice corner hash, any well mixed integer hash does
+fn hash(x: i32, y: i32, z: i32, seed: u32) -> u32 {
+    let mut h = (x as u32).wrapping_mul(0x8da6_b343)
+        ^ (y as u32).wrapping_mul(0xd816_3841)
+        ^ (z as u32).wrapping_mul(0xcb1a_b31f)
+        ^ seed.wrapping_mul(0x1656_67b1);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0x5bd1_e995);
+    h ^ (h >> 15)
+}
+
+/// Perlin's improved gradient noise, from about -1 to 1 and 0 at every lattice point.
+fn gradient_noise(point: &Vector3<f32>, seed: u32) -> f32 {
+    let cell = point.map(|value| value.floor());
+    let local = point - cell;
+    let fade = local.map(|t| t * t * t * (t * (t * 6.0 - 15.0) + 10.0));
+    let (x, y, z) = (cell.x as i32, cell.y as i32, cell.z as i32);
+
+    let corner = |dx: i32, dy: i32, dz: i32| {
+        let gradient = GRADIENTS[(hash(x + dx, y + dy, z + dz, seed) % 12) as usize];
+        let offset = local - Vector3::new(dx as f32, dy as f32, dz as f32);
+        gradient[0] * offset.x + gradient[1] * offset.y + gradient[2] * offset.z
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let x00 = lerp(corner(0, 0, 0), corner(1, 0, 0), fade.x);
+    let x10 = lerp(corner(0, 1, 0), corner(1, 1, 0), fade.x);
+    let x01 = lerp(corner(0, 0, 1), corner(1, 0, 1), fade.x);
+    let x11 = lerp(corner(0, 1, 1), corner(1, 1, 1), fade.x);
+
+    lerp(lerp(x00, x10, fade.y), lerp(x01, x11, fade.y), fade.z)
+}
+
+fn fractal_noise(point: &Vector3<f32>, seed: u32) -> f32 {
+    (0..TURBULENCE_OCTAVES)
+        .map(|octave| {
+            let frequency = (1 << octave) as f32;
+            gradient_noise(&(point * frequency), seed.wrapping_add(octave as u32 * 101)) / frequency
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shear_layers_and_boundary_layer() {
+        let wind = Wind {
+            layers: vec![
+                WindLayer { altitude: 1000.0, velocity: Vector3::new(10.0, 0.0, 0.0) },
+                WindLayer { altitude: 3000.0, velocity: Vector3::new(30.0, 0.0, 10.0) },
+            ],
+            boundary_layer: 500.0,
+            ..Default::default()
+        };
+
+        assert_eq!(wind.steady(2000.0), Vector3::new(20.0, 0.0, 5.0));
+        assert_eq!(wind.steady(9000.0), Vector3::new(30.0, 0.0, 10.0));
+        assert_eq!(wind.steady(0.0), Vector3::zeros());
+        assert!(wind.steady(100.0).x > 5.0 && wind.steady(100.0).x < 10.0);
+    }
+
+    #[test]
+    fn turbulence_has_the_requested_intensity() {
+        let turbulence = Turbulence { intensity: 2.0, scale: 100.0, seed: 7 };
+        let samples: Vec<Vector3<f32>> = (0..20_000)
+            .map(|i| turbulence.sample(&Vector3::new(i as f32 * 13.7, 500.0 + (i % 97) as f32 * 31.0, (i % 89) as f32 * 17.0)))
+            .collect();
+
+        let mean = samples.iter().sum::<Vector3<f32>>() / samples.len() as f32;
+        let rms = (samples.iter().map(|sample| sample.x * sample.x).sum::<f32>() / samples.len() as f32).sqrt();
+
+        assert!(mean.magnitude() < 0.2, "mean turbulence {:?}", mean);
+        assert!((rms - 2.0).abs() < 0.4, "turbulence RMS {}", rms);
+        // Frozen in the air mass, the same place always blows the same
+        assert_eq!(turbulence.sample(&Vector3::new(10.0, 20.0, 30.0)), turbulence.sample(&Vector3::new(10.0, 20.0, 30.0)));
+    }
+
+    #[test]
+    fn gusts_and_thermals_only_blow_where_they_are() {
+        let wind = Wind {
+            volumes: vec![
+                AirVolume::Gust { center: Vector3::new(0.0, 1000.0, 0.0), half_extents: Vector3::new(200.0, 200.0, 200.0), velocity: Vector3::new(0.0, 10.0, 0.0), falloff: 50.0, period: Some(4.0) },
+                AirVolume::Thermal { center: Vector3::new(5000.0, 0.0, 0.0), radius: 200.0, top: 2000.0, updraft: 4.0 },
+            ],
+            ..Default::default()
+        };
+
+        assert_eq!(wind.sample(&Vector3::new(0.0, 1000.0, 0.0), 2.0), Vector3::new(0.0, 10.0, 0.0));
+        assert!(wind.sample(&Vector3::new(0.0, 1000.0, 0.0), 0.0).magnitude() < 1.0e-6);
+        assert_eq!(wind.sample(&Vector3::new(0.0, 1300.0, 0.0), 2.0), Vector3::zeros());
+
+        assert_eq!(wind.sample(&Vector3::new(5000.0, 1000.0, 0.0), 0.0), Vector3::new(0.0, 4.0, 0.0));
+        assert!(wind.sample(&Vector3::new(5300.0, 1000.0, 0.0), 0.0).y < 0.0);
+        assert_eq!(wind.sample(&Vector3::new(5000.0, 2500.0, 0.0), 0.0), Vector3::zeros());
+
+        // They stay where the level put them when the physics world's origin moves
+        let rebased = Wind { origin: Vector3::new(4000.0, 0.0, 0.0), ..wind };
+        assert_eq!(rebased.sample(&Vector3::new(1000.0, 1000.0, 0.0), 0.0), Vector3::new(0.0, 4.0, 0.0));
+    }
+}
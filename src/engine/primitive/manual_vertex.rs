@@ -5,6 +5,15 @@ pub struct ManualVertex {
     pub color: [f32; 3],
 }
 
+impl Default for ManualVertex {
+    fn default() -> Self {
+        Self { 
+            position: [0.0, 0.0, 0.0],
+            color: [0.0, 1.0, 0.0] 
+        }
+    }
+}
+
 impl ManualVertex {
     pub fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
@@ -24,13 +33,6 @@ impl ManualVertex {
             ]
         }
     }
-
-    pub fn default() -> Self {
-        Self { 
-            position: [0.0, 0.0, 0.0],
-            color: [0.0, 1.0, 0.0] 
-        }
-    }
 }
 
 #[repr(C)]
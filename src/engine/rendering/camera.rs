@@ -1,7 +1,6 @@
 use nalgebra::{Matrix4, Perspective3, Point3, UnitQuaternion, Vector3};
 use sdl2::rect::Point;
 use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, BindGroupLayoutDescriptor, Buffer, Device};
-use std::f32::consts::FRAC_PI_2;
 
 // Maps OpenGL's [-1, 1] NDC z-range to wgpu's [0, 1] range, and reverses it
 // (near -> 1, far -> 0) so depth precision concentrates on distant geometry
@@ -15,8 +14,6 @@ pub const OPENGL_TO_WGPU_MATRIX: Matrix4<f32> = Matrix4::new(
     0.0, 0.0, 0.0, 1.0,
 );
 
-const SAFE_FRAC_PI_2: f32 = FRAC_PI_2 - 0.0001;
-
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct NearFarUniform {
@@ -95,7 +92,7 @@ impl CameraRenderizable {
 
             if ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && ndc.z >= 0.0 && ndc.z <= 1.0 {
                 let x = ((ndc.x + 1.0) * 0.5) * screen_width as f32;
-                let y = ((1.0 - (ndc.y + 1.0) * 0.5)) * screen_height as f32;
+                let y = (1.0 - (ndc.y + 1.0) * 0.5) * screen_height as f32;
 
                 Some(Point::new(x as i32, y as i32))
             } else {
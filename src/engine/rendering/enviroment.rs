@@ -1,3 +1,4 @@
 pub mod environment;
 pub mod light;
 pub mod skybox_renderer;
+pub mod terrain_renderer;
@@ -82,7 +82,7 @@ impl Light {
             };
             
             rendering_utils::create_render_pipeline(
-                &device,
+                device,
                 &layout,
                 config.format,
                 Some(Texture::DEPTH_FORMAT),
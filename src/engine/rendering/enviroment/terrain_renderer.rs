@@ -0,0 +1,264 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use nalgebra::Vector3;
+use wgpu::{util::DeviceExt, BindGroup, Buffer, Queue, RenderPipeline};
+
+use crate::engine::rendering::{camera::CameraRenderizable, enviroment::light::Light, models::textures::Texture, renderer::Renderer};
+use crate::engine::terrain::heightmap::TerrainSettings;
+use crate::engine::terrain::terrain_mesh::{chunk_distance, lod_for_distance, TerrainMesh, TerrainVertex};
+
+const LAYER_COUNT: usize = 4;
+// Layers the level doesn't set are a plain grey rather than a missing binding
+const MISSING_LAYER_COLOR: [u8; 4] = [128, 128, 128, 255];
+
+impl TerrainVertex {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 3] = wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x3, 2 => Float32x2];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<TerrainVertex>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+// Where a chunk's origin is relative to the camera, written every frame
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct ChunkInstance {
+    offset: [f32; 3],
+}
+
+impl ChunkInstance {
+    const ATTRIBUTES: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![3 => Float32x3];
+
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<ChunkInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBUTES,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+struct TerrainUniform {
+    layer_repeat: f32,
+    _padding: [f32; 3],
+}
+
+struct ChunkRender {
+    vertex_buffer: Buffer,
+    origin: Vector3<f32>,
+    min: Vector3<f32>,
+    max: Vector3<f32>,
+    lod: usize, // the one picked for the camera at the last update
+}
+
+/// # Terrain Render
+///
+/// Draws the level's terrain mesh (see `TerrainMesh`) with its own pipeline, like the skybox.
+/// Every chunk keeps its own vertex buffer and all of them share the LOD index buffer.
+///
+/// ## Contents:
+/// - **LOD**: `update` picks each chunk's LOD from its distance to the camera and moves the
+///   chunks relative to it, as the models are.
+/// - **Texturing**: up to 4 tiling layers, blended with the weights of the splat map.
+pub struct TerrainRender {
+    render_pipeline: RenderPipeline,
+    bind_group: BindGroup,
+    index_buffer: Buffer,
+    instance_buffer: Buffer,
+    lods: Vec<std::ops::Range<u32>>,
+    lod_distances: Vec<f32>,
+    chunks: Vec<ChunkRender>,
+}
+
+impl TerrainRender {
+    pub fn new(renderer: &Renderer, camera: &CameraRenderizable, light: &Light, mesh: TerrainMesh, settings: &TerrainSettings, splat_map: Option<DynamicImage>, layers: Vec<DynamicImage>) -> anyhow::Result<Self> {
+        let (device, queue, config) = (&renderer.device, &renderer.queue, &renderer.config);
+        let texture_entry = |binding: u32| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("terrain_bind_group_layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                texture_entry(1),
+                texture_entry(2),
+                texture_entry(3),
+                texture_entry(4),
+                texture_entry(5),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 6,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        // Without a splat map the whole terrain is the first layer
+        let splat_map = splat_map.unwrap_or_else(|| DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba([255, 0, 0, 0]))));
+        let splat_texture = Texture::from_data_image(&splat_map, device, queue, Some("terrain_splat_map"))?;
+        let mut layer_textures = Vec::with_capacity(LAYER_COUNT);
+        for index in 0..LAYER_COUNT {
+            let texture = match layers.get(index) {
+                Some(layer) => Texture::from_image(layer, device, queue, Some("terrain_layer"))?,
+                None => Texture::from_image(&DynamicImage::ImageRgba8(RgbaImage::from_pixel(1, 1, Rgba(MISSING_LAYER_COLOR))), device, queue, Some("terrain_layer"))?,
+            };
+            layer_textures.push(texture);
+        }
+
+        let uniform = TerrainUniform { layer_repeat: settings.size / settings.layer_tiling.max(0.01), _padding: [0.0; 3] };
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain Uniform"),
+            contents: bytemuck::cast_slice(&[uniform]),
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("terrain_bind_group"),
+            layout: &bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: uniform_buffer.as_entire_binding() },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::TextureView(&splat_texture.view) },
+                wgpu::BindGroupEntry { binding: 2, resource: wgpu::BindingResource::TextureView(&layer_textures[0].view) },
+                wgpu::BindGroupEntry { binding: 3, resource: wgpu::BindingResource::TextureView(&layer_textures[1].view) },
+                wgpu::BindGroupEntry { binding: 4, resource: wgpu::BindingResource::TextureView(&layer_textures[2].view) },
+                wgpu::BindGroupEntry { binding: 5, resource: wgpu::BindingResource::TextureView(&layer_textures[3].view) },
+                // The layers' repeating sampler also does for the splat map, its uv never leaves 0..1
+                wgpu::BindGroupEntry { binding: 6, resource: wgpu::BindingResource::Sampler(&layer_textures[0].sampler) },
+            ],
+        });
+
+        let chunks = mesh.chunks.iter().map(|chunk| ChunkRender {
+            vertex_buffer: device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Terrain Chunk VB"),
+                contents: bytemuck::cast_slice(&chunk.vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            }),
+            origin: chunk.origin,
+            min: chunk.min,
+            max: chunk.max,
+            lod: 0,
+        }).collect::<Vec<_>>();
+
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Terrain IB"),
+            contents: bytemuck::cast_slice(&mesh.indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+
+        let instance_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Terrain Chunk Instances"),
+            size: (chunks.len().max(1) * std::mem::size_of::<ChunkInstance>()) as wgpu::BufferAddress,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Terrain Pipeline Layout"),
+            bind_group_layouts: &[&camera.bind_group_layout, &light.rendering_data.bind_group_layout, &bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Terrain Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../../shaders/terrain.wgsl").into()),
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Terrain Render Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[TerrainVertex::desc(), ChunkInstance::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                // The skirts are seen from both sides depending on which neighbour is coarser
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                // Reversed-Z, like the models
+                depth_compare: wgpu::CompareFunction::Greater,
+                stencil: wgpu::StencilState::default(),
+                // Pulled slightly towards the camera so the shore wins over the sea plane lying
+                // at the same height instead of flickering with it
+                bias: wgpu::DepthBiasState { constant: 2, slope_scale: 1.0, clamp: 0.0 },
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Ok(Self { render_pipeline, bind_group, index_buffer, instance_buffer, lods: mesh.lods, lod_distances: settings.lod_distances.clone(), chunks })
+    }
+
+    /// Picks every chunk's LOD and moves them relative to the camera, once per frame.
+    pub fn update(&mut self, queue: &Queue, camera_position: Vector3<f32>) {
+        let mut instances = Vec::with_capacity(self.chunks.len());
+        for chunk in &mut self.chunks {
+            let distance = chunk_distance(&chunk.min, &chunk.max, camera_position);
+            chunk.lod = lod_for_distance(distance, &self.lod_distances);
+            instances.push(ChunkInstance { offset: (chunk.origin - camera_position).into() });
+        }
+        queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instances));
+    }
+
+    pub fn render<'a>(&'a self, render_pass: &mut wgpu::RenderPass<'a>, camera_bind_group: &'a BindGroup, light_bind_group: &'a BindGroup) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, camera_bind_group, &[]);
+        render_pass.set_bind_group(1, light_bind_group, &[]);
+        render_pass.set_bind_group(2, &self.bind_group, &[]);
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+
+        for (index, chunk) in self.chunks.iter().enumerate() {
+            render_pass.set_vertex_buffer(0, chunk.vertex_buffer.slice(..));
+            // The chunk's own instance, for its offset
+            render_pass.draw_indexed(self.lods[chunk.lod].clone(), 0, index as u32..index as u32 + 1);
+        }
+    }
+}
@@ -1,9 +1,9 @@
-use std::{collections::HashMap, default, mem, ops::Range};
+use std::{collections::HashMap, mem, ops::Range};
 
 
 use gltf::material::AlphaMode;
 use nalgebra::UnitQuaternion;
-use wgpu::{BindGroup, BindGroupLayoutDescriptor, Device};
+use wgpu::{BindGroupLayoutDescriptor, Device};
 
 use crate::transform::Transform;
 
@@ -80,20 +80,18 @@ pub struct Model {
 
 impl Mesh {
     pub fn update_transform(&self, queue: &wgpu::Queue) {
-        let transform_data: Transform;
-
-        match &self.parent_transform {
+        let transform_data = match &self.parent_transform {
             Some(parent_transform) => {
-                transform_data = Transform::new(
+                Transform::new(
                     parent_transform.position + UnitQuaternion::from_quaternion(parent_transform.rotation) * (self.transform.position - parent_transform.position),
                     parent_transform.rotation * self.transform.rotation,
                     parent_transform.scale,
-                );
+                )
             },
             None => {
-                transform_data = Transform::new(self.transform.position, self.transform.rotation, self.transform.scale)
+                Transform::new(self.transform.position, self.transform.rotation, self.transform.scale)
             },
-        }
+        };
 
         queue.write_buffer(&self.transform_buffer, 0, bytemuck::cast_slice(&[transform_data.to_matrix_bufferable()]));
     }
@@ -148,7 +146,7 @@ pub trait DrawModel<'a> {
         instances: Range<u32>,
         camera_bind_group: &'a wgpu::BindGroup,
         light_bind_group: &'a wgpu::BindGroup,
-        list_name: &String
+        list_name: &str
     );
 }
 
@@ -157,7 +155,7 @@ where
     'b: 'a,
 {
     fn draw_mesh(&mut self, mesh: &'b Mesh, material: &'b Material, camera_bind_group: &'b wgpu::BindGroup, light_bind_group: &'a wgpu::BindGroup) {
-        self.draw_mesh_instanced(mesh, material, 0..1, camera_bind_group, &light_bind_group);
+        self.draw_mesh_instanced(mesh, material, 0..1, camera_bind_group, light_bind_group);
     }
 
     fn draw_mesh_instanced(&mut self, mesh: &'b Mesh, material: &'b Material, instances: Range<u32>, camera_bind_group: &'b wgpu::BindGroup, light_bind_group: &'a wgpu::BindGroup) {
@@ -171,12 +169,12 @@ where
     }
 
     fn draw_model(&mut self, model: &'b Model, camera_bind_group: &'b wgpu::BindGroup, light_bind_group: &'a wgpu::BindGroup) {
-        self.draw_model_instanced_from_list(model, 0..1, camera_bind_group, &light_bind_group, &"opaque".to_owned());
+        self.draw_model_instanced_from_list(model, 0..1, camera_bind_group, light_bind_group, "opaque");
     }
 
-    fn draw_model_instanced_from_list(&mut self, model: &'b Model, instances: Range<u32>, camera_bind_group: &'b wgpu::BindGroup, light_bind_group: &'a wgpu::BindGroup, list_name: &String) {
+    fn draw_model_instanced_from_list(&mut self, model: &'b Model, instances: Range<u32>, camera_bind_group: &'b wgpu::BindGroup, light_bind_group: &'a wgpu::BindGroup, list_name: &str) {
         if let Some(meshes) = model.mesh_lists.get(list_name) {
-            for (_key, mesh) in meshes {
+            for mesh in meshes.values() {
                 let material = &model.materials[mesh.material];
                 self.draw_mesh_instanced(mesh, material, instances.clone(), camera_bind_group, light_bind_group);
             }
@@ -258,7 +256,7 @@ where
         camera_bind_group: &'b wgpu::BindGroup,
         light_bind_group: &'b wgpu::BindGroup,
     ) {
-        for (_id, mesh_lists) in &model.mesh_lists {
+        for mesh_lists in model.mesh_lists.values() {
             for mesh in mesh_lists {
                 self.draw_light_mesh_instanced(mesh.1, instances.clone(), camera_bind_group, light_bind_group);
             }
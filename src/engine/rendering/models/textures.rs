@@ -12,10 +12,6 @@ pub struct Texture {
 impl Texture {
     pub const DEPTH_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Depth32Float; // this is the format the depth will get into the render pipeline
     
-    pub fn new() {
-        
-    }
-
     pub fn create_texture(device: &wgpu::Device, desc: wgpu::TextureDescriptor) -> Self {
         
         let texture = device.create_texture(&desc);
@@ -126,10 +122,20 @@ impl Texture {
 
     pub fn from_bytes(bytes: &[u8], device: &Device, queue: &Queue, label: &str) -> Result<Self> {
         let image = image::load_from_memory(bytes).unwrap(); // load the image
-        return Self::from_image(&image, device, queue, Some(label))
+        Self::from_image(&image, device, queue, Some(label))
     }
 
     pub fn from_image(image: &DynamicImage, device: &Device, queue: &Queue, label: Option<&str>) -> Result<Self> {
+        Self::from_image_with_format(image, device, queue, label, wgpu::TextureFormat::Rgba8UnormSrgb)
+    }
+
+    /// For images holding data rather than colors (e.g. a terrain splat map's weights), stored
+    /// linear so the shader reads the values as they were painted.
+    pub fn from_data_image(image: &DynamicImage, device: &Device, queue: &Queue, label: Option<&str>) -> Result<Self> {
+        Self::from_image_with_format(image, device, queue, label, wgpu::TextureFormat::Rgba8Unorm)
+    }
+
+    fn from_image_with_format(image: &DynamicImage, device: &Device, queue: &Queue, label: Option<&str>, format: wgpu::TextureFormat) -> Result<Self> {
         let rgba = image.to_rgba8(); // transform the image to an array of rgba bytes
         let dimensions = image.dimensions(); // get the size/dimensions of the image
 
@@ -146,24 +152,24 @@ impl Texture {
                 mip_level_count: 1,
                 sample_count: 1,
                 dimension: wgpu::TextureDimension::D2,
-                format: wgpu::TextureFormat::Rgba8UnormSrgb, // we store the image as sRGB
+                format,
                 // texture_binding tells wgpu that this texture will be used in shaders and the copy_dst means that we will copy data to this texture
                 usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST, 
-                label: Some("diffuse_texture"),
+                label: Some(label.unwrap_or("diffuse_texture")),
                 view_formats: &[],
             }
         );
 
         queue.write_texture(
             // Tells wgpu where to copy the pixel data
-            wgpu::ImageCopyTexture {
+            wgpu::TexelCopyTextureInfo {
                 texture: &texture,
                 mip_level: 0,
                 origin: wgpu::Origin3d::ZERO,
                 aspect: wgpu::TextureAspect::All,
             },
             &rgba, // the vector of rgba bytes we made
-            wgpu::ImageDataLayout { // the "layout" of the image
+            wgpu::TexelCopyBufferLayout { // the "layout" of the image
                 offset: 0,
                 bytes_per_row: Some(4 * dimensions.0),
                 rows_per_image: Some(dimensions.1),
@@ -210,14 +216,14 @@ impl Texture {
         for (face_index, image) in images.iter().enumerate() {
             let rgba = image.to_rgba8();
             queue.write_texture(
-                wgpu::ImageCopyTexture {
+                wgpu::TexelCopyTextureInfo {
                     texture: &texture,
                     mip_level: 0,
                     origin: wgpu::Origin3d { x: 0, y: 0, z: face_index as u32 },
                     aspect: wgpu::TextureAspect::All,
                 },
                 &rgba,
-                wgpu::ImageDataLayout {
+                wgpu::TexelCopyBufferLayout {
                     offset: 0,
                     bytes_per_row: Some(4 * dimensions.0),
                     rows_per_image: Some(dimensions.1),
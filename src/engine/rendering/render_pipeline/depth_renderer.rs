@@ -43,7 +43,7 @@ pub struct DepthRender {
 
 impl DepthRender {
     pub fn new(device: &Device, config: &SurfaceConfiguration) -> Self {
-        let texture = Texture::create_depth_texture_non_comparison_sampler(&device, &config, "depth_texture");
+        let texture = Texture::create_depth_texture_non_comparison_sampler(device, config, "depth_texture");
 
         let near_far_uniform = NearFarUniform {
             near: 0.1,
@@ -218,7 +218,7 @@ impl DepthRender {
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Depth Visual Render Pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view,
+                view,
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Load,
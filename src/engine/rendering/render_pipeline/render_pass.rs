@@ -6,7 +6,7 @@ use crate::app::App;
 use crate::engine::primitive::manual_vertex::ManualVertex;
 use crate::engine::rendering::models::model::DrawModel;
 
-fn color_attachment(view: &wgpu::TextureView, load: wgpu::LoadOp<wgpu::Color>) -> Option<wgpu::RenderPassColorAttachment> {
+fn color_attachment(view: &wgpu::TextureView, load: wgpu::LoadOp<wgpu::Color>) -> Option<wgpu::RenderPassColorAttachment<'_>> {
     Some(wgpu::RenderPassColorAttachment {
         view,
         resolve_target: None,
@@ -14,7 +14,7 @@ fn color_attachment(view: &wgpu::TextureView, load: wgpu::LoadOp<wgpu::Color>) -
     })
 }
 
-fn depth_attachment(view: &wgpu::TextureView, load: wgpu::LoadOp<f32>) -> Option<RenderPassDepthStencilAttachment> {
+fn depth_attachment(view: &wgpu::TextureView, load: wgpu::LoadOp<f32>) -> Option<RenderPassDepthStencilAttachment<'_>> {
     Some(RenderPassDepthStencilAttachment {
         view,
         depth_ops: Some(wgpu::Operations { load, store: wgpu::StoreOp::Store }),
@@ -46,12 +46,16 @@ impl App {
             skybox.render(&mut render_pass, &self.camera.bind_group);
         }
 
+        if let Some(terrain) = &self.terrain {
+            terrain.render(&mut render_pass, &self.camera.bind_group, &self.light.rendering_data.bind_group);
+        }
+
         render_pass.set_pipeline(&self.render_pipeline);
 
         for model_ref in self.distinct_model_refs(Some("sun")) {
             if let Some(model_data) = self.game_models.get(&model_ref) {
                 render_pass.set_vertex_buffer(1, model_data.instance_buffer.slice(..));
-                render_pass.draw_model_instanced_from_list(&model_data.model, 0..model_data.instance_count as u32, &self.camera.bind_group, &self.light.rendering_data.bind_group, &"opaque".to_string());
+                render_pass.draw_model_instanced_from_list(&model_data.model, 0..model_data.instance_count, &self.camera.bind_group, &self.light.rendering_data.bind_group, "opaque");
             }
         }
     }
@@ -70,7 +74,7 @@ impl App {
         for model_ref in self.distinct_model_refs(None) {
             if let Some(model_data) = self.game_models.get(&model_ref) {
                 render_pass.set_vertex_buffer(1, model_data.instance_buffer.slice(..));
-                render_pass.draw_model_instanced_from_list(&model_data.model, 0..model_data.instance_count as u32, &self.camera.bind_group, &self.light.rendering_data.bind_group, &"transparent".to_string());
+                render_pass.draw_model_instanced_from_list(&model_data.model, 0..model_data.instance_count, &self.camera.bind_group, &self.light.rendering_data.bind_group, "transparent");
             }
         }
     }
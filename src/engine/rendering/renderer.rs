@@ -15,7 +15,6 @@ pub struct Glyphon {
  *
  * Graphics manager its the main iteration of WGPU for initial configuration, rendering and more related to it.
  */
-
 pub struct Renderer {
   // 'static: create_surface_unsafe doesn't actually borrow window_manager (it copies
   // the raw window/display handles out), so there's no real lifetime to track here.
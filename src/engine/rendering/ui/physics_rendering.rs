@@ -1,4 +1,4 @@
-use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, PipelineLayout, RenderPipeline, SurfaceConfiguration};
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Buffer, Device, RenderPipeline, SurfaceConfiguration};
 use crate::{engine::primitive::manual_vertex::ManualVertex, engine::rendering::{camera::CameraRenderizable, ui::rendering_utils}};
 
 
@@ -65,7 +65,7 @@ impl RenderPhysics {
             };
             
             rendering_utils::create_line_render_pipeline(
-                &device,
+                device,
                 &layout,
                 config.format,
                 None,
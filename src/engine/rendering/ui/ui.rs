@@ -32,7 +32,6 @@ pub struct UiRendering {
 ///     - **ui_pipeline and ui_rendering**: values to render our ui elements, like the render pipeline, vertex and index buffers
 ///     - **renderizable elements**: a list of lists where we define what we will render, if we want to show a button it should be added to one of the lists inside of it
 ///     - **text**: Usable data for text rendering, like font systems, text atrlas, and more... 
-
 pub struct Ui {
     pub renderizable_elements: HashMap<String, UiNode>,
     pub ui_pipeline: RenderPipeline,
@@ -48,10 +47,10 @@ impl Ui {
         font_system.db_mut().load_font_data(font.to_vec());
 
         let text_cache = SwashCache::new();
-        let mut text_atlas = TextAtlas::new(&device, queue, cache, config.format);
+        let mut text_atlas = TextAtlas::new(device, queue, cache, config.format);
         let text_renderer: TextRenderer = TextRenderer::new(
             &mut text_atlas,
-            &device,
+            device,
             wgpu::MultisampleState::default(),
             None,
         );
@@ -164,22 +163,19 @@ impl Ui {
     }
 
     pub fn open_ui(&mut self, path: &str) -> Option<UiStructure> {
-        match std::fs::read_to_string(path) {
-            Ok(file_contents) => {
-                match from_str::<UiStructure>(&file_contents) {
-                    Ok(ui) => {
-                        // Here we start loading elements based on this to the hashmap components
-                        return Some(ui)
-                    },
-                    Err(e) => {
-                        // Handle the error if deserialization fails
-                        eprintln!("Error deserializing RON: {}", e);
-                    }
+        if let Ok(file_contents) = std::fs::read_to_string(path) {
+            match from_str::<UiStructure>(&file_contents) {
+                Ok(ui) => {
+                    // Here we start loading elements based on this to the hashmap components
+                    return Some(ui)
+                },
+                Err(e) => {
+                    // Handle the error if deserialization fails
+                    eprintln!("Error deserializing RON: {}", e);
                 }
-            },
-            _ => {}
+            }
         }
-        return None
+        None
     }
 
     pub fn add_to_ui(&mut self, id: String, element_to_add: UiNode) {
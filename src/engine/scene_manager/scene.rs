@@ -4,24 +4,31 @@ use std::sync::mpsc::Sender;
 use sdl2::{controller::GameController, EventPump};
 
 use crate::app::{App, AppState};
-use crate::game::play::plane::plane::PlaneControls;
 use crate::engine::input::input::InputSubsystem;
+use crate::engine::physics::commands::EntityCommand;
+use crate::engine::physics::events::PhysicsEvent;
 use crate::engine::physics::physics::DebugPhysicsMessageType;
-use crate::engine::physics::physics_handler::{PhysicsTick, RenderMessage};
+use crate::engine::physics::physics_handler::{PhysicsCommand, PhysicsTick, RenderMessage, WorldChanges};
 
 
 /// Everything a scene might need out of a single frame, bundled so `Scene::tick`
 /// keeps one stable signature no matter which of these fields a given scene actually
 /// uses (a menu scene only cares about event_pump/controller, Playing only cares
-/// about input_subsystem/plane_control_tx/physics_data).
+/// about input_subsystem/command_tx/physics_data).
 pub struct FrameContext<'a> {
     pub app_state: &'a mut AppState,
     pub event_pump: &'a mut EventPump,
     pub controller: &'a mut Option<GameController>,
     pub input_subsystem: &'a InputSubsystem,
     // None whenever the active scene's Scene::physics() doesn't want physics.
-    pub plane_control_tx: Option<&'a Sender<PlaneControls>>,
+    pub command_tx: Option<&'a Sender<EntityCommand>>,
+    // Control commands for the physics world itself (spawn, despawn, teleport...), None like command_tx
+    pub physics_tx: Option<&'a Sender<PhysicsCommand>>,
     pub physics_data: &'a HashMap<String, RenderMessage>,
+    // Collisions reported by the physics thread since the last frame
+    pub physics_events: &'a [PhysicsEvent],
+    // Entities a loaded physics snapshot brought in and took out, only on the frame it happened
+    pub world_changes: Option<&'a WorldChanges>,
     pub debug_physics: &'a [DebugPhysicsMessageType],
 }
 
@@ -0,0 +1,95 @@
+struct CameraUniform {
+    view_proj: mat4x4<f32>,
+    view_pos: vec4<f32>,
+};
+
+struct Light {
+    position: vec3<f32>,
+    color: vec3<f32>,
+}
+
+struct TerrainUniform {
+    layer_repeat: f32, // times the layer textures repeat across the whole terrain
+}
+
+@group(0) @binding(0)
+var<uniform> camera: CameraUniform;
+
+@group(1) @binding(0)
+var<uniform> light: Light;
+
+@group(2) @binding(0)
+var<uniform> terrain: TerrainUniform;
+@group(2) @binding(1)
+var splat_map: texture_2d<f32>;
+@group(2) @binding(2)
+var layer_0: texture_2d<f32>;
+@group(2) @binding(3)
+var layer_1: texture_2d<f32>;
+@group(2) @binding(4)
+var layer_2: texture_2d<f32>;
+@group(2) @binding(5)
+var layer_3: texture_2d<f32>;
+@group(2) @binding(6)
+var terrain_sampler: sampler;
+
+struct VertexInput {
+    @location(0) position: vec3<f32>, // relative to the chunk's origin
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+}
+
+struct InstanceInput {
+    @location(3) chunk_offset: vec3<f32>, // chunk's origin relative to the camera
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) world_position: vec3<f32>,
+    @location(3) view_depth: f32, // Used for fog calculation
+};
+
+@vertex
+fn vs_main(model: VertexInput, instance: InstanceInput) -> VertexOutput {
+    var out: VertexOutput;
+
+    // Camera-relative like every other model, so the camera sits at the origin
+    let world_position = instance.chunk_offset + model.position;
+    out.world_position = world_position;
+    out.world_normal = model.normal;
+    out.uv = model.uv;
+    out.clip_position = camera.view_proj * vec4<f32>(world_position, 1.0);
+    out.view_depth = length(camera.view_pos.xyz - world_position);
+
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let weights = textureSample(splat_map, terrain_sampler, in.uv);
+    let layer_uv = in.uv * terrain.layer_repeat;
+    let blended = textureSample(layer_0, terrain_sampler, layer_uv) * weights.r
+        + textureSample(layer_1, terrain_sampler, layer_uv) * weights.g
+        + textureSample(layer_2, terrain_sampler, layer_uv) * weights.b
+        + textureSample(layer_3, terrain_sampler, layer_uv) * weights.a;
+    let object_color = blended.xyz / max(weights.r + weights.g + weights.b + weights.a, 0.001);
+
+    // Same lighting and fog as the models (depth.wgsl)
+    let normal = normalize(in.world_normal);
+    let ambient_color = light.color * 0.8;
+
+    let light_dir = normalize(light.position - in.world_position);
+    let diffuse_strength = max(dot(normal, light_dir), 0.0);
+    let diffuse_color = light.color * diffuse_strength;
+
+    let result = (ambient_color + diffuse_color) * object_color;
+
+    let fog_start = 1000.0;
+    let fog_end = 80000.0;
+    let fog_factor = clamp((in.view_depth - fog_start) / (fog_end - fog_start), 0.0, 1.0);
+    let fogged_color = mix(result, vec3<f32>(0.3, 0.3, 0.5), fog_factor);
+
+    return vec4<f32>(fogged_color, 1.0);
+}
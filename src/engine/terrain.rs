@@ -0,0 +1,2 @@
+pub mod heightmap;
+pub mod terrain_mesh;
@@ -0,0 +1,185 @@
+use image::DynamicImage;
+use nalgebra::{DMatrix, Vector3};
+use serde::Deserialize;
+
+fn default_chunk_cells() -> usize {
+    64
+}
+
+fn default_lod_distances() -> Vec<f32> {
+    vec![2000.0, 5000.0, 10000.0]
+}
+
+fn default_layer_tiling() -> f32 {
+    20.0
+}
+
+/// The level's ground, from the scene's `terrain` block.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TerrainSettings {
+    pub heightmap: String, // grayscale image under res, 16 bits so the hills don't come out in steps
+    pub size: f32,         // m the heightmap covers along x and z, centered on the origin
+    pub min_height: f32,   // m a black pixel stands for
+    pub max_height: f32,   // m a white one
+    // Cells per side of every render chunk, a power of two so each LOD halves it evenly
+    #[serde(default = "default_chunk_cells")]
+    pub chunk_cells: usize,
+    // m from the camera past which a chunk drops to the next coarser LOD
+    #[serde(default = "default_lod_distances")]
+    pub lod_distances: Vec<f32>,
+    // RGBA image spread over the whole terrain, each channel the weight of one of `layers`
+    #[serde(default)]
+    pub splat_map: Option<String>,
+    #[serde(default)]
+    pub layers: Vec<String>, // up to 4 tiling textures under res
+    #[serde(default = "default_layer_tiling")]
+    pub layer_tiling: f32, // m covered by one repeat of a layer texture
+}
+
+/// # Heightmap
+///
+/// The terrain's heights in meters, one sample per pixel of the heightmap image. Row `r`
+/// and column `c` sit at `z = -size / 2 + r * spacing` and `x = -size / 2 + c * spacing`,
+/// the same layout as rapier's heightfield, so the collider and the render mesh are built
+/// from the same samples.
+///
+/// ## Contents:
+/// - **Heights**: `height_at` follows the collider's triangles (each cell split along the
+///   diagonal from its +z corner to its +x one), so whatever stands on the terrain sits
+///   where it's drawn.
+/// - **Normals**: from the neighbouring samples, for the lighting.
+#[derive(Debug, Clone)]
+pub struct Heightmap {
+    pub size: f32,
+    pub resolution: usize, // samples per side
+    heights: Vec<f32>,     // row by row
+}
+
+impl Heightmap {
+    /// Reads the heightmap image from the `res` folder, like the models.
+    pub fn load(settings: &TerrainSettings) -> Result<Self, String> {
+        let path = std::path::Path::new(env!("OUT_DIR")).join("res").join(&settings.heightmap);
+        let image = image::open(&path).map_err(|error| format!("Couldn't read the heightmap '{}': {}", settings.heightmap, error))?;
+        Self::from_image(&image, settings)
+    }
+
+    pub fn from_image(image: &DynamicImage, settings: &TerrainSettings) -> Result<Self, String> {
+        let luma = image.to_luma16();
+        let (width, height) = luma.dimensions();
+        if width != height || width < 2 {
+            return Err(format!("The heightmap '{}' must be square and at least 2x2, got {}x{}", settings.heightmap, width, height));
+        }
+        if settings.size <= 0.0 {
+            return Err(format!("The terrain size must be positive, got {}", settings.size));
+        }
+        if settings.max_height <= settings.min_height {
+            return Err(format!("The terrain heights go from {} to {}", settings.min_height, settings.max_height));
+        }
+
+        // In f64 so the pixel values that stand for round heights (e.g. the sea level) land on them
+        let range = (settings.max_height - settings.min_height) as f64;
+        let heights = luma.pixels()
+            .map(|pixel| (settings.min_height as f64 + pixel.0[0] as f64 * range / u16::MAX as f64) as f32)
+            .collect();
+
+        Ok(Self { size: settings.size, resolution: width as usize, heights })
+    }
+
+    /// m between two samples
+    pub fn spacing(&self) -> f32 {
+        self.size / (self.resolution - 1) as f32
+    }
+
+    /// Height of a sample, the ones past the edges are those of the edge
+    pub fn sample(&self, row: isize, column: isize) -> f32 {
+        let last = self.resolution as isize - 1;
+        self.heights[row.clamp(0, last) as usize * self.resolution + column.clamp(0, last) as usize]
+    }
+
+    /// x and z of a sample
+    pub fn sample_position(&self, row: usize, column: usize) -> (f32, f32) {
+        let half = self.size * 0.5;
+        (column as f32 * self.spacing() - half, row as f32 * self.spacing() - half)
+    }
+
+    /// Ground height under a point, the edge's height outside the terrain.
+    pub fn height_at(&self, x: f32, z: f32) -> f32 {
+        let cells = (self.resolution - 1) as f32;
+        let column = ((x + self.size * 0.5) / self.spacing()).clamp(0.0, cells);
+        let row = ((z + self.size * 0.5) / self.spacing()).clamp(0.0, cells);
+        let (column0, row0) = (column.floor().min(cells - 1.0), row.floor().min(cells - 1.0));
+        let (tx, tz) = (column - column0, row - row0);
+        let (c, r) = (column0 as isize, row0 as isize);
+
+        let h10 = self.sample(r + 1, c);
+        let h01 = self.sample(r, c + 1);
+        if tx + tz <= 1.0 {
+            let h00 = self.sample(r, c);
+            h00 + (h01 - h00) * tx + (h10 - h00) * tz
+        } else {
+            let h11 = self.sample(r + 1, c + 1);
+            h11 + (h10 - h11) * (1.0 - tx) + (h01 - h11) * (1.0 - tz)
+        }
+    }
+
+    /// Up facing normal of a sample
+    pub fn normal(&self, row: usize, column: usize) -> Vector3<f32> {
+        let (r, c) = (row as isize, column as isize);
+        let dx = self.sample(r, c + 1) - self.sample(r, c - 1);
+        let dz = self.sample(r + 1, c) - self.sample(r - 1, c);
+        Vector3::new(-dx, 2.0 * self.spacing(), -dz).normalize()
+    }
+
+    /// Heights as rapier's heightfield takes them, rows along z and columns along x.
+    pub fn matrix(&self) -> DMatrix<f32> {
+        DMatrix::from_fn(self.resolution, self.resolution, |row, column| self.heights[row * self.resolution + column])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageBuffer, Luma};
+
+    fn settings() -> TerrainSettings {
+        TerrainSettings {
+            heightmap: "test".to_string(),
+            size: 300.0,
+            min_height: -100.0,
+            max_height: 1400.0,
+            chunk_cells: 2,
+            lod_distances: vec![],
+            splat_map: None,
+            layers: vec![],
+            layer_tiling: 20.0,
+        }
+    }
+
+    #[test]
+    fn heights_follow_the_collider_triangles() {
+        // 3x3 samples 150m apart, the +x +z corner 1400m high and the rest at 0
+        let image = ImageBuffer::from_fn(3, 3, |x, y| Luma([if x == 2 && y == 2 { u16::MAX } else { 4369 }]));
+        let heightmap = Heightmap::from_image(&DynamicImage::ImageLuma16(image), &settings()).unwrap();
+
+        assert_eq!(heightmap.height_at(-150.0, -150.0), 0.0);
+        assert!((heightmap.height_at(150.0, 150.0) - 1400.0).abs() < 1e-3);
+        // The last cell is split between its other two corners, so only the triangle with
+        // the high corner slopes
+        assert!((heightmap.height_at(112.5, 112.5) - 700.0).abs() < 1e-3);
+        assert!(heightmap.height_at(75.0, 75.0).abs() < 1e-3);
+        assert!(heightmap.height_at(150.0, 0.0).abs() < 1e-3);
+        assert!(heightmap.height_at(0.0, 150.0).abs() < 1e-3);
+        // Past the edges it's the edge's height
+        assert!((heightmap.height_at(1000.0, 1000.0) - 1400.0).abs() < 1e-3);
+        assert_eq!(heightmap.matrix()[(2, 2)], 1400.0);
+        assert!(heightmap.normal(1, 1).y > 0.0);
+    }
+
+    #[test]
+    fn bad_heightmaps_are_load_errors() {
+        let square = DynamicImage::ImageLuma16(ImageBuffer::new(4, 4));
+        assert!(Heightmap::from_image(&DynamicImage::ImageLuma16(ImageBuffer::new(4, 3)), &settings()).is_err());
+        assert!(Heightmap::from_image(&square, &TerrainSettings { size: 0.0, ..settings() }).is_err());
+        assert!(Heightmap::from_image(&square, &TerrainSettings { max_height: -100.0, ..settings() }).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+use std::ops::Range;
+
+use nalgebra::Vector3;
+
+use crate::engine::terrain::heightmap::{Heightmap, TerrainSettings};
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct TerrainVertex {
+    pub position: [f32; 3], // relative to the chunk's origin
+    pub normal: [f32; 3],
+    pub uv: [f32; 2], // over the whole terrain, for the splat map
+}
+
+/// A square piece of the terrain, drawn at the LOD its distance to the camera asks for.
+pub struct TerrainChunk {
+    pub origin: Vector3<f32>, // world position of its -x -z corner, at height 0
+    pub min: Vector3<f32>,    // bounds, for the distance to the camera
+    pub max: Vector3<f32>,
+    pub vertices: Vec<TerrainVertex>,
+}
+
+/// # Terrain Mesh
+///
+/// The render mesh of a heightmap, cut in chunks of `chunk_cells` cells per side. Every chunk
+/// has all of its samples, and each LOD is just an index buffer skipping some of them (every
+/// 2nd, 4th...), the same for every chunk.
+///
+/// ## Contents:
+/// - **Triangles**: split like the heightfield collider's, so at LOD 0 both are the same.
+/// - **Skirts**: a strip hanging below each chunk's edges hides the cracks where a chunk
+///   meets a coarser neighbour.
+pub struct TerrainMesh {
+    pub chunks: Vec<TerrainChunk>,
+    pub indices: Vec<u32>,
+    pub lods: Vec<Range<u32>>, // each LOD's part of `indices`, finest first
+}
+
+impl TerrainMesh {
+    pub fn new(heightmap: &Heightmap, settings: &TerrainSettings) -> Result<Self, String> {
+        let cells = heightmap.resolution - 1;
+        let chunk_cells = settings.chunk_cells;
+        let coarsest_step = 1 << settings.lod_distances.len();
+        if chunk_cells == 0 || !cells.is_multiple_of(chunk_cells) {
+            return Err(format!("Terrain chunks of {} cells don't fit the {} cells of the heightmap", chunk_cells, cells));
+        }
+        if !chunk_cells.is_multiple_of(coarsest_step) {
+            return Err(format!("Terrain chunks of {} cells can't drop {} LODs, they need a multiple of {}", chunk_cells, settings.lod_distances.len(), coarsest_step));
+        }
+        if settings.lod_distances.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(format!("Terrain LOD distances must go up, got {:?}", settings.lod_distances));
+        }
+
+        // Deep enough to cover the step between a chunk and its coarsest neighbour
+        let skirt_depth = heightmap.spacing() * coarsest_step as f32;
+        let chunks_per_side = cells / chunk_cells;
+        let mut chunks = Vec::with_capacity(chunks_per_side * chunks_per_side);
+        for chunk_row in 0..chunks_per_side {
+            for chunk_column in 0..chunks_per_side {
+                chunks.push(build_chunk(heightmap, chunk_row * chunk_cells, chunk_column * chunk_cells, chunk_cells, skirt_depth));
+            }
+        }
+
+        let mut indices = Vec::new();
+        let mut lods = Vec::new();
+        for lod in 0..=settings.lod_distances.len() {
+            let start = indices.len() as u32;
+            lod_indices(chunk_cells, 1 << lod, &mut indices);
+            lods.push(start..indices.len() as u32);
+        }
+
+        Ok(Self { chunks, indices, lods })
+    }
+}
+
+// Index of the vertex on a chunk's grid
+fn grid_index(chunk_cells: usize, row: usize, column: usize) -> u32 {
+    (row * (chunk_cells + 1) + column) as u32
+}
+
+// Index of the skirt vertex under one on the edges, edge 0 is the -z one, 1 the +z, 2 the -x and 3 the +x
+fn skirt_index(chunk_cells: usize, edge: usize, along: usize) -> u32 {
+    ((chunk_cells + 1) * (chunk_cells + 1) + edge * (chunk_cells + 1) + along) as u32
+}
+
+fn build_chunk(heightmap: &Heightmap, first_row: usize, first_column: usize, chunk_cells: usize, skirt_depth: f32) -> TerrainChunk {
+    let (origin_x, origin_z) = heightmap.sample_position(first_row, first_column);
+    let origin = Vector3::new(origin_x, 0.0, origin_z);
+    let cells = (heightmap.resolution - 1) as f32;
+
+    let vertex = |row: usize, column: usize, depth: f32| {
+        let (global_row, global_column) = (first_row + row, first_column + column);
+        let (x, z) = heightmap.sample_position(global_row, global_column);
+        let height = heightmap.sample(global_row as isize, global_column as isize);
+        TerrainVertex {
+            position: [x - origin.x, height - depth, z - origin.z],
+            normal: heightmap.normal(global_row, global_column).into(),
+            uv: [global_column as f32 / cells, global_row as f32 / cells],
+        }
+    };
+
+    let mut vertices = Vec::with_capacity((chunk_cells + 1) * (chunk_cells + 5));
+    for row in 0..=chunk_cells {
+        for column in 0..=chunk_cells {
+            vertices.push(vertex(row, column, 0.0));
+        }
+    }
+    // Skirts, in the order of skirt_index
+    for along in 0..=chunk_cells { vertices.push(vertex(0, along, skirt_depth)); }
+    for along in 0..=chunk_cells { vertices.push(vertex(chunk_cells, along, skirt_depth)); }
+    for along in 0..=chunk_cells { vertices.push(vertex(along, 0, skirt_depth)); }
+    for along in 0..=chunk_cells { vertices.push(vertex(along, chunk_cells, skirt_depth)); }
+
+    let (mut min, mut max) = (origin, origin);
+    for vertex in &vertices[..(chunk_cells + 1) * (chunk_cells + 1)] {
+        let position = origin + Vector3::from(vertex.position);
+        min = min.inf(&position);
+        max = max.sup(&position);
+    }
+
+    TerrainChunk { origin, min, max, vertices }
+}
+
+fn lod_indices(chunk_cells: usize, step: usize, indices: &mut Vec<u32>) {
+    let grid = |row, column| grid_index(chunk_cells, row, column);
+    for row in (0..chunk_cells).step_by(step) {
+        for column in (0..chunk_cells).step_by(step) {
+            let (p00, p10) = (grid(row, column), grid(row + step, column));
+            let (p01, p11) = (grid(row, column + step), grid(row + step, column + step));
+            // Counter clockwise seen from above, split between the +z and +x corners like the collider
+            indices.extend_from_slice(&[p00, p10, p01, p10, p11, p01]);
+        }
+    }
+
+    for along in (0..chunk_cells).step_by(step) {
+        let next = along + step;
+        let edges = [
+            (grid(0, along), grid(0, next), 0),
+            (grid(chunk_cells, along), grid(chunk_cells, next), 1),
+            (grid(along, 0), grid(next, 0), 2),
+            (grid(along, chunk_cells), grid(next, chunk_cells), 3),
+        ];
+        for (top, next_top, edge) in edges {
+            let (bottom, next_bottom) = (skirt_index(chunk_cells, edge, along), skirt_index(chunk_cells, edge, next));
+            indices.extend_from_slice(&[top, next_top, next_bottom, top, next_bottom, bottom]);
+        }
+    }
+}
+
+/// LOD a chunk is drawn at, 0 (every sample) closer than the first distance, one coarser past each of them.
+pub fn lod_for_distance(distance: f32, lod_distances: &[f32]) -> usize {
+    lod_distances.iter().take_while(|&&lod_distance| distance >= lod_distance).count()
+}
+
+/// m from a point to the closest part of a chunk's bounds
+pub fn chunk_distance(min: &Vector3<f32>, max: &Vector3<f32>, point: Vector3<f32>) -> f32 {
+    (point.sup(min).inf(max) - point).norm()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{DynamicImage, ImageBuffer, Luma};
+
+    fn terrain(resolution: u32, chunk_cells: usize, lod_distances: Vec<f32>) -> (Heightmap, TerrainSettings) {
+        let settings = TerrainSettings {
+            heightmap: "test".to_string(),
+            size: 1600.0,
+            min_height: 0.0,
+            max_height: 1000.0,
+            chunk_cells,
+            lod_distances,
+            splat_map: None,
+            layers: vec![],
+            layer_tiling: 20.0,
+        };
+        let image = ImageBuffer::from_fn(resolution, resolution, |x, y| Luma([((x * 7 + y * 13) % 50 * 1000) as u16]));
+        (Heightmap::from_image(&DynamicImage::ImageLuma16(image), &settings).unwrap(), settings)
+    }
+
+    #[test]
+    fn chunks_cover_the_heightmap_with_its_heights() {
+        let (heightmap, settings) = terrain(17, 8, vec![500.0, 1000.0]);
+        let mesh = TerrainMesh::new(&heightmap, &settings).unwrap();
+
+        assert_eq!(mesh.chunks.len(), 4);
+        for chunk in &mesh.chunks {
+            for vertex in &chunk.vertices[..81] {
+                let world = chunk.origin + Vector3::from(vertex.position);
+                assert!((heightmap.height_at(world.x, world.z) - world.y).abs() < 1e-3);
+            }
+        }
+        // The last chunk ends on the terrain's +x +z corner
+        let last = mesh.chunks.last().unwrap();
+        let corner = last.origin + Vector3::from(last.vertices[80].position);
+        assert_eq!((corner.x, corner.z), (800.0, 800.0));
+    }
+
+    #[test]
+    fn every_lod_covers_the_whole_chunk_with_fewer_triangles() {
+        let (heightmap, settings) = terrain(17, 8, vec![500.0, 1000.0]);
+        let mesh = TerrainMesh::new(&heightmap, &settings).unwrap();
+        let triangles = |lod: usize| mesh.lods[lod].len() / 3;
+
+        // 8x8 cells and 4 skirts of 8 quads, then 4x4 and 4 of 4, then 2x2 and 4 of 2
+        assert_eq!(mesh.lods.len(), 3);
+        assert_eq!((triangles(0), triangles(1), triangles(2)), (8 * 8 * 2 + 8 * 4 * 2, 4 * 4 * 2 + 4 * 4 * 2, 2 * 2 * 2 + 2 * 4 * 2));
+        let vertex_count = mesh.chunks[0].vertices.len() as u32;
+        assert!(mesh.indices.iter().all(|&index| index < vertex_count));
+    }
+
+    #[test]
+    fn chunks_drop_lods_with_distance() {
+        let lod_distances = [2000.0, 5000.0, 10000.0];
+        assert_eq!(lod_for_distance(0.0, &lod_distances), 0);
+        assert_eq!(lod_for_distance(2500.0, &lod_distances), 1);
+        assert_eq!(lod_for_distance(20000.0, &lod_distances), 3);
+
+        let (heightmap, settings) = terrain(17, 8, vec![500.0, 1000.0]);
+        let mesh = TerrainMesh::new(&heightmap, &settings).unwrap();
+        let chunk = &mesh.chunks[0];
+        assert_eq!(chunk_distance(&chunk.min, &chunk.max, Vector3::new(-400.0, 0.0, -400.0)), 0.0);
+        assert!(chunk_distance(&chunk.min, &chunk.max, Vector3::new(-400.0, 5000.0, -400.0)) > 4000.0);
+    }
+
+    #[test]
+    fn chunk_sizes_that_dont_fit_are_load_errors() {
+        let (heightmap, settings) = terrain(17, 8, vec![500.0, 1000.0]);
+        assert!(TerrainMesh::new(&heightmap, &TerrainSettings { chunk_cells: 5, ..settings.clone() }).is_err());
+        assert!(TerrainMesh::new(&heightmap, &TerrainSettings { lod_distances: vec![1.0, 2.0, 3.0, 4.0], ..settings.clone() }).is_err());
+        assert!(TerrainMesh::new(&heightmap, &TerrainSettings { lod_distances: vec![1000.0, 500.0], ..settings }).is_err());
+    }
+}
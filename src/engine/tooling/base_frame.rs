@@ -1,7 +1,7 @@
 use crate::engine::tooling::tooling_manager::ToolingManager;
 use std::sync::mpsc::Sender;
-use egui_plot::{Line, MarkerShape, Plot, PlotPoints, Points};
-use egui::{Color32, RichText};
+use egui_plot::{MarkerShape, Plot, Points};
+use egui::RichText;
 
 pub struct BaseFrame {
     pub data_sender: Sender<ToolingManager>,
@@ -11,7 +11,7 @@ pub struct BaseFrame {
 impl BaseFrame {
     pub fn new(data_sender: Sender<ToolingManager>) -> Self {
         Self {
-            data_sender: data_sender,
+            data_sender,
             selected_scene: "".to_string(),
         }
     }
@@ -62,9 +62,9 @@ impl eframe::App for BaseFrame {
                             ui.heading("GameObjects");
                             // make this buttons to use all the width they can
                             ui.with_layout(egui::Layout::top_down_justified(egui::Align::LEFT), |ui| {
-                                ui.button(RichText::new("F16"));
-                                ui.button(RichText::new("F14"));
-                                ui.button(RichText::new("Player"));
+                                let _ = ui.button(RichText::new("F16"));
+                                let _ = ui.button(RichText::new("F14"));
+                                let _ = ui.button(RichText::new("Player"));
                             });
                         });
                     });
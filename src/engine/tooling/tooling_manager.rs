@@ -20,7 +20,7 @@ pub fn tooling_handling() -> ToolingManager {
     match eframe::run_native(
         "Tooling Manager",
         options,
-        Box::new(|cc| {
+        Box::new(|_cc| {
             Ok(Box::new(BaseFrame::new(tx)))
         }),
     ) {
@@ -28,7 +28,5 @@ pub fn tooling_handling() -> ToolingManager {
         Err(e) => println!("Error running eframe: {:?}", e),
     }
 
-    let result = rx.recv().unwrap();
-
-    result
+    rx.recv().unwrap()
 }
@@ -42,14 +42,14 @@ impl Label {
         }
     }
 
-    pub fn ui_node_data_creation(&self, _size: &Size, vertices: &mut Vec<VertexUi>, vertices_slice: &[VertexUi; 4], indices: &mut Vec<u16>, indices_slice: &[u16; 6], parent_rect: &Rect) -> (TextArea, u16, u32) {
+    pub fn ui_node_data_creation(&self, _size: &Size, vertices: &mut Vec<VertexUi>, vertices_slice: &[VertexUi; 4], indices: &mut Vec<u16>, indices_slice: &[u16; 6], parent_rect: &Rect) -> (TextArea<'_>, u16, u32) {
         vertices.extend_from_slice(vertices_slice);
         indices.extend_from_slice(indices_slice);
 
         (self.text_area(parent_rect), vertices_slice.len() as u16, UiNode::NUM_INDICES)
     }
 
-    pub fn text_area(&self, parent_rect: &Rect) -> TextArea {
+    pub fn text_area(&self, parent_rect: &Rect) -> TextArea<'_> {
         let text_width = self.get_text_width();
         let TextWidth { width, buffer_width } = text_width;
 
@@ -8,7 +8,7 @@ use crate::engine::rendering::vertex::VertexUi;
 use crate::engine::utils::lerps::lerp;
 use super::components::label::Label;
 use super::components::container::Container;
-use super::ui_transform::{Anchor, ChildAnchor, Direction, Fit, Rect, SelfAnchor, UiTransform};
+use super::ui_transform::{Anchor, Direction, UiTransform};
 use super::ui_structure;
 
 pub enum UiNodeContent {
@@ -52,7 +52,7 @@ impl UiNode {
 
     // ── Rendering ──
 
-    pub fn node_content_preparation(&mut self, size: &Size, ui: &mut UiRendering, font_system: &mut FontSystem, delta_time: f32) -> (Vec<TextArea>, u16, u32) {
+    pub fn node_content_preparation(&mut self, size: &Size, ui: &mut UiRendering, font_system: &mut FontSystem, delta_time: f32) -> (Vec<TextArea<'_>>, u16, u32) {
         let mut text_areas: Vec<TextArea> = Vec::new();
 
         match &mut self.content {
@@ -124,7 +124,7 @@ impl UiNode {
 
                 let mut end_extent = cursor;
 
-                for (_id, child) in &mut container.children {
+                for child in container.children.values_mut() {
 
                     // Position on main axis
                     match direction {
@@ -234,7 +234,7 @@ impl UiNode {
         // Resolve on screen for top-level elements
         transform.resolve_on_screen(screen_width, screen_height);
 
-        let mut visibility = Visibility::new([0.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 0.0]);
+        let visibility;
 
         let content = match &component.content {
             ui_structure::UiContent::Label(label_data) => {
@@ -290,6 +290,7 @@ impl UiNode {
     }
 
     /// Create a label node from code.
+    #[allow(clippy::too_many_arguments)]
     pub fn label(
         font_system: &mut FontSystem,
         text: &str,
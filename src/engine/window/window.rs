@@ -1,6 +1,5 @@
 
 
-use std::env;
 use sdl2::{Sdl, video::{DisplayMode, Window}, render::Canvas};
 
 use crate::app::Size;
@@ -1,11 +1,10 @@
-use nalgebra::{ AbstractRotation, Point3, Quaternion, Rotation2, Unit, UnitQuaternion, Vector2, Vector3};
-use std::{collections::HashMap, f64::consts::PI, time::{Duration, Instant}};
+use nalgebra::{ Point3, Rotation2, Unit, UnitQuaternion, Vector2, Vector3};
 use sdl2::controller::GameController;
 
-use crate::{app::{App, AppState}, engine::rendering::enviroment::environment::Environment, resources::apply_environment, transform::Transform, engine::ui::{button, ui_node::{UiNode, UiNodeContent, Visibility}}, engine::utils::lerps::{lerp_quaternion, lerp_vector3}};
+use crate::{app::{App, AppState}, transform::Transform, engine::utils::lerps::{lerp_quaternion, lerp_vector3}};
 
 use crate::game::play::controller::Controller;
-use crate::engine::scene_manager::scene::{FrameContext, Scene};
+use crate::engine::scene_manager::scene::{FrameContext, GameState, Scene};
 
 pub struct ListOfPlanes {
     list: Vec<String>,
@@ -37,7 +36,7 @@ impl GameLogic {
     }
 
     // this is called every frame
-    pub fn update(&mut self, mut app_state: &mut AppState, mut event_pump: &mut sdl2::EventPump, app: &mut App, controller: &mut Option<GameController>) {
+    pub fn update(&mut self, app_state: &mut AppState, event_pump: &mut sdl2::EventPump, app: &mut App, controller: &mut Option<GameController>) {
         if let Some(plane) = app.renderizable_instances.get_mut(&self.plane_list.list[self.plane_list.index]) {
             if let Some(plane_model) = app.game_models.get_mut(&plane.instance.model) {
                 if let Some(meshes) = plane_model.model.mesh_lists.get_mut("transparent") {
@@ -62,14 +61,14 @@ impl GameLogic {
                     if let Some(meshes) = plane_model.model.mesh_lists.get_mut("opaque") {
                         if let Some(aleron) = meshes.get_mut("left_aleron") {
 
-                            let dependent = aleron.base_transform.rotation.clone() * *UnitQuaternion::from_axis_angle(&Vector3::x_axis() ,0.5 * -self.controller_simulation.x);
+                            let dependent = aleron.base_transform.rotation * *UnitQuaternion::from_axis_angle(&Vector3::x_axis() ,0.5 * -self.controller_simulation.x);
                             let aleron_rotation = lerp_quaternion(aleron.transform.rotation,  dependent, app.time.delta_time * 7.0);
                             let aleron_transform = Transform::new(aleron.transform.position, aleron_rotation, aleron.transform.scale);
                             aleron.change_transform(&app.renderer.queue, aleron_transform);
                         }
     
                         if let Some(aleron) = meshes.get_mut("right_aleron") {
-                            let dependent = aleron.base_transform.rotation.clone() * *UnitQuaternion::from_axis_angle(&Vector3::x_axis() ,0.5 * self.controller_simulation.x);
+                            let dependent = aleron.base_transform.rotation * *UnitQuaternion::from_axis_angle(&Vector3::x_axis() ,0.5 * self.controller_simulation.x);
                             let aleron_rotation = lerp_quaternion(aleron.transform.rotation,  dependent, app.time.delta_time * 7.0);
                             let aleron_transform = Transform::new(aleron.transform.position, aleron_rotation, aleron.transform.scale);
                             aleron.change_transform(&app.renderer.queue, aleron_transform);
@@ -100,7 +99,7 @@ impl GameLogic {
         }
 
         self.camera_control(app, app.time.delta_time);
-        self.controller.update(&mut app_state, &mut event_pump, app, controller, app.time.delta_time);
+        self.controller.update(app_state, event_pump, app, controller, app.time.delta_time);
     }
 
     fn camera_control(&mut self, app: &mut App, delta_time: f32) {
@@ -116,6 +115,12 @@ impl GameLogic {
         if self.controller.ui_right && self.plane_list.index < self.plane_list.list.len() - 1 {
             self.plane_list.index += 1;
         }
+
+        // The plane list ids double as aircraft definition names (assets/aircraft/<id>.ron)
+        if self.controller.ui_select {
+            app.selected_aircraft = self.plane_list.list[self.plane_list.index].clone();
+            app.scene_manager.switch_to(GameState::Playing);
+        }
     }
 
     fn rotate_camera_position(base_position: Vector3<f32>, pivot: Vector3<f32>, rotation_speed: f32, rotation_axis: Vector3<f32>, delta_time: f32) -> Vector3<f32> {
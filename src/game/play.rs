@@ -1,4 +1,6 @@
 pub mod event_handling;
 pub mod controller;
 pub mod plane;
+pub mod missile;
+pub mod gun;
 pub mod play;
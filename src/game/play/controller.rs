@@ -84,19 +84,19 @@ impl Controller {
             self.change_camera.released = false;
         }
 
-        if self.ui_down == true {
+        if self.ui_down {
             self.ui_down = false;
         }
 
-        if self.ui_up == true {
+        if self.ui_up {
             self.ui_up = false;
         }
 
-        if self.ui_left == true {
+        if self.ui_left {
             self.ui_left = false;
         }
 
-        if self.ui_right == true {
+        if self.ui_right {
             self.ui_right = false;
         }
 
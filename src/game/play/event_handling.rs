@@ -1,9 +1,9 @@
 use std::{collections::HashMap, time::Duration};
 use serde::Deserialize;
 
-use crate::engine::audio::audio::{self, Audio};
 use crate::app::App;
-use crate::engine::audio::subtitles::{Subtitle, SubtitleData};
+use crate::engine::audio::subtitles::{Subtitle, SubtitleData, SubtitleEntry};
+use crate::engine::physics::events::{PhysicsEvent, PhysicsEventKind};
 
 #[derive(Debug, Deserialize)]
 pub struct AudioFile {
@@ -16,7 +16,8 @@ pub struct AudioFile {
 
 #[derive(Debug, Deserialize)]
 enum EventType {
-    PlayAudio(AudioFile)
+    PlayAudio(AudioFile),
+    ShowSubtitle(SubtitleEntry),
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,9 +27,58 @@ pub struct Event {
     activated: bool
 }
 
+impl Event {
+    // Back to how the level planning left it, so it runs from the start again
+    fn reset(&mut self) {
+        self.activated = false;
+        if let EventType::PlayAudio(audio_file) = &mut self.event_type {
+            audio_file.timer = 0.0;
+            audio_file.played.clear();
+        }
+    }
+}
+
+fn default_once() -> bool { true }
+
+#[derive(Debug, Deserialize, Default, PartialEq)]
+enum TriggerEdge {
+    #[default]
+    Entered,
+    Exited,
+}
+
+// An event started by something crossing a sensor collider of the level (see ColliderType::Sensor)
+#[derive(Debug, Deserialize)]
+pub struct TriggerEvent {
+    trigger: String,
+    #[serde(default)]
+    on: TriggerEdge,
+    #[serde(default)] // only this entity crossing counts, anything does if it isn't set
+    by: Option<String>,
+    #[serde(default = "default_once")] // false runs the event again every time it's crossed
+    once: bool,
+    #[serde(skip)] // runtime state, the level can't start one already fired
+    fired: bool,
+    event: Event,
+}
+
+impl TriggerEvent {
+    fn fired_by(&self, physics_event: &PhysicsEvent) -> bool {
+        let edge = match &physics_event.kind {
+            PhysicsEventKind::TriggerEntered { trigger } if *trigger == self.trigger => TriggerEdge::Entered,
+            PhysicsEventKind::TriggerExited { trigger } if *trigger == self.trigger => TriggerEdge::Exited,
+            _ => return false,
+        };
+
+        edge == self.on && self.by.as_ref().is_none_or(|entity| physics_event.entities[1].as_ref() == Some(entity))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct EventSystem {
-    pub event_list: HashMap<u64, Event>
+    pub event_list: HashMap<u64, Event>,
+    #[serde(default)]
+    pub trigger_list: Vec<TriggerEvent>,
 }
 
 impl EventSystem {
@@ -52,23 +102,51 @@ impl EventSystem {
     }
 
 
-    pub fn handle_events(&mut self, seconds: f64, app: &mut App, subtitle_system: &mut Subtitle) {  
+    pub fn handle_events(&mut self, seconds: f64, physics_events: &[PhysicsEvent], app: &mut App, subtitle_system: &mut Subtitle) {  
         let duration = Duration::from_secs_f64(seconds).as_millis();
         
         for (millis, event) in &mut self.event_list {
             if duration > (*millis).into() {
-                match &mut event.event_type {
-                    EventType::PlayAudio(audio_file) => {
-                        EventSystem::handle_play_audio(event.activated, app, audio_file, subtitle_system);
-                    },
-                    _ => {
+                EventSystem::handle_event(event, app, subtitle_system);
+            }
+            
+        }
 
-                    }
+        // Once fired a trigger event runs just like a timed one that is due, those that aren't
+        // `once` start over each time they're crossed again
+        for trigger_event in &mut self.trigger_list {
+            if (!trigger_event.fired || !trigger_event.once) && physics_events.iter().any(|physics_event| trigger_event.fired_by(physics_event)) {
+                if trigger_event.fired {
+                    trigger_event.event.reset();
                 }
-                event.activated = true;
+                trigger_event.fired = true;
             }
-            
+            if trigger_event.fired {
+                EventSystem::handle_event(&mut trigger_event.event, app, subtitle_system);
+            }
+        }
+    }
+
+    /// Lets every trigger event fire again, e.g. once the player respawns to fly through them anew.
+    pub fn reset_triggers(&mut self) {
+        for trigger_event in &mut self.trigger_list {
+            trigger_event.fired = false;
+            trigger_event.event.reset();
+        }
+    }
+
+    fn handle_event(event: &mut Event, app: &mut App, subtitle_system: &mut Subtitle) {
+        match &mut event.event_type {
+            EventType::PlayAudio(audio_file) => {
+                EventSystem::handle_play_audio(event.activated, app, audio_file, subtitle_system);
+            },
+            EventType::ShowSubtitle(entry) => {
+                if !event.activated {
+                    subtitle_system.add_text(&entry.text, entry.duration, app);
+                }
+            },
         }
+        event.activated = true;
     }
 
     pub fn handle_play_audio(activated: bool, app: &mut App, audio_file: &mut AudioFile, subtitle_system: &mut Subtitle) {
@@ -85,9 +163,9 @@ impl EventSystem {
                     match ron::from_str::<SubtitleData>(&ron_result_string) {
                         Ok(subtitles) => {
                             for (time, entry) in subtitles.subtitles {
-                                if !audio_file.played.contains(&(time as u64)) && duration > time.into() {
+                                if !audio_file.played.contains(&time) && duration > time.into() {
                                     subtitle_system.add_text(&entry.text, entry.duration, app);
-                                    audio_file.played.push(time as u64);
+                                    audio_file.played.push(time);
                                 }
                             }
                         },
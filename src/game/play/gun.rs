@@ -0,0 +1,3 @@
+pub mod definition;
+pub mod ballistics;
+pub mod gun_logic;
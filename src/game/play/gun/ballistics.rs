@@ -0,0 +1,101 @@
+use nalgebra::Vector3;
+
+const GRAVITY: f32 = 9.81;
+// The time of flight depends on the range, which depends on where the target is by then
+const LEAD_ITERATIONS: usize = 5;
+
+fn gravity() -> Vector3<f32> {
+    Vector3::new(0.0, -GRAVITY, 0.0)
+}
+
+/// Moves a round on by `delta_time`: gravity, and drag (see GunDefinition::drag_factor)
+/// against its `airspeed`.
+pub fn step_round(position: &mut Vector3<f32>, velocity: &mut Vector3<f32>, airspeed: &Vector3<f32>, drag_factor: f32, delta_time: f32) {
+    let drag = -airspeed * airspeed.norm() * drag_factor;
+    *velocity += (gravity() + drag) * delta_time;
+    *position += *velocity * delta_time;
+}
+
+/// m a round leaving at `speed` covers in `time` s, slowed by drag alone.
+pub fn distance_flown(time: f32, speed: f32, drag_factor: f32) -> f32 {
+    if drag_factor <= f32::EPSILON {
+        return speed * time;
+    }
+    (1.0 + drag_factor * speed * time).ln() / drag_factor
+}
+
+/// s a round leaving at `speed` takes to cover `distance` m, the inverse of `distance_flown`.
+pub fn time_of_flight(distance: f32, speed: f32, drag_factor: f32) -> f32 {
+    if drag_factor <= f32::EPSILON {
+        return distance / speed;
+    }
+    ((drag_factor * distance).exp() - 1.0) / (drag_factor * speed)
+}
+
+/// # Lead Computing Pipper
+///
+/// Where the rounds fired now down `nose` will be, relative to the muzzle, once they've flown
+/// as far as the target will be by then, seen from a frame moving with the target: whenever
+/// the pipper sits on the target, what's fired meets it. So it trails a crossing target
+/// (the nose has to lead it) and sits lower the further it is (the rounds drop).
+/// `relative_position` is the target's from the muzzle. Returns the point and the time of flight.
+pub fn pipper(relative_position: &Vector3<f32>, target_velocity: &Vector3<f32>, shooter_velocity: &Vector3<f32>, nose: &Vector3<f32>, muzzle_velocity: f32, drag_factor: f32) -> (Vector3<f32>, f32) {
+    let round_velocity = shooter_velocity + nose * muzzle_velocity;
+    let speed = round_velocity.norm();
+    let direction = round_velocity / speed;
+
+    let mut time = time_of_flight(relative_position.norm(), speed, drag_factor);
+    for _ in 0..LEAD_ITERATIONS {
+        let range = (relative_position + target_velocity * time).norm();
+        time = time_of_flight(range, speed, drag_factor);
+    }
+
+    let point = direction * distance_flown(time, speed, drag_factor) + gravity() * 0.5 * time * time - target_velocity * time;
+    (point, time)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An M61 round at 6000m
+    const DRAG_FACTOR: f32 = 3.1e-4;
+
+    #[test]
+    fn rounds_slow_down_along_the_drag_curve() {
+        let time = time_of_flight(1000.0, 1250.0, DRAG_FACTOR);
+        assert!(time > 1000.0 / 1250.0);
+        assert!((distance_flown(time, 1250.0, DRAG_FACTOR) - 1000.0).abs() < 1e-2);
+
+        // Stepped the long way it ends up in the same place
+        let (mut position, mut velocity) = (Vector3::zeros(), Vector3::new(0.0, 0.0, 1250.0));
+        for _ in 0..(time * 1000.0).round() as usize {
+            let airspeed = velocity;
+            step_round(&mut position, &mut velocity, &airspeed, DRAG_FACTOR, 0.001);
+        }
+        assert!((position.z - 1000.0).abs() < 2.0, "flew {}m", position.z);
+        assert!((position.y + 0.5 * GRAVITY * time * time).abs() < 0.5);
+    }
+
+    #[test]
+    fn rounds_fired_with_the_pipper_on_the_target_hit_it() {
+        let (shooter_velocity, nose) = (Vector3::new(0.0, 0.0, 200.0), Vector3::z());
+        let target_velocity = Vector3::new(120.0, 10.0, 30.0);
+
+        // Fly a round for a second, the target starts where it meets it
+        let (mut position, mut velocity) = (Vector3::zeros(), shooter_velocity + nose * 1050.0);
+        for _ in 0..1000 {
+            let airspeed = velocity;
+            step_round(&mut position, &mut velocity, &airspeed, DRAG_FACTOR, 0.001);
+        }
+        let target = position - target_velocity;
+
+        let (point, time) = pipper(&target, &target_velocity, &shooter_velocity, &nose, 1050.0, DRAG_FACTOR);
+        assert!((time - 1.0).abs() < 0.02, "{}s", time);
+        assert!(point.angle(&target) < 1e-3, "{}mrad off", point.angle(&target) * 1000.0);
+
+        // It trails a target crossing to the right, and sits below it
+        let (point, _) = pipper(&Vector3::new(0.0, 0.0, 800.0), &Vector3::new(150.0, 0.0, 0.0), &shooter_velocity, &nose, 1050.0, DRAG_FACTOR);
+        assert!(point.x < -50.0 && point.y < 0.0);
+    }
+}
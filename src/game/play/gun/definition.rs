@@ -0,0 +1,55 @@
+use std::f32::consts::PI;
+
+use serde::Deserialize;
+
+/// # Gun Definition
+///
+/// One kind of cannon, loaded from `assets/guns/<name>.ron` and mounted on the aircraft
+/// (see GunMountDefinition).
+///
+/// ## Contents:
+/// - **rate_of_fire** / **muzzle_velocity**: how often a round leaves the muzzle and how
+///   fast, on top of the shooter's own velocity.
+/// - **Round**: mass, caliber and drag, which with gravity decide how it slows and drops.
+/// - **damage**: taken off the part a round hits, reaching `damage_radius` around it.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GunDefinition {
+    pub name: String,
+    pub rate_of_fire: f32,    // rounds/min
+    pub muzzle_velocity: f32, // m/s
+    pub round_mass: f32,      // kg
+    pub caliber: f32,         // m
+    pub drag_coefficient: f32,
+    pub max_time_of_flight: f32, // s, a round that hit nothing by then is dropped
+    pub damage: f32,
+    pub damage_radius: f32, // m
+}
+
+impl GunDefinition {
+    /// Loads `assets/guns/<name>.ron`, `name` being the id the mounts use (e.g. "m61").
+    pub fn load(name: &str) -> Result<GunDefinition, String> {
+        let path = format!("assets/guns/{}.ron", name);
+
+        match std::fs::read_to_string(&path) {
+            Ok(ron_result_string) => {
+                match ron::from_str::<GunDefinition>(&ron_result_string) {
+                    Ok(definition) => Ok(definition),
+                    Err(error) => Err(format!("Something went wrong structuring the gun '{}': {}", path, error)),
+                }
+            },
+            Err(err) => Err(format!("Something went wrong reading the gun '{}': {}", path, err)),
+        }
+    }
+
+    /// s between two rounds.
+    pub fn round_interval(&self) -> f32 {
+        60.0 / self.rate_of_fire
+    }
+
+    /// The deceleration of a round over its speed squared (1/m) in air of `density`, so
+    /// drag = -drag_factor * |v| * v.
+    pub fn drag_factor(&self, density: f32) -> f32 {
+        let reference_area = PI * self.caliber * self.caliber * 0.25;
+        0.5 * density * self.drag_coefficient * reference_area / self.round_mass
+    }
+}
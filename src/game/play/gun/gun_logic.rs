@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+
+use nalgebra::{Point3, Vector3};
+use rapier3d::prelude::{ColliderSet, QueryFilter, QueryPipeline, Ray, RigidBodyHandle, RigidBodySet};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::commands::{CommandQueue, EntityCommand};
+use crate::engine::physics::physics::DebugPhysicsMessageType;
+use crate::engine::physics::physics_handler::{PhysicsData, PhysicsTick};
+use crate::engine::primitive::manual_vertex::ManualVertex;
+use crate::game::play::gun::ballistics::step_round;
+use crate::game::play::gun::definition::GunDefinition;
+use crate::game::play::plane::damage::Damage;
+
+const TRACER_COLOR: [f32; 3] = [1.0, 0.8, 0.2];
+
+/// Sent to a shooter whenever its trigger is pulled or let go, the gun keeps firing until then.
+#[derive(Debug, Clone, Copy)]
+pub struct GunTrigger {
+    pub pulled: bool,
+}
+
+/// The gun an entity carries, reported as its `gun/status` metadata.
+#[derive(Debug, Clone)]
+pub struct GunData {
+    pub ammo: u32,     // rounds left
+    pub firing: bool,  // trigger held with rounds left
+    pub hits: u32,     // rounds that hit another entity, whether it can be damaged or not
+}
+
+/// What a physics snapshot keeps of a gun, the rounds in flight aren't kept.
+#[derive(Serialize, Deserialize)]
+struct GunState {
+    ammo: u32,
+    hits: u32,
+}
+
+struct Round {
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+    time: f32, // s since it left the muzzle, below 0 at the start of the tick it leaves partway through
+}
+
+/// One entity's gun, see GunLogic.
+pub struct Gun {
+    pub entity: String,
+    pub definition: GunDefinition,
+    pub position: Vector3<f32>, // the muzzle in the entity's local space
+    pub ammo: u32,
+    trigger: bool,
+    cooldown: f32, // s until the next round leaves, below 0 when it's overdue
+    rounds: Vec<Round>,
+    hits: u32,
+}
+
+impl Gun {
+    pub fn new(entity: impl Into<String>, definition: GunDefinition, position: Vector3<f32>, ammo: u32) -> Self {
+        Self { entity: entity.into(), definition, position, ammo, trigger: false, cooldown: 0.0, rounds: Vec::new(), hits: 0 }
+    }
+
+    fn state_key(&self) -> String {
+        format!("{}/gun", self.entity)
+    }
+}
+
+/// # Gun Logic
+///
+/// Fires the guns and flies their rounds. A round is only a point, not a rigidbody: each tick
+/// it's moved on and a ray is cast along the way it went to find what it hit in between.
+///
+/// ## Contents:
+/// - **Firing**: while the `GunTrigger` is held a round leaves the muzzle every
+///   `round_interval`, down the nose at the muzzle velocity plus the shooter's own.
+/// - **Ballistics**: gravity, and drag against the airspeed (see step_round).
+/// - **Hits**: the first thing in the way, other than the shooter, is sent a `Damage` where
+///   it was hit. This tick runs before the planes' so it lands the same step.
+/// - **Report**: ammo, whether it's firing and how many rounds hit, as `gun/status` metadata.
+#[derive(Default)]
+pub struct GunLogic {
+    guns: Vec<Gun>,
+    debug_rendering_enabled: bool,
+    renderizable_lines: Vec<DebugPhysicsMessageType>,
+}
+
+impl GunLogic {
+    pub fn new(guns: Vec<Gun>) -> Self {
+        Self { guns, ..Self::default() }
+    }
+
+    fn entity_of(physics_elements: &HashMap<String, Option<PhysicsData>>, handle: RigidBodyHandle) -> Option<&String> {
+        physics_elements.iter()
+            .find(|(_, physics_data)| physics_data.as_ref().is_some_and(|physics_data| physics_data.rigidbody_handle == handle))
+            .map(|(entity, _)| entity)
+    }
+}
+
+impl PhysicsTick for GunLogic {
+    fn tick(&mut self, commands: &mut CommandQueue, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, atmosphere: &Atmosphere, physics_elements: &mut HashMap<String, Option<PhysicsData>>, _debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, time: f64, delta_time: f32) {
+        self.renderizable_lines.clear();
+
+        for gun in self.guns.iter_mut() {
+            if let Some(trigger) = commands.latest::<GunTrigger>(&gun.entity) {
+                gun.trigger = trigger.pulled;
+            }
+            let Some(Some(physics_data)) = physics_elements.get(&gun.entity) else { continue };
+            let handle = physics_data.rigidbody_handle;
+            let Some(shooter) = rigidbody_set.get(handle) else { continue };
+
+            gun.cooldown -= delta_time;
+            while gun.trigger && gun.ammo > 0 && gun.cooldown <= 0.0 {
+                let muzzle = shooter.position() * Point3::from(gun.position);
+                let velocity = shooter.velocity_at_point(&muzzle) + shooter.rotation() * Vector3::z() * gun.definition.muzzle_velocity;
+                // Rounds due earlier in the step leave the muzzle partway through it and fly the
+                // `late` seconds left of it, they don't bunch up
+                let late = -gun.cooldown;
+                gun.rounds.push(Round { position: muzzle.coords, velocity, time: late - delta_time });
+                gun.ammo -= 1;
+                gun.cooldown += gun.definition.round_interval();
+            }
+            gun.cooldown = gun.cooldown.max(0.0);
+
+            let filter = QueryFilter::default().exclude_sensors().exclude_rigid_body(handle);
+            let mut hits = Vec::new();
+            gun.rounds.retain_mut(|round| {
+                let start = round.position;
+                let airspeed = round.velocity - atmosphere.wind.sample(&round.position, time as f32);
+                let drag_factor = gun.definition.drag_factor(atmosphere.sample(round.position.y).density);
+                let flight_time = delta_time + round.time.min(0.0);
+                step_round(&mut round.position, &mut round.velocity, &airspeed, drag_factor, flight_time);
+                round.time += delta_time;
+
+                let path = round.position - start;
+                let length = path.norm();
+                if length > 0.0 {
+                    let ray = Ray::new(start.into(), path / length);
+                    if let Some((collider, time_of_impact)) = query_pipeline.cast_ray(rigidbody_set, collider_set, &ray, length, true, filter) {
+                        hits.push((collider_set[collider].parent(), ray.point_at(time_of_impact)));
+                        return false;
+                    }
+                }
+
+                if self.debug_rendering_enabled {
+                    self.renderizable_lines.push(DebugPhysicsMessageType::RenderizableLines([
+                        ManualVertex { position: start.into(), color: TRACER_COLOR },
+                        ManualVertex { position: round.position.into(), color: TRACER_COLOR },
+                    ]));
+                }
+                round.time < gun.definition.max_time_of_flight
+            });
+
+            // The terrain and whatever else isn't an entity just stops the round
+            for (body, point) in hits {
+                let Some(body) = body else { continue };
+                let Some(entity) = Self::entity_of(physics_elements, body) else { continue };
+                let local_point = rigidbody_set[body].position().inverse_transform_point(&point);
+                commands.push(EntityCommand::new(entity.clone(), Damage { amount: gun.definition.damage, point: local_point.coords, radius: gun.definition.damage_radius }));
+                gun.hits += 1;
+            }
+
+            if let Some(Some(physics_data)) = physics_elements.get_mut(&gun.entity) {
+                let data = GunData { ammo: gun.ammo, firing: gun.trigger && gun.ammo > 0, hits: gun.hits };
+                physics_data.metadata.insert("gun/status", data);
+            }
+        }
+    }
+
+    fn toggle_debug_rendering(&mut self) {
+        self.debug_rendering_enabled = !self.debug_rendering_enabled;
+    }
+
+    fn debug_lines(&self) -> &[DebugPhysicsMessageType] {
+        &self.renderizable_lines
+    }
+
+    fn save_state(&self) -> HashMap<String, String> {
+        self.guns.iter()
+            .filter_map(|gun| match ron::to_string(&GunState { ammo: gun.ammo, hits: gun.hits }) {
+                Ok(state) => Some((gun.state_key(), state)),
+                Err(error) => {
+                    eprintln!("Couldn't save the state of '{}': {}", gun.state_key(), error);
+                    None
+                },
+            })
+            .collect()
+    }
+
+    fn load_state(&mut self, states: &HashMap<String, String>) -> Result<(), String> {
+        for gun in self.guns.iter_mut() {
+            let Some(state) = states.get(&gun.state_key()) else { continue };
+            let state: GunState = ron::from_str(state).map_err(|error| format!("Bad state for '{}': {}", gun.state_key(), error))?;
+            gun.ammo = state.ammo;
+            gun.hits = state.hits;
+            gun.trigger = false;
+            gun.cooldown = 0.0;
+            gun.rounds.clear();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod definition;
+pub mod seeker;
+pub mod guidance;
+pub mod missile_logic;
+pub mod launcher;
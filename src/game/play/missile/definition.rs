@@ -0,0 +1,199 @@
+use std::f32::consts::PI;
+
+use nalgebra::{UnitQuaternion, Vector3};
+use serde::Deserialize;
+
+use crate::engine::game_nodes::game_object::{ColliderMaterial, ColliderType, GameObject, MetaData, Physics, RigidBodyData, Transform};
+use crate::game::play::missile::seeker::SeekerDefinition;
+
+/// # Missile Definition
+///
+/// One kind of missile, loaded from `assets/missiles/<name>.ron` and carried by the
+/// aircraft's pylons (see PylonDefinition).
+///
+/// ## Contents:
+/// - **Airframe**: launch mass, size (the drag reference area is its cross section) and drag.
+/// - **motor**: thrust curve, the propellant burns off in step with the impulse delivered.
+/// - **seeker**: what it can see and follow, and how far off its nose (see Seeker).
+/// - **Guidance**: proportional navigation with `navigation_constant`, pulling up to `max_g`
+///   once `guidance_delay` has taken it clear of the launcher.
+/// - **fuze** / **warhead**: goes off when the target passes within the fuze radius, hurting
+///   everything within the warhead's.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MissileDefinition {
+    pub name: String,
+    #[serde(default = "default_model")]
+    pub model: String,
+    #[serde(default = "default_model_scale")]
+    pub model_scale: Vector3<f32>,
+    pub launch_mass: f32, // kg
+    pub length: f32,      // m
+    pub diameter: f32,    // m
+    pub drag_coefficient: f32,
+    pub motor: MotorDefinition,
+    pub seeker: SeekerDefinition,
+    #[serde(default = "default_navigation_constant")]
+    pub navigation_constant: f32,
+    pub max_g: f32,
+    #[serde(default = "default_guidance_delay")]
+    pub guidance_delay: f32, // s after launch before it starts steering
+    pub max_flight_time: f32, // s, it blows itself up past it
+    pub fuze: FuzeDefinition,
+    pub warhead: WarheadDefinition,
+}
+
+fn default_model() -> String { "Missile/missile.gltf".to_string() }
+fn default_model_scale() -> Vector3<f32> { Vector3::new(1.0, 1.0, 1.0) }
+fn default_navigation_constant() -> f32 { 4.0 }
+fn default_guidance_delay() -> f32 { 0.3 }
+fn default_arming_time() -> f32 { 0.5 }
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct MotorDefinition {
+    pub thrust: Vec<(f32, f32)>, // (s since launch, N), straight lines between them and nothing past the last
+    pub propellant_mass: f32,    // kg, part of the launch mass
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FuzeDefinition {
+    pub radius: f32, // m
+    #[serde(default = "default_arming_time")]
+    pub arming_time: f32, // s after launch, before it neither the fuze nor a hit sets it off
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct WarheadDefinition {
+    pub damage: f32, // health taken off a part right at the blast, see Damage
+    pub radius: f32, // m
+}
+
+impl MotorDefinition {
+    /// N at `time` s after launch.
+    pub fn thrust_at(&self, time: f32) -> f32 {
+        let Some(last) = self.thrust.last() else { return 0.0 };
+        if time < 0.0 || time > last.0 {
+            return 0.0;
+        }
+
+        match self.thrust.windows(2).find(|pair| time <= pair[1].0) {
+            Some(pair) => {
+                let ((start, start_thrust), (end, end_thrust)) = (pair[0], pair[1]);
+                let ratio = if end > start { (time - start) / (end - start) } else { 1.0 };
+                start_thrust + (end_thrust - start_thrust) * ratio.clamp(0.0, 1.0)
+            },
+            None => last.1,
+        }
+    }
+
+    pub fn burn_time(&self) -> f32 {
+        self.thrust.last().map_or(0.0, |last| last.0)
+    }
+
+    /// N·s delivered by `time` s after launch.
+    pub fn impulse_at(&self, time: f32) -> f32 {
+        self.thrust.windows(2)
+            .map(|pair| {
+                let ((start, start_thrust), (end, _)) = (pair[0], pair[1]);
+                let until = time.clamp(start, end);
+                (until - start) * (start_thrust + self.thrust_at(until)) * 0.5
+            })
+            .sum()
+    }
+
+    /// Propellant left `time` s after launch.
+    pub fn propellant_at(&self, time: f32) -> f32 {
+        let total = self.impulse_at(self.burn_time());
+        if total > 0.0 { self.propellant_mass * (1.0 - self.impulse_at(time) / total) } else { 0.0 }
+    }
+}
+
+impl MissileDefinition {
+    /// Loads `assets/missiles/<name>.ron`, `name` being the id the pylons use (e.g. "aim9").
+    pub fn load(name: &str) -> Result<MissileDefinition, String> {
+        let path = format!("assets/missiles/{}.ron", name);
+
+        match std::fs::read_to_string(&path) {
+            Ok(ron_result_string) => {
+                match ron::from_str::<MissileDefinition>(&ron_result_string) {
+                    Ok(definition) => Ok(definition),
+                    Err(error) => Err(format!("Something went wrong structuring the missile '{}': {}", path, error)),
+                }
+            },
+            Err(err) => Err(format!("Something went wrong reading the missile '{}': {}", path, err)),
+        }
+    }
+
+    /// m² the drag is worked out over.
+    pub fn reference_area(&self) -> f32 {
+        PI * self.diameter * self.diameter * 0.25
+    }
+
+    /// kg `time` s after launch, lighter as the motor burns.
+    pub fn mass_at(&self, time: f32) -> f32 {
+        self.launch_mass - self.motor.propellant_mass + self.motor.propellant_at(time)
+    }
+
+    /// The missile as a level object, `id` in the world at `position`, pointing down `rotation`'s
+    /// z and moving at `linvel` (the launcher's, it's dropped before the motor pushes it away).
+    pub fn game_object(&self, id: &str, position: Vector3<f32>, rotation: UnitQuaternion<f32>, linvel: Vector3<f32>) -> GameObject {
+        let radius = self.diameter * 0.5;
+
+        GameObject {
+            id: id.to_string(),
+            model: self.model.clone(),
+            transform: Transform { position, rotation, scale: self.model_scale },
+            children: Vec::new(),
+            metadata: MetaData {
+                physics: Some(Physics {
+                    rigidbody: RigidBodyData {
+                        is_static: false,
+                        mass: self.launch_mass,
+                        center_of_mass: Vector3::zeros(),
+                        initial_velocity: linvel,
+                        initial_angular_velocity: Vector3::zeros(),
+                        linear_damping: 0.0,
+                        angular_damping: 0.0,
+                        // Several times its own length per step once the motor is done
+                        ccd: true,
+                        gravity_scale: 1.0,
+                    },
+                    colliders: vec![ColliderType::Cuboid { half_extents: (radius, radius, self.length * 0.5), position: (0.0, 0.0, 0.0) }],
+                    // Massless, MissileLogic sets the body's mass as the motor burns
+                    material: Some(ColliderMaterial { density: 0.0, ..ColliderMaterial::default() }),
+                }),
+                cameras: None,
+                lighting: None,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_motor_burns_its_propellant_along_the_thrust_curve() {
+        let missile = MissileDefinition::load("aim7").unwrap();
+        let motor = &missile.motor;
+        let burn_time = motor.burn_time();
+
+        // Boost then sustain, nothing once it's burnt out
+        assert!(motor.thrust_at(1.0) > motor.thrust_at(burn_time * 0.8) * 2.0);
+        assert_eq!(motor.thrust_at(burn_time + 0.1), 0.0);
+
+        assert_eq!(missile.mass_at(0.0), missile.launch_mass);
+        assert!(missile.mass_at(1.0) < missile.launch_mass);
+        assert!((missile.mass_at(burn_time) - (missile.launch_mass - motor.propellant_mass)).abs() < 1e-3);
+        assert_eq!(missile.mass_at(burn_time + 10.0), missile.mass_at(burn_time));
+    }
+
+    #[test]
+    fn every_missile_loads() {
+        for name in ["aim9", "aim7", "aim120"] {
+            let missile = MissileDefinition::load(name).unwrap();
+            assert!(missile.motor.propellant_mass < missile.launch_mass, "{}", name);
+            assert!(missile.fuze.radius < missile.warhead.radius, "{}", name);
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use nalgebra::Vector3;
+
+const GRAVITY: f32 = 9.81;
+
+/// # Proportional Navigation
+///
+/// Turns the missile at `navigation_constant` times the rate the line of sight to the target
+/// rotates, so that the line of sight stops turning: both keep heading for the same point and
+/// meet there (a collision course). `relative_position` and `relative_velocity` are the
+/// target's, from the missile. Returns the acceleration (m/s²) to pull, across the line of sight.
+pub fn proportional_navigation(relative_position: &Vector3<f32>, relative_velocity: &Vector3<f32>, navigation_constant: f32) -> Vector3<f32> {
+    let distance_squared = relative_position.norm_squared();
+    if distance_squared < 1e-6 {
+        return Vector3::zeros();
+    }
+
+    let line_of_sight_rate = relative_position.cross(relative_velocity) / distance_squared;
+    let closing_speed = -relative_position.dot(relative_velocity) / distance_squared.sqrt();
+
+    line_of_sight_rate.cross(&relative_position.normalize()) * navigation_constant * closing_speed
+}
+
+/// The acceleration the missile flying at `velocity` can actually pull for `command`: only across
+/// its flight path (the motor and drag decide the rest), with gravity's pull down made up for,
+/// and no more than `max_g` in all.
+pub fn limit_command(command: &Vector3<f32>, velocity: &Vector3<f32>, max_g: f32) -> Vector3<f32> {
+    let Some(direction) = velocity.try_normalize(1e-3) else {
+        return Vector3::zeros();
+    };
+
+    let gravity = Vector3::new(0.0, -GRAVITY, 0.0);
+    let lateral = |vector: Vector3<f32>| vector - direction * vector.dot(&direction);
+    let command = lateral(*command - gravity);
+
+    command.cap_magnitude(max_g * GRAVITY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_turning_line_of_sight_asks_for_a_turn() {
+        // Head on, the line of sight never moves
+        let head_on = proportional_navigation(&Vector3::new(0.0, 0.0, 3000.0), &Vector3::new(0.0, 0.0, -900.0), 4.0);
+        assert!(head_on.norm() < 1e-3);
+
+        // A target crossing left to right pulls the missile right, harder the faster it crosses
+        let crossing = proportional_navigation(&Vector3::new(0.0, 0.0, 3000.0), &Vector3::new(200.0, 0.0, -600.0), 4.0);
+        let faster = proportional_navigation(&Vector3::new(0.0, 0.0, 3000.0), &Vector3::new(400.0, 0.0, -600.0), 4.0);
+        assert!(crossing.x > 0.0 && crossing.y.abs() < 1e-3);
+        assert!(faster.x > crossing.x);
+        // 4 * 600m/s * (200 / 3000)rad/s
+        assert!((crossing.x - 160.0).abs() < 1.0, "pulled {}m/s²", crossing.x);
+    }
+
+    #[test]
+    fn commands_are_limited_to_what_the_airframe_pulls() {
+        let velocity = Vector3::new(0.0, 0.0, 600.0);
+
+        // Flying straight it still holds itself up against gravity
+        let level = limit_command(&Vector3::zeros(), &velocity, 30.0);
+        assert!((level - Vector3::new(0.0, GRAVITY, 0.0)).norm() < 1e-3);
+
+        // Nothing along the flight path, and no more than max_g
+        let hard = limit_command(&Vector3::new(1000.0, 0.0, 1000.0), &velocity, 30.0);
+        assert!(hard.z.abs() < 1e-3);
+        assert!((hard.norm() - 30.0 * GRAVITY).abs() < 1e-2);
+    }
+}
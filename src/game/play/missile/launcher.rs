@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+
+use nalgebra::Vector3;
+
+use crate::app::App;
+use crate::engine::physics::commands::EntityCommand;
+use crate::engine::physics::physics_handler::{PhysicsCommand, RenderMessage, WorldChanges};
+use crate::game::play::missile::definition::MissileDefinition;
+use crate::game::play::missile::missile_logic::{MissileData, MissileLaunch};
+use crate::game::play::missile::seeker::SeekerKind;
+use crate::game::play::plane::aircraft::PylonDefinition;
+use crate::resources::{despawn_game_object, spawn_game_object};
+
+pub struct Pylon {
+    pub position: Vector3<f32>, // in the launcher's local space
+    pub missile: String,
+    pub definition: MissileDefinition,
+    pub loaded: bool,
+}
+
+/// # Missile Launcher
+///
+/// The main thread's side of the missiles one entity carries: the player's plane, or any
+/// other that's given its pylons and fires them the same way.
+///
+/// ## Contents:
+/// - **Firing**: the next loaded pylon's missile is spawned under it, and sent its
+///   `MissileLaunch` so `MissileLogic` flies it from the next step. The launcher drawn here
+///   trails the physics, so it's `MissileLogic` that puts the missile exactly under the pylon
+///   and gives it the launcher's velocity.
+/// - **Clean up**: the missiles the physics reports `detonated` are despawned.
+pub struct MissileLauncher {
+    pub entity: String,
+    pub pylons: Vec<Pylon>,
+    in_flight: Vec<String>,
+    fired: usize, // missiles fired so far, they're named after it
+}
+
+impl MissileLauncher {
+    /// Pylons with a missile that doesn't load are left out.
+    pub fn new(entity: impl Into<String>, pylons: &[PylonDefinition]) -> Self {
+        let pylons = pylons.iter()
+            .filter_map(|pylon| match MissileDefinition::load(&pylon.missile) {
+                Ok(definition) => Some(Pylon { position: pylon.position, missile: pylon.missile.clone(), definition, loaded: true }),
+                Err(error) => {
+                    eprintln!("Error: {}", error);
+                    None
+                },
+            })
+            .collect();
+
+        Self { entity: entity.into(), pylons, in_flight: Vec::new(), fired: 0 }
+    }
+
+    pub fn loaded(&self) -> usize {
+        self.pylons.iter().filter(|pylon| pylon.loaded).count()
+    }
+
+    /// Loads every pylon again.
+    pub fn rearm(&mut self) {
+        for pylon in self.pylons.iter_mut() {
+            pylon.loaded = true;
+        }
+    }
+
+    /// Fires the next loaded pylon at `target`, or at whatever the seeker finds ahead for None.
+    /// Returns the kind of seeker it went with, for the brevity call.
+    pub fn fire(&mut self, app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>, command_tx: Option<&Sender<EntityCommand>>, target: Option<String>) -> Result<SeekerKind, String> {
+        let command_tx = command_tx.ok_or("There's no physics to fire missiles in")?;
+        let launcher = app.renderizable_instances.get(&self.entity).ok_or_else(|| format!("There is no '{}' to fire from", self.entity))?;
+        let (position, rotation) = (launcher.instance.transform.position, launcher.instance.transform.rotation);
+        let id = format!("{}{}", self.missile_prefix(), self.fired);
+        let pylon = self.pylons.iter_mut().find(|pylon| pylon.loaded).ok_or("Out of missiles")?;
+
+        let game_object = pylon.definition.game_object(&id, position + rotation * pylon.position, rotation, Vector3::zeros());
+        spawn_game_object(app, physics_tx, game_object)?;
+
+        let launch = MissileLaunch { missile: pylon.missile.clone(), definition: pylon.definition.clone(), launcher: self.entity.clone(), pylon: pylon.position, target };
+        command_tx.send(EntityCommand::new(id.clone(), launch)).map_err(|error| error.to_string())?;
+
+        pylon.loaded = false;
+        self.fired += 1;
+        self.in_flight.push(id);
+        Ok(pylon.definition.seeker.kind)
+    }
+
+    /// Keeps track of the missiles a loaded physics snapshot (see WorldChanges) took out or
+    /// brought back, and doesn't reuse the name of any it brought back. Runs after the level
+    /// was reconciled (see reconcile_physics_world), only what's drawn is in flight.
+    pub fn reconcile(&mut self, app: &App, changes: &WorldChanges) {
+        self.in_flight.retain(|id| !changes.removed.contains(id));
+
+        let prefix = self.missile_prefix();
+        for id in &changes.added {
+            let Some(number) = id.strip_prefix(&prefix).and_then(|number| number.parse::<usize>().ok()) else { continue };
+            self.fired = self.fired.max(number + 1);
+            if app.renderizable_instances.contains_key(id) && !self.in_flight.contains(id) {
+                self.in_flight.push(id.clone());
+            }
+        }
+    }
+
+    fn missile_prefix(&self) -> String {
+        format!("{}_missile_", self.entity)
+    }
+
+    /// Despawns the missiles that went off since the last frame.
+    pub fn update(&mut self, app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>, physics_data: &HashMap<String, RenderMessage>) {
+        let (spent, in_flight): (Vec<String>, Vec<String>) = self.in_flight.drain(..).partition(|id| {
+            physics_data.get(id).and_then(|data| data.metadata.get::<MissileData>("missile/status")).is_some_and(|missile| missile.detonated)
+        });
+        self.in_flight = in_flight;
+
+        for id in spent {
+            if let Err(error) = despawn_game_object(app, physics_tx, &id) {
+                eprintln!("{}", error);
+            }
+        }
+    }
+}
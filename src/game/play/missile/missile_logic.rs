@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::sync::mpsc::Sender;
+
+use nalgebra::{Point3, UnitQuaternion, Vector3};
+use rapier3d::prelude::{ColliderSet, Isometry, QueryPipeline, RigidBody, RigidBodyHandle, RigidBodySet};
+use serde::{Deserialize, Serialize};
+
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::commands::{CommandQueue, EntityCommand};
+use crate::engine::physics::events::{PhysicsEvent, PhysicsEventKind};
+use crate::engine::physics::physics::DebugPhysicsMessageType;
+use crate::engine::physics::physics_handler::{PhysicsData, PhysicsTick};
+use crate::engine::primitive::manual_vertex::ManualVertex;
+use crate::game::play::missile::definition::MissileDefinition;
+use crate::game::play::missile::guidance::{limit_command, proportional_navigation};
+use crate::game::play::missile::seeker::{Seeker, SeekerKind};
+use crate::game::play::plane::damage::Damage;
+
+// Parts of a plane can be this far from its center and still be caught by a blast
+const TARGET_REACH: f32 = 15.0;
+const LOCK_COLOR: [f32; 3] = [1.0, 0.3, 0.0];
+const COMMAND_COLOR: [f32; 3] = [1.0, 1.0, 0.0];
+
+/// Sent to a missile that was just spawned (its own entity), for `MissileLogic` to fly it.
+#[derive(Debug, Clone)]
+pub struct MissileLaunch {
+    pub missile: String, // the definition's id, e.g. "aim9"
+    pub definition: MissileDefinition,
+    pub launcher: String,
+    pub pylon: Vector3<f32>,    // in the launcher's local space, the missile is put there on its first step
+    pub target: Option<String>, // None lets the seeker pick whatever it sees ahead
+}
+
+/// A missile in flight, reported as its `missile/status` metadata.
+#[derive(Debug, Clone)]
+pub struct MissileData {
+    pub target: Option<String>, // what its seeker is following
+    pub burning: bool,          // motor still pushing
+    pub detonated: bool,        // spent, waiting to be despawned
+}
+
+/// What a physics snapshot keeps of a missile in flight, the definition is loaded again.
+#[derive(Serialize, Deserialize)]
+struct MissileState {
+    missile: String,
+    launcher: String,
+    target: Option<String>,
+    lost: bool,
+    time: f32,
+    detonated: bool,
+}
+
+struct Missile {
+    entity: String,
+    missile: String,
+    definition: MissileDefinition,
+    launcher: String,
+    pylon: Vector3<f32>,
+    seeker: Seeker,
+    time: f32, // s since launch
+    spawned: bool, // the launch can get here a step before the body does
+    detonated: bool,
+    command: Vector3<f32>, // acceleration pulled last tick, for the debug lines
+}
+
+/// # Missile Logic
+///
+/// Flies every missile in the world: each one is spawned like any other body and then sent a
+/// `MissileLaunch` (see MissileLauncher), from then on it's driven here until it goes off.
+///
+/// ## Contents:
+/// - **Launch**: on its first step a missile is put under the launcher's pylon, as the physics
+///   has the launcher then, with the launcher's velocity there.
+/// - **Motor and drag**: thrust along the body from the motor's curve, the burnt propellant
+///   taken off the rigidbody's mass, and drag over the cross section against the airspeed.
+/// - **Guidance**: the seeker's target is chased with proportional navigation, pulling across
+///   the flight path only. The body always points down its velocity.
+/// - **Fuze**: armed after `arming_time`, it goes off on hitting anything but the launcher, or
+///   when the target passes within the fuze radius during a step. Everything within reach of
+///   the blast is sent a `Damage`, this tick runs before the planes' so it lands the same step.
+/// - **Self destruct**: past `max_flight_time`. A spent missile is disabled where it went off
+///   and reported `detonated` for the main thread to despawn.
+#[derive(Default)]
+pub struct MissileLogic {
+    missiles: Vec<Missile>,
+    debug_rendering_enabled: bool,
+    renderizable_lines: Vec<DebugPhysicsMessageType>,
+}
+
+impl MissileLogic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn launch(&mut self, entity: String, launch: MissileLaunch) {
+        self.missiles.push(Missile {
+            entity,
+            missile: launch.missile,
+            seeker: Seeker::new(&launch.definition.seeker, launch.target),
+            definition: launch.definition,
+            launcher: launch.launcher,
+            pylon: launch.pylon,
+            time: 0.0,
+            spawned: false,
+            detonated: false,
+            command: Vector3::zeros(),
+        });
+    }
+
+    fn state_key(entity: &str) -> String {
+        format!("{}/missile", entity)
+    }
+
+    fn is_missile(&self, entity: &str) -> bool {
+        self.missiles.iter().any(|missile| missile.entity == entity)
+    }
+
+    /// Position and velocity of every body a seeker could follow: whatever moves, except missiles,
+    /// and whatever a missile was fired at even if it doesn't (e.g. a locked ground target).
+    fn targets(&self, rigidbody_set: &RigidBodySet, physics_elements: &HashMap<String, Option<PhysicsData>>) -> HashMap<String, (Vector3<f32>, Vector3<f32>)> {
+        let designated: Vec<&String> = self.missiles.iter().filter_map(|missile| missile.seeker.target.as_ref()).collect();
+
+        physics_elements.iter()
+            .filter(|(entity, _)| !self.is_missile(entity))
+            .filter_map(|(entity, physics_data)| {
+                let rigidbody = rigidbody_set.get(physics_data.as_ref()?.rigidbody_handle)?;
+                (rigidbody.is_dynamic() || designated.contains(&entity)).then(|| (entity.clone(), (*rigidbody.translation(), *rigidbody.linvel())))
+            })
+            .collect()
+    }
+
+    // Sends the blast at `point` to everything close enough to be hurt, in each one's own space
+    fn detonate(missile: &Missile, point: Vector3<f32>, rigidbody_set: &RigidBodySet, physics_elements: &HashMap<String, Option<PhysicsData>>, commands: &mut CommandQueue) {
+        let warhead = &missile.definition.warhead;
+
+        for (entity, physics_data) in physics_elements {
+            let Some(rigidbody) = physics_data.as_ref().and_then(|physics_data| rigidbody_set.get(physics_data.rigidbody_handle)) else { continue };
+            if *entity == missile.entity || (rigidbody.translation() - point).norm() > warhead.radius + TARGET_REACH {
+                continue;
+            }
+
+            let local_point = rigidbody.position().inverse_transform_point(&point.into());
+            commands.push(EntityCommand::new(entity.clone(), Damage { amount: warhead.damage, point: local_point.coords, radius: warhead.radius }));
+        }
+    }
+
+    // Where this step sets the fuze off, if it does
+    fn fuze(missile: &Missile, rigidbody: &RigidBody, events: &[PhysicsEvent], target: Option<&(Vector3<f32>, Vector3<f32>)>, delta_time: f32) -> Option<Vector3<f32>> {
+        if missile.time < missile.definition.fuze.arming_time {
+            return None;
+        }
+
+        let position = *rigidbody.translation();
+        let hit = events.iter().any(|event| {
+            event.kind == PhysicsEventKind::CollisionStarted && event.other(&missile.entity) != Some(missile.launcher.as_str())
+        });
+        if hit {
+            return Some(position);
+        }
+
+        // Closest the target gets over the coming step, moving in a straight line relative to the missile
+        let (target_position, target_velocity) = target?;
+        let relative_position = target_position - position;
+        let relative_velocity = target_velocity - rigidbody.linvel();
+        let closest_time = if relative_velocity.norm_squared() > 0.0 {
+            (-relative_position.dot(&relative_velocity) / relative_velocity.norm_squared()).clamp(0.0, delta_time)
+        } else {
+            0.0
+        };
+        let miss_distance = (relative_position + relative_velocity * closest_time).norm();
+
+        (miss_distance <= missile.definition.fuze.radius).then(|| position + rigidbody.linvel() * closest_time)
+    }
+
+    // Puts a missile that was just spawned under its launcher's pylon, moving with it: the main
+    // thread spawned it where it saw the launcher, which trails the physics
+    fn place(missile: &Missile, handle: RigidBodyHandle, rigidbody_set: &mut RigidBodySet, physics_elements: &HashMap<String, Option<PhysicsData>>) {
+        let Some(Some(launcher)) = physics_elements.get(&missile.launcher) else { return };
+        let Some(launcher) = rigidbody_set.get(launcher.rigidbody_handle) else { return };
+        let position = launcher.position() * Point3::from(missile.pylon);
+        let (rotation, linvel) = (*launcher.rotation(), launcher.velocity_at_point(&position));
+
+        let rigidbody = &mut rigidbody_set[handle];
+        rigidbody.set_position(Isometry::from_parts(position.coords.into(), rotation), true);
+        rigidbody.set_linvel(linvel, true);
+        rigidbody.set_angvel(Vector3::zeros(), true);
+    }
+
+    fn fly(missile: &mut Missile, rigidbody: &mut RigidBody, atmosphere: &Atmosphere, target: Option<&(Vector3<f32>, Vector3<f32>)>, time: f32, delta_time: f32) {
+        let definition = &missile.definition;
+        let velocity = *rigidbody.linvel();
+
+        rigidbody.reset_forces(true);
+        // Its colliders weigh nothing, this is all of its mass. The step the motor burns out
+        // in sets it one last time, with all the propellant gone.
+        if missile.time - delta_time <= definition.motor.burn_time() {
+            rigidbody.set_additional_mass(definition.mass_at(missile.time), true);
+        }
+
+        let air = atmosphere.sample(rigidbody.translation().y);
+        let airspeed = velocity - atmosphere.wind.sample(rigidbody.translation(), time);
+        let drag = -airspeed * airspeed.norm() * 0.5 * air.density * definition.drag_coefficient * definition.reference_area();
+        let thrust = rigidbody.rotation() * Vector3::z() * definition.motor.thrust_at(missile.time);
+
+        missile.command = match target {
+            Some((target_position, target_velocity)) if missile.time >= definition.guidance_delay => {
+                let command = proportional_navigation(&(target_position - rigidbody.translation()), &(target_velocity - velocity), definition.navigation_constant);
+                limit_command(&command, &velocity, definition.max_g)
+            },
+            _ => Vector3::zeros(),
+        };
+
+        rigidbody.add_force(thrust + drag + missile.command * rigidbody.mass(), true);
+
+        // Fins keep it flying nose first, no need to work out its rotation
+        if let Some(direction) = velocity.try_normalize(1.0) {
+            let up = if direction.y.abs() < 0.99 { Vector3::y() } else { Vector3::x() };
+            rigidbody.set_rotation(UnitQuaternion::face_towards(&direction, &up), true);
+            rigidbody.set_angvel(Vector3::zeros(), true);
+        }
+    }
+}
+
+impl PhysicsTick for MissileLogic {
+    fn tick(&mut self, commands: &mut CommandQueue, _collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, _query_pipeline: &QueryPipeline, atmosphere: &Atmosphere, physics_elements: &mut HashMap<String, Option<PhysicsData>>, _debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, time: f64, delta_time: f32) {
+        for (entity, launch) in commands.take_all::<MissileLaunch>() {
+            self.launch(entity, launch);
+        }
+        // Despawned, there's nothing left to fly
+        self.missiles.retain(|missile| !missile.spawned || physics_elements.contains_key(&missile.entity));
+
+        self.renderizable_lines.clear();
+        let targets = self.targets(rigidbody_set, physics_elements);
+        let launchers: HashMap<String, (Vector3<f32>, Vector3<f32>)> = self.missiles.iter()
+            .filter_map(|missile| {
+                let rigidbody = rigidbody_set.get(physics_elements.get(&missile.launcher)?.as_ref()?.rigidbody_handle)?;
+                Some((missile.launcher.clone(), (*rigidbody.translation(), rigidbody.rotation() * Vector3::z())))
+            })
+            .collect();
+
+        for missile in self.missiles.iter_mut() {
+            let events = commands.take::<PhysicsEvent>(&missile.entity);
+            let Some(Some(physics_data)) = physics_elements.get(&missile.entity) else { continue };
+            let handle = physics_data.rigidbody_handle;
+            if !missile.spawned {
+                missile.spawned = true;
+                Self::place(missile, handle, rigidbody_set, physics_elements);
+            }
+            if missile.detonated {
+                continue;
+            }
+            missile.time += delta_time;
+
+            let Some(rigidbody) = rigidbody_set.get(handle) else { continue };
+            let (position, nose) = (*rigidbody.translation(), rigidbody.rotation() * Vector3::z());
+
+            // A semi-active seeker only sees what the launcher's radar is pointed at
+            let illuminated = |target_position: &Vector3<f32>| match missile.seeker.definition.kind {
+                SeekerKind::SemiActiveRadar => launchers.get(&missile.launcher).is_some_and(|(launcher_position, launcher_nose)| {
+                    launcher_nose.angle(&(target_position - launcher_position)).to_degrees() <= missile.seeker.definition.gimbal_limit
+                }),
+                _ => true,
+            };
+            let tracked = missile.seeker.target.as_ref()
+                .and_then(|target| targets.get(target))
+                .is_some_and(|(target_position, _)| missile.seeker.can_track(&nose, &(target_position - position)) && illuminated(target_position));
+            if !tracked {
+                if missile.seeker.target.take().is_some() {
+                    missile.seeker.lost = true;
+                } else if !missile.seeker.lost {
+                    // Fired with no lock, it takes whatever comes in front of it
+                    let candidates = targets.iter()
+                        .filter(|(entity, (target_position, _))| **entity != missile.launcher && illuminated(target_position))
+                        .map(|(entity, (target_position, _))| (entity, target_position - position));
+                    missile.seeker.target = missile.seeker.acquire(&nose, candidates);
+                }
+            }
+            let target = missile.seeker.target.as_ref().and_then(|target| targets.get(target));
+
+            let burst = Self::fuze(missile, rigidbody, &events, target, delta_time);
+            let burst = burst.or_else(|| (missile.time >= missile.definition.max_flight_time).then_some(position));
+            match burst {
+                Some(point) => {
+                    missile.detonated = true;
+                    missile.command = Vector3::zeros();
+                    Self::detonate(missile, point, rigidbody_set, physics_elements, commands);
+                    // Nothing left to hit or be hit by until it's despawned
+                    rigidbody_set[handle].set_enabled(false);
+                },
+                None => Self::fly(missile, &mut rigidbody_set[handle], atmosphere, target, time as f32, delta_time),
+            }
+
+            if self.debug_rendering_enabled {
+                let position: [f32; 3] = position.into();
+                if let Some((target_position, _)) = target {
+                    self.renderizable_lines.push(DebugPhysicsMessageType::RenderizableLines([
+                        ManualVertex { position, color: LOCK_COLOR },
+                        ManualVertex { position: (*target_position).into(), color: LOCK_COLOR },
+                    ]));
+                }
+                self.renderizable_lines.push(DebugPhysicsMessageType::RenderizableLines([
+                    ManualVertex { position, color: COMMAND_COLOR },
+                    ManualVertex { position: (Vector3::from(position) + missile.command * 0.1).into(), color: COMMAND_COLOR },
+                ]));
+            }
+        }
+
+        for missile in &self.missiles {
+            if let Some(Some(physics_data)) = physics_elements.get_mut(&missile.entity) {
+                let data = MissileData {
+                    target: missile.seeker.target.clone(),
+                    burning: !missile.detonated && missile.definition.motor.thrust_at(missile.time) > 0.0,
+                    detonated: missile.detonated,
+                };
+                physics_data.metadata.insert("missile/status", data);
+            }
+        }
+    }
+
+    fn toggle_debug_rendering(&mut self) {
+        self.debug_rendering_enabled = !self.debug_rendering_enabled;
+    }
+
+    fn debug_lines(&self) -> &[DebugPhysicsMessageType] {
+        &self.renderizable_lines
+    }
+
+    fn save_state(&self) -> HashMap<String, String> {
+        self.missiles.iter()
+            .filter_map(|missile| {
+                let state = MissileState {
+                    missile: missile.missile.clone(),
+                    launcher: missile.launcher.clone(),
+                    target: missile.seeker.target.clone(),
+                    lost: missile.seeker.lost,
+                    time: missile.time,
+                    detonated: missile.detonated,
+                };
+                match ron::to_string(&state) {
+                    Ok(state) => Some((Self::state_key(&missile.entity), state)),
+                    Err(error) => {
+                        eprintln!("Couldn't save the state of '{}': {}", missile.entity, error);
+                        None
+                    },
+                }
+            })
+            .collect()
+    }
+
+    fn load_state(&mut self, states: &HashMap<String, String>) -> Result<(), String> {
+        let mut missiles = Vec::new();
+        for (key, state) in states {
+            let Some(entity) = key.strip_suffix("/missile") else { continue };
+            let state: MissileState = ron::from_str(state).map_err(|error| format!("Bad state for '{}': {}", key, error))?;
+            let definition = MissileDefinition::load(&state.missile)?;
+            let mut seeker = Seeker::new(&definition.seeker, state.target);
+            seeker.lost = state.lost;
+            missiles.push(Missile {
+                entity: entity.to_string(),
+                missile: state.missile,
+                seeker,
+                definition,
+                launcher: state.launcher,
+                pylon: Vector3::zeros(), // it was placed before it was saved
+                time: state.time,
+                spawned: true,
+                detonated: state.detonated,
+                command: Vector3::zeros(),
+            });
+        }
+
+        self.missiles = missiles;
+        Ok(())
+    }
+}
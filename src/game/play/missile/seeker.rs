@@ -0,0 +1,88 @@
+use nalgebra::Vector3;
+use serde::Deserialize;
+
+/// How the seeker finds its target, which is also the brevity call for the launch.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub enum SeekerKind {
+    SemiActiveRadar, // follows the launcher's radar, lost once the target leaves the launcher's own gimbal limits
+    Infrared,
+    ActiveRadar,
+}
+
+impl SeekerKind {
+    pub fn brevity(&self) -> &'static str {
+        match self {
+            SeekerKind::SemiActiveRadar => "FOX 1",
+            SeekerKind::Infrared => "FOX 2",
+            SeekerKind::ActiveRadar => "FOX 3",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SeekerDefinition {
+    pub kind: SeekerKind,
+    pub field_of_view: f32, // degrees off the nose it acquires a new target within
+    pub gimbal_limit: f32,  // degrees off the nose it keeps following one
+    pub range: f32,         // m
+}
+
+/// # Seeker
+///
+/// Picks a target for a missile and keeps it while it can see it.
+///
+/// ## Contents:
+/// - **Acquisition**: with no target yet, the one closest to the nose within `field_of_view`
+///   and `range` is taken (e.g. the plane ahead when it's fired with no lock).
+/// - **Tracking**: a target further off the nose than `gimbal_limit`, or out of range, is lost
+///   for good (`lost`) and the missile flies on unguided.
+pub struct Seeker {
+    pub definition: SeekerDefinition,
+    pub target: Option<String>,
+    pub lost: bool,
+}
+
+impl Seeker {
+    pub fn new(definition: &SeekerDefinition, target: Option<String>) -> Self {
+        Self { definition: definition.clone(), target, lost: false }
+    }
+
+    /// Whether something `line_of_sight` away (missile to target) is within `limit` degrees of `nose` and in range.
+    fn sees(&self, nose: &Vector3<f32>, line_of_sight: &Vector3<f32>, limit: f32) -> bool {
+        let distance = line_of_sight.norm();
+        distance > 0.0 && distance <= self.definition.range && nose.angle(line_of_sight).to_degrees() <= limit
+    }
+
+    pub fn can_track(&self, nose: &Vector3<f32>, line_of_sight: &Vector3<f32>) -> bool {
+        self.sees(nose, line_of_sight, self.definition.gimbal_limit)
+    }
+
+    /// The candidate (entity and line of sight to it) closest to the nose within the field of view.
+    pub fn acquire<'a>(&self, nose: &Vector3<f32>, candidates: impl Iterator<Item = (&'a String, Vector3<f32>)>) -> Option<String> {
+        candidates
+            .filter(|(_, line_of_sight)| self.sees(nose, line_of_sight, self.definition.field_of_view))
+            .min_by(|(_, a), (_, b)| nose.angle(a).total_cmp(&nose.angle(b)))
+            .map(|(entity, _)| entity.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seekers_acquire_ahead_and_lose_targets_past_the_gimbal_limit() {
+        let definition = SeekerDefinition { kind: SeekerKind::Infrared, field_of_view: 10.0, gimbal_limit: 40.0, range: 8000.0 };
+        let seeker = Seeker::new(&definition, None);
+        let nose = Vector3::z();
+        let (ahead, off_nose, too_far) = ("ahead".to_string(), "off_nose".to_string(), "too_far".to_string());
+
+        let candidates = [(&off_nose, Vector3::new(500.0, 0.0, 1000.0)), (&ahead, Vector3::new(50.0, 0.0, 2000.0)), (&too_far, Vector3::new(0.0, 0.0, 9000.0))];
+        assert_eq!(seeker.acquire(&nose, candidates.into_iter()), Some(ahead));
+
+        // 26° off is too far to pick up but close enough to keep following
+        assert!(seeker.can_track(&nose, &Vector3::new(500.0, 0.0, 1000.0)));
+        assert!(!seeker.can_track(&nose, &Vector3::new(1000.0, 0.0, 500.0)));
+        assert!(!seeker.can_track(&nose, &Vector3::new(0.0, 0.0, 9000.0)));
+    }
+}
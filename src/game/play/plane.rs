@@ -3,3 +3,9 @@ pub mod plane;
 pub mod physics_logic;
 pub mod flight_system;
 pub mod utils;
+pub mod aircraft;
+pub mod simulation;
+pub mod engine;
+pub mod fly_by_wire;
+pub mod damage;
+pub mod metadata;
\ No newline at end of file
@@ -0,0 +1,302 @@
+use nalgebra::Vector3;
+use serde::Deserialize;
+
+use crate::game::play::plane::plane::PlaneControls;
+
+/// # Aircraft Definition
+///
+/// Everything the flight model needs to know about one aircraft, loaded from
+/// `assets/aircraft/<name>.ron` instead of being hard-coded in the wing/wheel managers,
+/// so each plane in the selection screen flies with its own aerodynamics.
+///
+/// ## Contents:
+/// - **model** / **model_scale**: the glTF the player is drawn with and its scale, whatever the level placed there.
+/// - **mass** / **center_of_mass**: empty aircraft, replace whatever the level declared for the player body.
+/// - **engine**: thrust, spool and fuel consumption of the (combined) engines.
+/// - **fuel**: internal tanks, burnt fuel is taken off the rigidbody mass. No fuel system means endless fuel.
+/// - **fuselage**: side profile used for the sideslip (bluff body) force.
+/// - **surfaces**: every lifting/control surface, with its airfoil table and control mixing.
+/// - **gear**: every suspension ray, keyed by the wheel mesh it moves.
+/// - **speedbrake**: drag surface opened with the speedbrake switch, none if the plane has no airbrake.
+/// - **fly_by_wire**: control laws and limiters between the stick and the surfaces, none means direct control.
+/// - **impact_damage**: how hard the airframe hitting something damages the parts near the contact.
+///   Only the surfaces, engine and gear with a `damage` block can be damaged (see PlaneDamage).
+/// - **pylons**: the missiles it carries and where, fired in the order they're listed (see MissileLauncher).
+/// - **gun**: the cannon it carries, if any, where its muzzle is and how many rounds it's loaded with.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AircraftDefinition {
+    pub name: String,
+    pub model: String,
+    pub model_scale: f32,
+    pub mass: f32,
+    pub center_of_mass: Vector3<f32>,
+    pub engine: EngineDefinition,
+    #[serde(default)]
+    pub fuel: Option<FuelDefinition>,
+    pub fuselage: FuselageDefinition,
+    pub surfaces: Vec<SurfaceDefinition>,
+    #[serde(default)]
+    pub gear: Vec<GearDefinition>,
+    #[serde(default)]
+    pub speedbrake: Option<SpeedbrakeDefinition>,
+    #[serde(default)]
+    pub fly_by_wire: Option<FlyByWireDefinition>,
+    #[serde(default)]
+    pub impact_damage: ImpactDamageDefinition,
+    #[serde(default)]
+    pub pylons: Vec<PylonDefinition>,
+    #[serde(default)]
+    pub gun: Option<GunMountDefinition>,
+}
+
+fn default_afterburner_detent() -> f32 { 0.8 }
+fn default_spool_time() -> f32 { 4.0 }
+fn default_tsfc() -> f32 { 2.2e-5 }
+fn default_afterburner_tsfc() -> f32 { 5.7e-5 }
+fn default_g_limit() -> f32 { 9.0 }
+fn default_negative_g_limit() -> f32 { -3.0 }
+fn default_aoa_limit() -> f32 { 25.0 }
+fn default_engage_speed() -> f32 { 60.0 }
+fn default_rolling_resistance() -> f32 { 0.02 }
+fn default_tyre_friction() -> f32 { 0.8 }
+fn default_deploy_time() -> f32 { 4.0 }
+fn default_flap_deploy_time() -> f32 { 5.0 }
+fn default_leading_edge_deploy_time() -> f32 { 1.0 }
+fn default_speedbrake_deploy_time() -> f32 { 1.5 }
+fn default_impact_speed() -> f32 { 5.0 }
+fn default_impact_damage() -> f32 { 10.0 }
+fn default_impact_radius() -> f32 { 4.0 }
+
+/// All thrust figures are sea level, static (no forward speed).
+#[derive(Debug, Deserialize, Clone)]
+pub struct EngineDefinition {
+    pub max_thrust: f32, // N at military (full dry) power
+    #[serde(default)]
+    pub afterburner_thrust: Option<f32>, // N at full afterburner, None for engines without one
+    #[serde(default = "default_afterburner_detent")]
+    pub afterburner_detent: f32, // throttle position past which the afterburner lights
+    #[serde(default = "default_spool_time")]
+    pub spool_time: f32, // s from idle to military
+    #[serde(default = "default_tsfc")]
+    pub tsfc: f32, // kg of fuel per N of thrust per second
+    #[serde(default = "default_afterburner_tsfc")]
+    pub afterburner_tsfc: f32,
+    #[serde(default)]
+    pub position: Vector3<f32>, // where it sits in the model's local space, hits are measured from here
+    #[serde(default)]
+    pub damage: Option<ComponentDamageDefinition>,
+}
+
+/// A part that can be damaged, losing effectiveness as its health goes down and falling
+/// off once it's gone, taking its `mass` and the collider around `collider` with it.
+/// Parts without one can't be damaged.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ComponentDamageDefinition {
+    pub health: f32,
+    #[serde(default)]
+    pub meshes: Vec<String>, // hidden once the part is destroyed
+    #[serde(default)]
+    pub mass: f32, // kg of the aircraft's mass that falls off with it, at the part's position
+    #[serde(default)]
+    pub collider: Option<Vector3<f32>>, // a point inside the player body's collider that falls off with it, in the model's local space
+}
+
+/// Damage from the airframe hitting something at `closing speed - min_speed` m/s, given to
+/// the parts within `radius` of the contact.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ImpactDamageDefinition {
+    #[serde(default = "default_impact_speed")]
+    pub min_speed: f32,     // m/s, slower contacts (taxiing into a cone) do nothing
+    #[serde(default = "default_impact_damage")]
+    pub damage_per_speed: f32, // health per m/s past min_speed
+    #[serde(default = "default_impact_radius")]
+    pub radius: f32,        // m
+}
+
+impl Default for ImpactDamageDefinition {
+    fn default() -> Self {
+        Self { min_speed: default_impact_speed(), damage_per_speed: default_impact_damage(), radius: default_impact_radius() }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FlyByWireDefinition {
+    #[serde(default = "default_g_limit")]
+    pub g_limit: f32,
+    #[serde(default = "default_negative_g_limit")]
+    pub negative_g_limit: f32,
+    #[serde(default = "default_aoa_limit")]
+    pub aoa_limit: f32, // degrees
+    pub max_pitch_rate: f32, // rad/s
+    pub max_roll_rate: f32,  // rad/s at full stick
+    #[serde(default = "default_engage_speed")]
+    pub engage_speed: f32,   // m/s, below it the stick drives the surfaces directly
+    pub pitch: PidGains,
+    pub roll: PidGains,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy)]
+pub struct PidGains {
+    #[serde(default)]
+    pub p: f32,
+    #[serde(default)]
+    pub i: f32,
+    #[serde(default)]
+    pub d: f32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FuelDefinition {
+    pub capacity: f32, // kg
+    #[serde(default)]
+    pub initial: Option<f32>, // kg, full tanks if not set
+    #[serde(default)]
+    pub position: Vector3<f32>, // center of mass of the fuel, in the same local space as center_of_mass
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FuselageDefinition {
+    pub side_area: f32,
+    pub side_cd: f32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SurfaceDefinition {
+    pub label: String,
+    pub airfoil: String,
+    pub pressure_center: Vector3<f32>,
+    pub area: f32,
+    #[serde(default)]
+    pub chord: f32,
+    pub normal: Vector3<f32>,
+    #[serde(default)]
+    pub is_roll_axis: bool,
+    #[serde(default)]
+    pub stable: bool,
+    #[serde(default)]
+    pub incidence_angle: f32,
+    pub max_force: f32,
+    #[serde(default)]
+    pub mixing: ControlMixing,
+    #[serde(default)]
+    pub flap: Option<FlapDefinition>,
+    #[serde(default)]
+    pub leading_edge: Option<LeadingEdgeDefinition>,
+    #[serde(default)]
+    pub damage: Option<ComponentDamageDefinition>, // hits are measured from the pressure center
+}
+
+/// Trailing edge flap: shifts the whole lift curve up (more lift at the same alpha) and adds drag.
+#[derive(Debug, Deserialize, Clone)]
+pub struct FlapDefinition {
+    pub lift_increment: f32, // Cl added fully down
+    #[serde(default)]
+    pub drag_increment: f32, // Cd added fully down
+    #[serde(default = "default_flap_deploy_time")]
+    pub deploy_time: f32,    // s from up to fully down
+}
+
+/// Leading edge slats/flaps: keep the flow attached past the clean lift peak, so the
+/// wing stalls later (and at a higher Cl) instead of lifting more at low alpha.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LeadingEdgeDefinition {
+    pub stall_alpha_increase: f32, // degrees the lift peak moves out fully deployed
+    #[serde(default)]
+    pub drag_increment: f32,       // Cd added fully deployed
+    #[serde(default = "default_leading_edge_deploy_time")]
+    pub deploy_time: f32,
+    #[serde(default)]
+    pub alpha_schedule: Option<(f32, f32)>, // degrees of alpha where they start coming out and are fully out, None for switch only
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpeedbrakeDefinition {
+    pub drag_area: f32,          // m², drag coefficient times area fully open
+    pub position: Vector3<f32>,  // where the drag acts, in the model's local space
+    #[serde(default = "default_speedbrake_deploy_time")]
+    pub deploy_time: f32,
+}
+
+/// How much each pilot channel moves a surface, summed and clamped to [-1, 1].
+/// e.g. a left aileron is `(aileron: -1.0, trim_roll: 1.0)`, a stabilator `(elevator: 1.0, trim_pitch: 1.0)`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ControlMixing {
+    #[serde(default)]
+    pub aileron: f32,
+    #[serde(default)]
+    pub elevator: f32,
+    #[serde(default)]
+    pub rudder: f32,
+    #[serde(default)]
+    pub trim_roll: f32,
+    #[serde(default)]
+    pub trim_pitch: f32,
+    #[serde(default)]
+    pub trim_yaw: f32,
+}
+
+impl ControlMixing {
+    pub fn apply(&self, controls: &PlaneControls) -> f32 {
+        let input = controls.aileron * self.aileron
+            + controls.elevator * self.elevator
+            + controls.rudder * self.rudder
+            + controls.trim_roll * self.trim_roll
+            + controls.trim_pitch * self.trim_pitch
+            + controls.trim_yaw * self.trim_yaw;
+
+        input.clamp(-1.0, 1.0)
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PylonDefinition {
+    pub position: Vector3<f32>, // where the missile hangs in the model's local space, clear of the colliders
+    pub missile: String,        // id of its MissileDefinition, e.g. "aim9"
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GunMountDefinition {
+    pub gun: String,            // id of its GunDefinition, e.g. "m61"
+    pub position: Vector3<f32>, // the muzzle in the model's local space, it fires along the nose
+    pub ammo: u32,              // rounds
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GearDefinition {
+    pub mesh_name: String,
+    pub offset: Vector3<f32>,
+    pub max_suspension_length: f32,
+    pub stiffness: f32,
+    pub damping: f32,
+    #[serde(default)]
+    pub steering: f32, // degrees at full rudder, 0 for wheels that don't steer
+    #[serde(default)]
+    pub max_brake_force: f32, // N at full brake, 0 for wheels without brakes
+    #[serde(default = "default_rolling_resistance")]
+    pub rolling_resistance: f32, // fraction of the load
+    #[serde(default = "default_tyre_friction")]
+    pub friction: f32, // tyre to ground friction coefficient, caps braking and side forces
+    #[serde(default = "default_deploy_time")]
+    pub deploy_time: f32, // s from up and locked to down and locked
+    #[serde(default)]
+    pub drag_area: f32, // m², drag coefficient times frontal area of the leg and doors when down
+    #[serde(default)]
+    pub damage: Option<ComponentDamageDefinition>, // hits are measured from the offset, `mesh_name` is hidden with the rest once it breaks off
+}
+
+impl AircraftDefinition {
+    /// Loads `assets/aircraft/<name>.ron`, `name` being the id used by the plane selection list (e.g. "f16").
+    pub fn load(name: &str) -> Result<AircraftDefinition, String> {
+        let path = format!("assets/aircraft/{}.ron", name);
+
+        match std::fs::read_to_string(&path) {
+            Ok(ron_result_string) => {
+                match ron::from_str::<AircraftDefinition>(&ron_result_string) {
+                    Ok(definition) => Ok(definition),
+                    Err(error) => Err(format!("Something went wrong structuring the aircraft '{}': {}", path, error)),
+                }
+            },
+            Err(err) => Err(format!("Something went wrong reading the aircraft '{}': {}", path, err)),
+        }
+    }
+}
@@ -0,0 +1,194 @@
+use nalgebra::Vector3;
+
+use crate::game::play::plane::aircraft::{AircraftDefinition, ComponentDamageDefinition, ImpactDamageDefinition};
+
+/// Damage dealt to a plane, sent to its entity as an `EntityCommand` by weapons or worked
+/// out by the plane itself from its collisions.
+#[derive(Debug, Clone)]
+pub struct Damage {
+    pub amount: f32,         // health taken off a part right at `point`
+    pub point: Vector3<f32>, // where it hit, in the plane's local space
+    pub radius: f32,         // m the damage reaches around the point, fading out to nothing at the edge. 0 only hits the closest part
+}
+
+/// Which of the aircraft definition's parts a component is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ComponentKind {
+    Surface(usize), // index in `surfaces`
+    Engine,
+    Gear(usize),    // index in `gear`
+}
+
+#[derive(Debug, Clone)]
+pub struct Component {
+    pub label: String,
+    pub kind: ComponentKind,
+    pub position: Vector3<f32>, // in the plane's local space, hits are measured from it
+    pub max_health: f32,
+    pub health: f32,
+    pub meshes: Vec<String>,    // hidden once it's destroyed
+    pub mass: f32,              // kg that fall off with it
+    pub collider: Option<Vector3<f32>>, // a point inside the collider that falls off with it
+}
+
+impl Component {
+    fn new(label: String, kind: ComponentKind, position: Vector3<f32>, definition: &ComponentDamageDefinition, meshes: Vec<String>) -> Self {
+        Self { label, kind, position, max_health: definition.health, health: definition.health, meshes, mass: definition.mass, collider: definition.collider }
+    }
+
+    /// How well the part still works, 1 undamaged down to 0 destroyed.
+    pub fn effectiveness(&self) -> f32 {
+        if self.max_health > 0.0 { (self.health / self.max_health).clamp(0.0, 1.0) } else { 0.0 }
+    }
+
+    pub fn destroyed(&self) -> bool {
+        self.health <= 0.0
+    }
+}
+
+/// # Plane Damage
+///
+/// Health of every part of the aircraft that has a `damage` block in its definition (surfaces,
+/// engine, gear). The physics logic turns each part's `effectiveness` into less lift, thrust
+/// or a missing wheel every tick.
+///
+/// ## Contents:
+/// - **Hits**: a `Damage` takes `amount` off the parts around its point, less the further they
+///   are, or all of it off the closest one for a point hit (radius 0).
+/// - **Impacts**: the airframe hitting something damages the parts near the contact, see
+///   `ImpactDamageDefinition`. The gear's own contacts are rays, so they never count.
+/// - **Destruction**: a part at 0 health is gone for good: its mass and collider go with it
+///   (see `lost_mass`) and its meshes are hidden by the main thread.
+pub struct PlaneDamage {
+    pub components: Vec<Component>,
+    impact: ImpactDamageDefinition,
+}
+
+impl PlaneDamage {
+    pub fn new(definition: &AircraftDefinition) -> Self {
+        let surfaces = definition.surfaces.iter().enumerate().filter_map(|(index, surface)| {
+            let damage = surface.damage.as_ref()?;
+            Some(Component::new(surface.label.clone(), ComponentKind::Surface(index), surface.pressure_center, damage, damage.meshes.clone()))
+        });
+        let engine = definition.engine.damage.as_ref().map(|damage| {
+            Component::new("Engine".to_string(), ComponentKind::Engine, definition.engine.position, damage, damage.meshes.clone())
+        });
+        let gear = definition.gear.iter().enumerate().filter_map(|(index, gear)| {
+            let damage = gear.damage.as_ref()?;
+            let meshes = std::iter::once(gear.mesh_name.clone()).chain(damage.meshes.iter().cloned()).collect();
+            Some(Component::new(gear.mesh_name.clone(), ComponentKind::Gear(index), gear.offset, damage, meshes))
+        });
+
+        Self { components: surfaces.chain(engine).chain(gear).collect(), impact: definition.impact_damage.clone() }
+    }
+
+    /// Takes a hit, returns the labels of the parts it destroyed.
+    pub fn apply(&mut self, damage: &Damage) -> Vec<String> {
+        let intact = self.components.iter().enumerate().filter(|(_, component)| !component.destroyed());
+        let hits: Vec<(usize, f32)> = if damage.radius > 0.0 {
+            intact
+                .map(|(index, component)| (index, damage.amount * (1.0 - (component.position - damage.point).norm() / damage.radius)))
+                .filter(|(_, amount)| *amount > 0.0)
+                .collect()
+        } else {
+            intact
+                .min_by(|(_, a), (_, b)| (a.position - damage.point).norm().total_cmp(&(b.position - damage.point).norm()))
+                .map(|(index, _)| vec![(index, damage.amount)])
+                .unwrap_or_default()
+        };
+
+        let mut destroyed = Vec::new();
+        for (index, amount) in hits {
+            let component = &mut self.components[index];
+            component.health = (component.health - amount).max(0.0);
+            if component.destroyed() {
+                destroyed.push(component.label.clone());
+            }
+        }
+        destroyed
+    }
+
+    /// What the airframe hitting something at `impact_speed` m/s at `point` (local space) does,
+    /// None if it was too slow to hurt.
+    pub fn impact(&self, point: Vector3<f32>, impact_speed: f32) -> Option<Damage> {
+        let excess = impact_speed - self.impact.min_speed;
+        (excess > 0.0).then_some(Damage { amount: excess * self.impact.damage_per_speed, point, radius: self.impact.radius })
+    }
+
+    /// Mass of the parts that fell off and its center, zero when nothing did.
+    pub fn lost_mass(&self) -> (f32, Vector3<f32>) {
+        let lost = self.components.iter().filter(|component| component.destroyed() && component.mass > 0.0);
+        let (mass, moment) = lost.fold((0.0, Vector3::zeros()), |(mass, moment), component| (mass + component.mass, moment + component.position * component.mass));
+        if mass > 0.0 { (mass, moment / mass) } else { (0.0, Vector3::zeros()) }
+    }
+
+    /// Where the colliders of the parts that fell off are, see ComponentDamageDefinition::collider.
+    pub fn lost_colliders(&self) -> impl Iterator<Item = Vector3<f32>> + '_ {
+        self.components.iter().filter(|component| component.destroyed()).filter_map(|component| component.collider)
+    }
+
+    /// Effectiveness of a part, 1 for the ones that can't be damaged.
+    pub fn effectiveness(&self, kind: ComponentKind) -> f32 {
+        self.components.iter().find(|component| component.kind == kind).map_or(1.0, Component::effectiveness)
+    }
+
+    pub fn health(&self) -> Vec<f32> {
+        self.components.iter().map(|component| component.health).collect()
+    }
+
+    /// Puts back what `health` returned, ignored if it's from an aircraft with other parts.
+    pub fn set_health(&mut self, health: &[f32]) {
+        if health.len() == self.components.len() {
+            for (component, health) in self.components.iter_mut().zip(health) {
+                component.health = *health;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damage() -> PlaneDamage {
+        let mut definition = AircraftDefinition::load("f16").unwrap();
+        definition.impact_damage = ImpactDamageDefinition { min_speed: 5.0, damage_per_speed: 10.0, radius: 4.0 };
+        PlaneDamage::new(&definition)
+    }
+
+    fn component<'a>(damage: &'a PlaneDamage, label: &str) -> &'a Component {
+        damage.components.iter().find(|component| component.label == label).unwrap()
+    }
+
+    #[test]
+    fn hits_damage_the_parts_around_them() {
+        let mut damage = damage();
+        let left_wing = component(&damage, "Left wing").clone();
+
+        // A point hit on the left wingtip only hurts the left wing
+        assert!(damage.apply(&Damage { amount: 100.0, point: Vector3::new(8.0, 0.0, 1.0), radius: 0.0 }).is_empty());
+        assert_eq!(component(&damage, "Left wing").health, left_wing.max_health - 100.0);
+        assert_eq!(component(&damage, "Right wing").effectiveness(), 1.0);
+        assert!(damage.effectiveness(ComponentKind::Surface(0)) < 1.0);
+
+        // A blast right on it takes what's left, the right wing is out of its reach
+        let destroyed = damage.apply(&Damage { amount: left_wing.max_health, point: left_wing.position, radius: 3.0 });
+        assert_eq!(destroyed, vec!["Left wing".to_string()]);
+        assert_eq!(damage.effectiveness(ComponentKind::Surface(0)), 0.0);
+        assert_eq!(component(&damage, "Right wing").effectiveness(), 1.0);
+        // Destroyed parts can't be destroyed again
+        assert!(damage.apply(&Damage { amount: 1000.0, point: left_wing.position, radius: 0.0 }).iter().all(|label| label != "Left wing"));
+
+        // It took its mass and collider with it
+        assert_eq!(damage.lost_mass(), (left_wing.mass, left_wing.position));
+        assert_eq!(damage.lost_colliders().collect::<Vec<_>>(), vec![left_wing.collider.unwrap()]);
+    }
+
+    #[test]
+    fn only_hard_impacts_do_damage() {
+        let damage = damage();
+        assert!(damage.impact(Vector3::zeros(), 4.0).is_none());
+        let impact = damage.impact(Vector3::zeros(), 25.0).unwrap();
+        assert_eq!((impact.amount, impact.radius), (200.0, 4.0));
+    }
+}
@@ -0,0 +1,213 @@
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+use rapier3d::prelude::RigidBody;
+
+use crate::engine::physics::atmosphere::AirData;
+use crate::game::play::plane::aircraft::{EngineDefinition, FuelDefinition};
+
+// Core power at idle, as a fraction of military thrust
+const IDLE_POWER: f32 = 0.05;
+// The afterburner only lights once the core is (nearly) at military power
+const AFTERBURNER_LIGHT_POWER: f32 = 0.95;
+// Time constant of the afterburner stages lighting up / dying down
+const AFTERBURNER_TIME_CONSTANT: f32 = 0.3;
+// How much ram air compression adds per Mach, the afterburner gains far more from it than the core
+const DRY_RAM_FACTOR: f32 = 0.25;
+const AFTERBURNER_RAM_FACTOR: f32 = 0.7;
+
+/// What the engine is doing right now, sent to the main thread every tick so the HUD
+/// (and the afterburner flame) show the same thing the physics is flying with.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EngineState {
+    pub rpm: f32,         // core speed as a gauge would show it, 0 (stopped) to 1 (100%)
+    pub afterburner: f32, // 0 (off) to 1 (full afterburner)
+    pub thrust: f32,      // N
+    pub fuel_mass: f32,   // kg left, infinite if the aircraft has no fuel system
+    pub fuel_flow: f32,   // kg/s
+    pub flamed_out: bool,
+}
+
+/// # Jet Engine
+///
+/// Turns the throttle into thrust the way a turbofan does instead of instantly:
+///
+/// ## Contents:
+/// - **Spool**: the core power chases the throttle with a lag (`spool_time` from idle to military).
+/// - **Detent**: throttle above `afterburner_detent` lights the afterburner stages, once the core is at military power.
+/// - **Lapse**: thrust drops with air density and gains some back with Mach (ram compression).
+/// - **Fuel**: burns `tsfc` kg per N of thrust per second, the plane physics updates the rigidbody mass from `fuel_mass`.
+/// - **Flameout**: with no fuel left the core spools down to nothing and stays there.
+/// - **Damage**: a damaged engine burns the same fuel for `effectiveness` of the thrust.
+pub struct JetEngine {
+    pub definition: EngineDefinition,
+    pub power: f32, // core power, IDLE_POWER to 1 (military) - 0 once flamed out
+    pub state: EngineState,
+    pub effectiveness: f32, // 1 undamaged down to 0 destroyed, see PlaneDamage
+    fuel_capacity: Option<f32>,
+}
+
+impl JetEngine {
+    pub fn new(definition: &EngineDefinition, fuel: Option<&FuelDefinition>) -> Self {
+        let fuel_mass = match fuel {
+            Some(fuel) => fuel.initial.unwrap_or(fuel.capacity).min(fuel.capacity),
+            None => f32::INFINITY,
+        };
+
+        Self {
+            definition: definition.clone(),
+            power: IDLE_POWER,
+            state: EngineState { fuel_mass, ..Default::default() },
+            effectiveness: 1.0,
+            fuel_capacity: fuel.map(|fuel| fuel.capacity),
+        }
+    }
+
+    pub fn has_fuel_system(&self) -> bool {
+        self.fuel_capacity.is_some()
+    }
+
+    /// Splits the throttle lever at the detent: (core power command, afterburner command).
+    fn commands(&self, throttle: f32) -> (f32, f32) {
+        let throttle = throttle.clamp(0.0, 1.0);
+
+        match self.definition.afterburner_thrust {
+            Some(_) if self.definition.afterburner_detent < 1.0 => {
+                let detent = self.definition.afterburner_detent;
+                if throttle <= detent {
+                    (IDLE_POWER + (1.0 - IDLE_POWER) * throttle / detent, 0.0)
+                } else {
+                    (1.0, (throttle - detent) / (1.0 - detent))
+                }
+            },
+            _ => (IDLE_POWER + (1.0 - IDLE_POWER) * throttle, 0.0),
+        }
+    }
+
+    /// Advances spool, afterburner and fuel by `delta_time` and returns the thrust (N) along the nose.
+    pub fn update(&mut self, throttle: f32, air: &AirData, mach: f32, delta_time: f32) -> f32 {
+        if self.state.fuel_mass <= 0.0 {
+            self.state.fuel_mass = 0.0;
+            self.state.flamed_out = true;
+        }
+
+        let (power_command, afterburner_command) = if self.state.flamed_out {
+            (0.0, 0.0)
+        } else {
+            self.commands(throttle)
+        };
+
+        // First order lag, ~95% of the way there after spool_time
+        let spool_time_constant = (self.definition.spool_time / 3.0).max(f32::EPSILON);
+        self.power += (power_command - self.power) * (delta_time / spool_time_constant).min(1.0);
+
+        let afterburner_target = if self.power >= AFTERBURNER_LIGHT_POWER { afterburner_command } else { 0.0 };
+        self.state.afterburner += (afterburner_target - self.state.afterburner) * (delta_time / AFTERBURNER_TIME_CONSTANT).min(1.0);
+
+        // Without combustion the spool only winds down, it doesn't push anymore
+        let density_lapse = if self.state.flamed_out { 0.0 } else { air.density_ratio().powf(0.7) };
+        let dry_thrust = self.definition.max_thrust * self.power * density_lapse * (1.0 + DRY_RAM_FACTOR * mach);
+        let afterburner_thrust = match self.definition.afterburner_thrust {
+            Some(max_afterburner_thrust) => (max_afterburner_thrust - self.definition.max_thrust).max(0.0) * self.state.afterburner * density_lapse * (1.0 + AFTERBURNER_RAM_FACTOR * mach),
+            None => 0.0,
+        };
+
+        self.state.fuel_flow = dry_thrust * self.definition.tsfc + afterburner_thrust * self.definition.afterburner_tsfc;
+
+        if self.has_fuel_system() {
+            self.state.fuel_mass = (self.state.fuel_mass - self.state.fuel_flow * delta_time).max(0.0);
+        }
+
+        // Gauges show ~60% rpm at idle and 100% at military, windmilling down to 0 after a flameout
+        self.state.rpm = if self.power >= IDLE_POWER {
+            0.6 + 0.4 * (self.power - IDLE_POWER) / (1.0 - IDLE_POWER)
+        } else {
+            0.6 * self.power / IDLE_POWER
+        };
+        self.state.thrust = (dry_thrust + afterburner_thrust) * self.effectiveness;
+        self.state.thrust
+    }
+
+    pub fn apply_thrust(&self, rigidbody: &mut RigidBody) {
+        let thrust_world = rigidbody.rotation() * Vector3::new(0.0, 0.0, self.state.thrust);
+        rigidbody.add_force(thrust_world, true);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::physics::atmosphere::Atmosphere;
+
+    fn definition() -> EngineDefinition {
+        EngineDefinition {
+            max_thrust: 76_000.0,
+            afterburner_thrust: Some(129_000.0),
+            afterburner_detent: 0.8,
+            spool_time: 4.0,
+            tsfc: 2.2e-5,
+            afterburner_tsfc: 5.7e-5,
+            position: Vector3::zeros(),
+            damage: None,
+        }
+    }
+
+    fn run(engine: &mut JetEngine, throttle: f32, altitude: f32, mach: f32, seconds: f32) -> f32 {
+        let air = Atmosphere::default().sample(altitude);
+        let mut thrust = 0.0;
+        for _ in 0..(seconds * 120.0) as usize {
+            thrust = engine.update(throttle, &air, mach, 1.0 / 120.0);
+        }
+        thrust
+    }
+
+    #[test]
+    fn spools_up_with_lag() {
+        let mut engine = JetEngine::new(&definition(), None);
+
+        let after_half_second = run(&mut engine, 0.8, 0.0, 0.0, 0.5);
+        assert!(after_half_second < 0.5 * 76_000.0, "thrust jumped to {}N", after_half_second);
+
+        let spooled = run(&mut engine, 0.8, 0.0, 0.0, 5.0);
+        assert!((spooled - 76_000.0).abs() < 0.05 * 76_000.0, "military thrust was {}N", spooled);
+        assert_eq!(engine.state.afterburner, 0.0);
+    }
+
+    #[test]
+    fn afterburner_past_the_detent() {
+        let mut engine = JetEngine::new(&definition(), None);
+
+        let full = run(&mut engine, 1.0, 0.0, 0.0, 8.0);
+        assert!((full - 129_000.0).abs() < 0.05 * 129_000.0, "full afterburner was {}N", full);
+        assert!(engine.state.afterburner > 0.95);
+    }
+
+    #[test]
+    fn thrust_lapses_with_altitude_and_gains_with_mach() {
+        let mut sea_level = JetEngine::new(&definition(), None);
+        let mut high = JetEngine::new(&definition(), None);
+        let mut high_fast = JetEngine::new(&definition(), None);
+
+        let sea_level_thrust = run(&mut sea_level, 0.8, 0.0, 0.0, 6.0);
+        let high_thrust = run(&mut high, 0.8, 10_000.0, 0.0, 6.0);
+        let high_fast_thrust = run(&mut high_fast, 0.8, 10_000.0, 0.9, 6.0);
+
+        assert!(high_thrust < sea_level_thrust * 0.6);
+        assert!(high_fast_thrust > high_thrust);
+    }
+
+    #[test]
+    fn burns_fuel_until_flameout() {
+        let fuel = FuelDefinition { capacity: 10.0, initial: None, position: Vector3::zeros() };
+        let mut engine = JetEngine::new(&definition(), Some(&fuel));
+
+        run(&mut engine, 1.0, 0.0, 0.0, 1.0);
+        assert!(engine.state.fuel_mass < 10.0);
+        assert!(!engine.state.flamed_out);
+
+        let thrust = run(&mut engine, 1.0, 0.0, 0.0, 10.0);
+        assert!(engine.state.flamed_out);
+        assert_eq!(engine.state.fuel_mass, 0.0);
+        assert_eq!(thrust, 0.0);
+        assert!(engine.state.rpm < 0.6);
+    }
+}
@@ -1,6 +1,4 @@
-use nalgebra::{Vector3, clamp};
-use rapier3d::prelude::RigidBody;
-use crate::game::play::plane::utils;
+use nalgebra::Vector3;
 
 pub struct AoA {
     pub aoa_pitch: f32,
@@ -17,6 +15,12 @@ pub struct FlightSystem {
     pub input: Vector3<f32>, // x = roll, y = pitch, z = yaw
 }
 
+impl Default for FlightSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl FlightSystem {
     pub fn new() -> Self {
         Self {
@@ -32,10 +36,4 @@ impl FlightSystem {
             input: Vector3::new(0.0, 0.0, 0.0),
         }
     }
-
-    pub fn update_thrust(&mut self, rigidbody: &mut RigidBody, deltaTime: f32, throttle: f32) {
-        let thrust_local = nalgebra::Vector3::new(0.0, 0.0, 129000.0) * throttle;
-        let thrust_world = rigidbody.rotation() * thrust_local;
-        rigidbody.add_force(thrust_world, true);
-    }
 }
\ No newline at end of file
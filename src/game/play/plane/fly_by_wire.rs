@@ -0,0 +1,178 @@
+use rapier3d::prelude::RigidBody;
+use nalgebra::Vector3;
+
+use crate::game::play::plane::aircraft::{FlyByWireDefinition, PidGains};
+use crate::game::play::plane::plane::PlaneControls;
+
+const GRAVITY: f32 = 9.81;
+// Pitch rate (rad/s) commanded per radian of alpha left before the AoA limit
+const AOA_LIMITER_GAIN: f32 = 5.0;
+// Pitch rate (rad/s) added per G the plane is short of (or over) the command
+const LOAD_FACTOR_GAIN: f32 = 0.05;
+// Time constant (s) of the filter on the measured load factor
+const LOAD_FACTOR_FILTER: f32 = 0.05;
+
+/// Textbook PID, the integral is clamped so it can never ask for more than full deflection
+/// on its own (anti windup) and the output is a surface command from -1 to 1.
+#[derive(Debug, Clone)]
+pub struct Pid {
+    gains: PidGains,
+    integral: f32,
+    previous_error: Option<f32>,
+}
+
+impl Pid {
+    pub fn new(gains: PidGains) -> Self {
+        Self { gains, integral: 0.0, previous_error: None }
+    }
+
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+        self.previous_error = None;
+    }
+
+    pub fn update(&mut self, error: f32, delta_time: f32) -> f32 {
+        let derivative = match self.previous_error {
+            Some(previous_error) if delta_time > 0.0 => (error - previous_error) / delta_time,
+            _ => 0.0,
+        };
+        self.previous_error = Some(error);
+
+        let integral_limit = if self.gains.i > 0.0 { 1.0 / self.gains.i } else { 0.0 };
+        self.integral = (self.integral + error * delta_time).clamp(-integral_limit, integral_limit);
+
+        (self.gains.p * error + self.gains.i * self.integral + self.gains.d * derivative).clamp(-1.0, 1.0)
+    }
+}
+
+/// What the flight computer is doing, for the HUD (sent as the `plane/fly_by_wire` metadata).
+#[derive(Debug, Clone, Default)]
+pub struct FlyByWireState {
+    pub engaged: bool,            // false below the engage speed, the stick moves the surfaces directly
+    pub limiters_overridden: bool,
+    pub limiting: bool,           // the G or AoA limiter is cutting the pilot's command
+    pub commanded_g: f32,
+    pub load_factor: f32,          // G measured along the lift axis
+    pub commanded_pitch_rate: f32, // rad/s, nose up positive
+    pub commanded_roll_rate: f32,  // rad/s, same sign as the aileron input
+}
+
+/// # Fly By Wire
+///
+/// Control laws between the pilot's `PlaneControls` and the wings: the stick asks for a
+/// motion and the computer moves the surfaces to get it, instead of the stick being the surfaces.
+///
+/// ## Contents:
+/// - **Pitch**: the stick commands G (neutral stick holds 1G), turned into the pitch rate that
+///   pulls that G at the current speed, corrected by how far the measured G is from the command,
+///   and tracked by a PID. Trim still reaches the surfaces directly, as the feed forward the PID works around.
+/// - **Roll**: the stick commands a roll rate, tracked by a PID.
+/// - **Limiters**: the G command stops at `g_limit`/`negative_g_limit` and the pitch rate is cut back
+///   as alpha nears `aoa_limit`. Holding the override (`limiter_override`) drops both and turns
+///   pitch into a plain rate command up to `max_pitch_rate`.
+/// - **Engage speed**: below it (taxiing, take off roll) there's no airflow to control with,
+///   the stick is passed through and the PIDs are kept reset so they don't wind up.
+///
+/// Rudder and throttle are always passed through.
+pub struct FlyByWire {
+    pub definition: FlyByWireDefinition,
+    pub state: FlyByWireState,
+    pitch: Pid,
+    roll: Pid,
+    previous_velocity: Option<Vector3<f32>>,
+}
+
+impl FlyByWire {
+    pub fn new(definition: &FlyByWireDefinition) -> Self {
+        Self {
+            definition: definition.clone(),
+            state: FlyByWireState::default(),
+            pitch: Pid::new(definition.pitch),
+            roll: Pid::new(definition.roll),
+            previous_velocity: None,
+        }
+    }
+
+    /// Turns the pilot's controls into the ones the wings fly with.
+    pub fn update(&mut self, controls: &PlaneControls, rigidbody: &RigidBody, delta_time: f32) -> PlaneControls {
+        let rotation = rigidbody.rotation();
+        let local_velocity = rotation.inverse() * rigidbody.linvel();
+        let local_angular_velocity = rotation.inverse() * rigidbody.angvel();
+        let speed = local_velocity.magnitude();
+        let lift_axis = rotation * Vector3::y();
+
+        // What the pilot feels: the acceleration over the last step minus gravity, along the lift axis
+        let measured_load_factor = match self.previous_velocity {
+            Some(previous_velocity) if delta_time > 0.0 => {
+                let acceleration = (rigidbody.linvel() - previous_velocity) / delta_time;
+                (acceleration + Vector3::new(0.0, GRAVITY, 0.0)).dot(&lift_axis) / GRAVITY
+            },
+            _ => lift_axis.y,
+        };
+        self.previous_velocity = Some(*rigidbody.linvel());
+        self.state.load_factor += (measured_load_factor - self.state.load_factor) * (delta_time / LOAD_FACTOR_FILTER).min(1.0);
+        self.state.limiters_overridden = controls.limiter_override;
+
+        if speed < self.definition.engage_speed {
+            self.pitch.reset();
+            self.roll.reset();
+            self.state = FlyByWireState { limiters_overridden: controls.limiter_override, load_factor: self.state.load_factor, ..Default::default() };
+            return controls.clone();
+        }
+
+        // Body axes: x = left, y = up, z = forward, a nose up pitch is a negative rotation about x
+        let pitch_rate = -local_angular_velocity.x;
+        let roll_rate = local_angular_velocity.z;
+        let alpha = (-local_velocity.y).atan2(local_velocity.z);
+        let max_pitch_rate = self.definition.max_pitch_rate;
+
+        let commanded_pitch_rate = if controls.limiter_override {
+            self.state.commanded_g = 0.0;
+            self.state.limiting = false;
+            controls.elevator * max_pitch_rate
+        } else {
+            let commanded_g = if controls.elevator >= 0.0 {
+                1.0 + controls.elevator * (self.definition.g_limit - 1.0)
+            } else {
+                1.0 - controls.elevator * (self.definition.negative_g_limit - 1.0)
+            };
+
+            // n = V·q/g + the part of gravity the wings already carry (cos pitch · cos bank)
+            let load_factor_error = commanded_g - self.state.load_factor;
+            let g_pitch_rate = ((commanded_g - lift_axis.y) * GRAVITY / speed + LOAD_FACTOR_GAIN * load_factor_error).clamp(-max_pitch_rate, max_pitch_rate);
+            let aoa_pitch_rate = AOA_LIMITER_GAIN * (self.definition.aoa_limit.to_radians() - alpha);
+
+            self.state.commanded_g = commanded_g;
+            self.state.limiting = aoa_pitch_rate < g_pitch_rate;
+            g_pitch_rate.min(aoa_pitch_rate)
+        };
+        let commanded_roll_rate = controls.aileron * self.definition.max_roll_rate;
+
+        self.state.engaged = true;
+        self.state.commanded_pitch_rate = commanded_pitch_rate;
+        self.state.commanded_roll_rate = commanded_roll_rate;
+
+        PlaneControls {
+            elevator: self.pitch.update(commanded_pitch_rate - pitch_rate, delta_time),
+            aileron: self.roll.update(commanded_roll_rate - roll_rate, delta_time),
+            ..controls.clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pid_integral_does_not_wind_up() {
+        let mut pid = Pid::new(PidGains { p: 0.0, i: 2.0, d: 0.0 });
+
+        for _ in 0..1000 {
+            assert!(pid.update(10.0, 0.1) <= 1.0);
+        }
+
+        // Fully wound up it comes straight off the stop once the error flips
+        assert!(pid.update(-10.0, 0.1) < 0.0);
+    }
+}
@@ -0,0 +1,45 @@
+use nalgebra::Vector3;
+
+// What PlanePhysicsLogic reports about the plane every tick, under the "plane" namespace of
+// its metadata (see Metadata). The debug shapes are only sent while debug rendering is on.
+
+#[derive(Debug, Clone)]
+pub struct ColliderDebugData {
+    pub half_extents: Vector3<f32>,
+    pub local_offset: Vector3<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WingDebugData {
+    pub pressure_center: Vector3<f32>,
+    pub last_lift_force: Vector3<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct WingStallData {
+    pub label: String,
+    pub alpha: f32, // degrees
+    pub stalled: bool,
+}
+
+/// How far out the high lift devices and the speedbrake are, 0 retracted to 1 fully deployed.
+#[derive(Debug, Clone, Default)]
+pub struct DeviceData {
+    pub flaps: f32,
+    pub leading_edge: f32,
+    pub speedbrake: f32,
+}
+
+/// Health left in a part that can be damaged, 0 destroyed to 1 undamaged.
+#[derive(Debug, Clone)]
+pub struct ComponentDamageData {
+    pub label: String,
+    pub health: f32,
+    pub meshes: Vec<String>, // hidden once it's destroyed
+}
+
+#[derive(Debug, Clone)]
+pub struct SuspensionDebugData {
+    pub local_origin: Vector3<f32>,
+    pub local_wheel: Vector3<f32>,
+}
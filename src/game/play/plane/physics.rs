@@ -1,2 +1,3 @@
 pub mod wheels;
 pub mod wings;
+pub mod speedbrake;
@@ -0,0 +1,36 @@
+use nalgebra::Vector3;
+use rapier3d::prelude::RigidBody;
+
+use crate::engine::physics::atmosphere::AirData;
+use crate::game::play::plane::aircraft::SpeedbrakeDefinition;
+use crate::game::play::plane::utils::move_towards;
+
+/// # Speedbrake
+///
+/// Panels that open into the airflow with the speedbrake switch. They only add drag, any
+/// pitching moment comes from where `position` puts it relative to the center of mass.
+pub struct Speedbrake {
+    pub definition: SpeedbrakeDefinition,
+    pub position: f32, // 0 closed, 1 fully open
+}
+
+impl Speedbrake {
+    pub fn new(definition: &SpeedbrakeDefinition) -> Self {
+        Self { definition: definition.clone(), position: 0.0 }
+    }
+
+    pub fn update(&mut self, open: bool, rigidbody: &mut RigidBody, air: &AirData, wind: &Vector3<f32>, delta_time: f32) {
+        let target = if open { 1.0 } else { 0.0 };
+        self.position = move_towards(self.position, target, self.definition.deploy_time, delta_time);
+
+        if self.position <= 0.0 {
+            return;
+        }
+
+        let world_position = rigidbody.translation() + rigidbody.rotation() * self.definition.position;
+        let velocity = rigidbody.velocity_at_point(&world_position.into()) - wind;
+        let drag = -velocity * velocity.magnitude() * 0.5 * air.density * self.definition.drag_area * self.position;
+
+        rigidbody.add_force_at_point(drag, world_position.into(), true);
+    }
+}
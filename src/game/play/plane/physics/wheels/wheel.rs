@@ -1,11 +1,28 @@
-use nalgebra::Vector3;
+use nalgebra::{UnitQuaternion, Vector3};
 use rapier3d::prelude::{ColliderSet, QueryFilter, QueryPipeline, Ray, RigidBodySet};
 
 use crate::engine::physics::physics_handler::PhysicsData;
+use crate::game::play::plane::aircraft::GearDefinition;
+use crate::game::play::plane::utils::move_towards;
+
+// Speed (m/s) at which a tyre gives its full rolling/braking force, below it the force fades
+// out linearly so a stopped plane doesn't jitter back and forth
+const TYRE_SLIP_SPEED: f32 = 1.0;
+// Slip angle (between where the tyre points and where it goes) giving the full side force
+const TYRE_PEAK_SLIP_ANGLE: f32 = 10.0 * std::f32::consts::PI / 180.0;
 
 #[derive(Debug, Clone)]
 pub struct WheelData {
     pub local_position: Vector3<f32>,
+    pub deployed: f32, // 0 up and locked, 1 down and locked
+}
+
+/// Forces from one wheel touching the ground.
+pub struct WheelForces {
+    pub suspension_force: Vector3<f32>,
+    pub suspension_origin: Vector3<f32>,
+    pub friction_force: Vector3<f32>, // rolling resistance, brakes and side grip, applied at the contact point
+    pub wheel_position: Vector3<f32>,
 }
 
 pub struct Wheel {
@@ -14,31 +31,94 @@ pub struct Wheel {
     max_suspension_length: f32,
     pub stiffness: f32,
     pub damping: f32,
+    pub steering: f32,
+    pub max_brake_force: f32,
+    pub rolling_resistance: f32,
+    pub friction: f32,
+    pub deploy_time: f32,
+    pub drag_area: f32,
+    pub deployed: f32,       // 0 up and locked, 1 down and locked
+    pub steering_angle: f32, // degrees, set every tick from the controls
+    pub brake: f32,          // 0 to 1, set every tick from the controls
+    pub load: f32,           // N the ground pushed back with last tick, 0 in the air
+    pub destroyed: bool,     // broken off, it never touches the ground again (see PlaneDamage)
 }
 
 impl Wheel {
     pub fn new(mesh_name: String, offset: Vector3<f32>, max_suspension_length: f32, stiffness: f32, damping: f32) -> Self {
-        Self { mesh_name, offset, max_suspension_length, stiffness, damping }
+        Self {
+            mesh_name,
+            offset,
+            max_suspension_length,
+            stiffness,
+            damping,
+            steering: 0.0,
+            max_brake_force: 0.0,
+            rolling_resistance: 0.0,
+            friction: 0.0,
+            deploy_time: 0.0,
+            drag_area: 0.0,
+            deployed: 1.0,
+            steering_angle: 0.0,
+            brake: 0.0,
+            load: 0.0,
+            destroyed: false,
+        }
     }
 
-    pub fn update_wheel(&mut self, physics_data: &PhysicsData, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline) -> Option<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
+    pub fn from_definition(definition: &GearDefinition) -> Self {
+        let mut wheel = Wheel::new(definition.mesh_name.clone(), definition.offset, definition.max_suspension_length, definition.stiffness, definition.damping);
+        wheel.steering = definition.steering;
+        wheel.max_brake_force = definition.max_brake_force;
+        wheel.rolling_resistance = definition.rolling_resistance;
+        wheel.friction = definition.friction;
+        wheel.deploy_time = definition.deploy_time;
+        wheel.drag_area = definition.drag_area;
+        wheel
+    }
+
+    /// Moves the leg towards up (`down == false`) or down, over `deploy_time`.
+    pub fn move_gear(&mut self, down: bool, delta_time: f32) {
+        let target = if down { 1.0 } else { 0.0 };
+        self.deployed = move_towards(self.deployed, target, self.deploy_time, delta_time);
+    }
+
+    /// Wheel position on the strut when nothing is under it, the leg swings in with the gear.
+    pub fn free_position(&self, suspension_origin: Vector3<f32>, ray_direction: Vector3<f32>) -> Vector3<f32> {
+        suspension_origin + ray_direction * self.max_suspension_length * self.deployed
+    }
+
+    pub fn update_wheel(&mut self, physics_data: &PhysicsData, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline) -> Option<WheelForces> {
+        self.load = 0.0;
+
         if let Some(rigidbody) = rigidbody_set.get(physics_data.rigidbody_handle) {
             // Origin of the raycast
             let rotation = rigidbody.rotation();
             let suspension_origin = rigidbody.translation() + (rotation * self.offset);
-    
+
             // Direction of the ray (downward in local space)
             let local_ray_direction = -Vector3::y_axis();
-            
+
             // Transforming local ray direction to world space
             let ray_direction = rotation * local_ray_direction;
-            
-            let max_wheel_position = suspension_origin + (ray_direction.into_inner() * self.max_suspension_length);
-            
+
+            let free_wheel = WheelForces {
+                suspension_force: Vector3::zeros(),
+                suspension_origin,
+                friction_force: Vector3::zeros(),
+                wheel_position: self.free_position(suspension_origin, ray_direction.into_inner()),
+            };
+
+            // Only a leg that is down and locked carries any load
+            if self.deployed < 1.0 {
+                return Some(free_wheel);
+            }
+
             // Raycast from the wheel downward to detect the ground
             let ray = Ray::new(suspension_origin.into(), ray_direction.into_inner());
-            
-            let mut filter = QueryFilter::default();
+
+            // Trigger volumes aren't something to roll on
+            let mut filter = QueryFilter::default().exclude_sensors();
             // Exclude all colliders belonging to this physics object
             if let Some(first_collider) = physics_data.collider_handles.first() {
                 filter.exclude_collider = Some(*first_collider);
@@ -55,23 +135,51 @@ impl Wheel {
             ) {
                 // Calculate compression based on hit distance
                 let compression = 1.0 - (time_of_impact / self.max_suspension_length);
-    
+
                 // Calculate spring force (Hooke's law) and damping force
                 let spring_force = compression * self.stiffness;
                 let damping_force = rigidbody.linvel().y * self.damping;
-    
+
                 // Apply total force in the upward direction at the wheel position
                 let suspension_force = Vector3::new(0.0, spring_force - damping_force, 0.0);
                 let wheel_position = ray.point_at(time_of_impact);
 
+                self.load = suspension_force.y.max(0.0);
+
+                // Tyre forces work in the ground plane, along and across where the wheel points
+                let up = Vector3::y();
+                let heading = rotation * UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.steering_angle.to_radians()) * Vector3::z();
+                let forward = heading - up * heading.dot(&up);
+                let friction_force = match forward.try_normalize(1.0e-6) {
+                    Some(forward) => {
+                        let side = up.cross(&forward);
+                        let contact_velocity = rigidbody.velocity_at_point(&wheel_position);
+                        let rolling_speed = contact_velocity.dot(&forward);
+                        let sliding_speed = contact_velocity.dot(&side);
+
+                        let grip = self.friction * self.load;
+                        let rolling_direction = (rolling_speed / TYRE_SLIP_SPEED).clamp(-1.0, 1.0);
+                        let longitudinal = -rolling_direction * (self.rolling_resistance * self.load + (self.brake * self.max_brake_force).min(grip));
+                        // Crawling, the slip angle is measured against TYRE_SLIP_SPEED instead of the (near zero) rolling speed
+                        let slip_angle = sliding_speed.atan2(rolling_speed.abs().max(TYRE_SLIP_SPEED));
+                        let lateral = -(slip_angle / TYRE_PEAK_SLIP_ANGLE).clamp(-1.0, 1.0) * grip;
+
+                        // Braking and cornering share the same grip (friction circle)
+                        let force = forward * longitudinal + side * lateral;
+                        let magnitude = force.magnitude();
+                        if magnitude > grip && magnitude > 0.0 { force * (grip / magnitude) } else { force }
+                    },
+                    None => Vector3::zeros(), // wheel pointing straight down, nothing to roll on
+                };
+
                 // render_basic_line(renderizable_lines, suspension_origin, [0.5, 1.0, 0.5], wheel_position.coords, [0.5, 1.0, 0.5]);
-                return Some((suspension_force, suspension_origin, wheel_position.coords));
+                return Some(WheelForces { suspension_force, suspension_origin, friction_force, wheel_position: wheel_position.coords });
             } else {
                 // render_basic_line(renderizable_lines, suspension_origin, [0.5, 1.0, 0.5], max_wheel_position, [0.5, 1.0, 0.5]);
-                return Some((Vector3::zeros(), suspension_origin, max_wheel_position));
+                return Some(free_wheel);
             };
         }
 
         None
     }
-}
\ No newline at end of file
+}
@@ -1,46 +1,82 @@
 use std::collections::HashMap;
 
-use nalgebra::vector;
 use rapier3d::{dynamics::{RigidBody, RigidBodySet}, geometry::ColliderSet, pipeline::QueryPipeline};
 
-use crate::{engine::physics::physics_handler::{PhysicsData, SuspensionDebugData}, game::play::plane::physics::wheels::wheel::WheelData};
+use crate::{engine::physics::{atmosphere::AirData, physics_handler::PhysicsData}, game::play::plane::{aircraft::GearDefinition, metadata::SuspensionDebugData, physics::wheels::wheel::WheelData, plane::PlaneControls}};
 
 use super::wheel::Wheel;
 
+// Seconds every wheel has to be unloaded before the gear may come up, so a bounce on the runway doesn't retract it
+const WEIGHT_OFF_WHEELS_DELAY: f32 = 1.0;
+
+/// # Wheel Manager
+///
+/// The landing gear as a whole:
+///
+/// ## Contents:
+/// - **Retraction**: every leg follows `PlaneControls::gear_down` over its own `deploy_time`, the gear
+///   won't come up while any wheel still carries weight. Deployed legs add their `drag_area` drag.
+/// - **Steering**: wheels with a `steering` angle turn with the rudder.
+/// - **Brakes**: wheels on the left (+x) side use the left brake, the ones on the right side the right
+///   brake and centerline wheels both, so the plane can be turned with differential braking.
+/// - **Damage**: a destroyed wheel is gone, the plane settles on whatever legs are left.
 pub struct WheelManager {
     pub wheels: Vec<Wheel>,
     pub renderizable_wheels: HashMap<String, WheelData>,
+    pub weight_off_wheels_time: f32, // s since the wheels last carried any load
 }
 
 impl WheelManager {
-    pub fn new() -> Self {
-      let wheels = vec![
-        Wheel::new("wheel-f".to_string(), vector![0.0, 0.0, 9.8], 4.2, 100000.0, 50000.0),
-        Wheel::new("wheel-lb".to_string(), vector![-1.4, 0.0, 0.0], 4.2, 500000.0, 50000.0),
-        Wheel::new("wheel-rb".to_string(), vector![1.4, 0.0, 0.0], 4.2, 500000.0, 50000.0)
-      ];
+    pub fn new(gear: &[GearDefinition]) -> Self {
+      let wheels = gear.iter()
+        .map(Wheel::from_definition)
+        .collect();
 
       Self {
         wheels,
         renderizable_wheels: HashMap::new(),
+        weight_off_wheels_time: 0.0,
       }
     }
 
-    pub fn update(&mut self, physics_data: &mut PhysicsData, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline) -> Vec<SuspensionDebugData> {
+    /// Any wheel pressing on the ground (weight on wheels).
+    pub fn on_ground(&self) -> bool {
+      self.wheels.iter().any(|wheel| wheel.load > 0.0)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(&mut self, plane_controls: &PlaneControls, physics_data: &mut PhysicsData, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, air: &AirData, wind: &nalgebra::Vector3<f32>, delta_time: f32) -> Vec<SuspensionDebugData> {
       self.renderizable_wheels.clear();
       let mut suspension_debug_data: Vec<SuspensionDebugData> = Vec::new();
-      
-      for (index, wheel) in self.wheels.iter_mut().enumerate() {
-        if let Some((suspension_force, suspension_origin, wheel_position)) = wheel.update_wheel(&physics_data, &collider_set, rigidbody_set, &query_pipeline) {
+
+      // Weight on wheels interlock, the handle can go up but the gear stays down on the ground
+      self.weight_off_wheels_time = if self.on_ground() { 0.0 } else { self.weight_off_wheels_time + delta_time };
+      let gear_down = plane_controls.gear_down || self.weight_off_wheels_time < WEIGHT_OFF_WHEELS_DELAY;
+
+      for wheel in self.wheels.iter_mut().filter(|wheel| !wheel.destroyed) {
+        wheel.move_gear(gear_down, delta_time);
+        // Positive rudder yaws the nose right, towards -x
+        wheel.steering_angle = -plane_controls.rudder * wheel.steering;
+        wheel.brake = if wheel.offset.x > 0.0 {
+          plane_controls.brake_left
+        } else if wheel.offset.x < 0.0 {
+          plane_controls.brake_right
+        } else {
+          plane_controls.brake_left.max(plane_controls.brake_right)
+        };
+
+        if let Some(forces) = wheel.update_wheel(physics_data, collider_set, rigidbody_set, query_pipeline) {
             if let Some(rigidbody) = rigidbody_set.get_mut(physics_data.rigidbody_handle) {
-                rigidbody.add_force_at_point(suspension_force, suspension_origin.into(), true);
+                rigidbody.add_force_at_point(forces.suspension_force, forces.suspension_origin.into(), true);
+                rigidbody.add_force_at_point(forces.friction_force, forces.wheel_position.into(), true);
+                Self::apply_gear_drag(wheel, rigidbody, forces.suspension_origin, air, wind);
             }
             if let Some(rigidbody) = rigidbody_set.get(physics_data.rigidbody_handle) {
                 let rb_pos = rigidbody.translation();
                 let rb_rot = rigidbody.rotation();
-                let local_position = rb_rot.inverse() * (wheel_position - rb_pos);
-                let local_origin = rb_rot.inverse() * (suspension_origin - rb_pos);
-                self.renderizable_wheels.insert(wheel.mesh_name.clone(), WheelData { local_position });
+                let local_position = rb_rot.inverse() * (forces.wheel_position - rb_pos);
+                let local_origin = rb_rot.inverse() * (forces.suspension_origin - rb_pos);
+                self.renderizable_wheels.insert(wheel.mesh_name.clone(), WheelData { local_position, deployed: wheel.deployed });
                 suspension_debug_data.push(SuspensionDebugData {
                     local_origin,
                     local_wheel: local_position,
@@ -49,6 +85,14 @@ impl WheelManager {
         }
       }
 
-      return suspension_debug_data;
+      suspension_debug_data
     }
-}
\ No newline at end of file
+
+    // Legs and doors hanging in the airflow, growing as they come down
+    fn apply_gear_drag(wheel: &Wheel, rigidbody: &mut RigidBody, suspension_origin: nalgebra::Vector3<f32>, air: &AirData, wind: &nalgebra::Vector3<f32>) {
+      let velocity = rigidbody.linvel() - wind;
+      let drag = -velocity * velocity.magnitude() * 0.5 * air.density * wheel.drag_area * wheel.deployed;
+
+      rigidbody.add_force_at_point(drag, suspension_origin.into(), true);
+    }
+}
@@ -1,84 +1,302 @@
 use nalgebra::Vector3;
 use ron::from_str;
+use serde::Deserialize;
+
+// Normal force coefficient of a (two dimensional) flat plate square to the flow
+const FLAT_PLATE_CD: f32 = 1.98;
+const FLAT_PLATE_CD0: f32 = 0.02;
+// Degrees past the end of a table over which its values fade into the flat plate ones
+const POST_STALL_BLEND: f32 = 10.0;
 
 /// # Air Foil
 ///
 /// This structure is dedicated to the generation of "lift coefficient" and "drag coefficient" based on data given from http://airfoiltools.com/search/index
-/// 
+///
 /// **Meaning:** Described as the lateral structure of a wing designed to get the most favourable ratio of lift to drag in flight.
-/// 
+///
 /// ## Contents:
-/// - **data**: A vector 3 containing the next values [alpha (angle of attack), lift coefficient, drag coefficient]
-/// - **min_alpha**: minimum angle of attack in the data given
-/// - **max_alpha**: maximum angle of attack in the data given
-/// 
-/// The data values are used to get the exact lift and drag coefficient for each angle of attack and you have to search them for each aircraft to use.
-/// 
+/// - **polars**: one polar per Mach number, sorted by Mach. A table with no Mach information is a single polar used at every speed.
+///
+/// The data values are used to get the exact lift and drag coefficient for each angle of attack and Mach number, and you have to search them for each aircraft to use.
+///
+/// ## File formats:
+/// - **Plain table** (Mach independent): `[(alpha, Cl, Cd), ...]`
+/// - **Mach indexed**: `[(mach: 0.3, polar: [(alpha, Cl, Cd), ...]), (mach: 0.9, polar: [...]), ...]`
+///
+///   Polars don't need to share the same alpha values, each one is interpolated on its own
+///   and the two around the requested Mach are then blended (bilinear interpolation).
+///
+/// ## Beyond the table:
+/// Tables only cover the attached flow range (usually ±20°), past their ends the values fade
+/// into a flat plate's over `POST_STALL_BLEND` degrees, so any alpha from -180° to 180° (tail
+/// slides, flat spins, flying backwards) gets a sensible Cl and Cd.
+///
 /// ### Examples
-/// 
+///
 /// - **F-16**: uses **NACA 64A204**
 /// - **F-14**: uses **NACA 64A-112**
 /// - **SU-27**: uses **NACA 64A-212**
 
 #[derive(Debug, Clone)]
 pub struct AirFoil {
-    pub min_alpha: f32,
-    pub max_alpha: f32,
-    pub data: Vec<Vector3<f32>>
-} 
+    pub polars: Vec<Polar>,
+}
+
+/// Cl and Cd against alpha at a single Mach number.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Polar {
+    pub mach: f32,
+    pub polar: Vec<Vector3<f32>>, // [alpha (angle of attack), lift coefficient, drag coefficient]
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum AirFoilFile {
+    MachIndexed(Vec<Polar>),
+    Plain(Vec<Vector3<f32>>),
+}
 
 impl AirFoil {
     // this is called once
     pub fn new(data_path: String) -> Self {
-        let curve = match std::fs::read_to_string(data_path) {
+        let polars = match std::fs::read_to_string(&data_path) {
             Ok(file_contents) => {
-                match from_str::<Vec<nalgebra::Vector3<f32>>>(&file_contents) {
-                    Ok(data) => {
-                        data
+                match from_str::<AirFoilFile>(&file_contents) {
+                    Ok(AirFoilFile::MachIndexed(mut polars)) => {
+                        polars.sort_by(|a, b| a.mach.total_cmp(&b.mach));
+                        polars
+                    },
+                    Ok(AirFoilFile::Plain(polar)) => {
+                        vec![Polar { mach: 0.0, polar }]
                     },
-                    _ => {
+                    Err(e) => {
+                        eprintln!("Something went wrong structuring the airfoil '{}': {}", data_path, e);
                         vec![]
                     }
                 }
             },
-            _ => {
+            Err(e) => {
+                eprintln!("Something went wrong reading the airfoil '{}': {}", data_path, e);
                 vec![]
             }
         };
 
-        AirFoil { min_alpha: curve[0].x, max_alpha: curve[curve.len() - 1].x, data: curve }
+        AirFoil { polars }
+    }
+
+    // Sample function to get Cl and Cd based on alpha (degrees) and Mach number
+    pub fn sample(&self, alpha: f32, mach: f32) -> (f32, f32) {
+        match self.polars.len() {
+            0 => return (0.0, 0.0),
+            1 => return self.polars[0].sample(alpha),
+            _ => {}
+        }
+
+        let (lower, upper, t) = self.mach_blend(mach);
+        let (low_cl, low_cd) = lower.sample(alpha);
+        let (high_cl, high_cd) = upper.sample(alpha);
+
+        (low_cl + (high_cl - low_cl) * t, low_cd + (high_cd - low_cd) * t)
+    }
+
+    /// Cl and Cd once the flow has separated: never more lift nor less drag than a flat plate
+    /// would give, even at angles where attached flow would still be fine (stall hysteresis).
+    pub fn sample_stalled(&self, alpha: f32, mach: f32) -> (f32, f32) {
+        let (cl, cd) = self.sample(alpha, mach);
+        let (flat_cl, flat_cd) = flat_plate(alpha);
+
+        (if flat_cl.abs() < cl.abs() { flat_cl } else { cl }, cd.max(flat_cd))
+    }
+
+    /// Angles of attack (negative, positive) where the lift peaks, past them the wing stalls.
+    pub fn stall_alpha(&self, mach: f32) -> (f32, f32) {
+        match self.polars.len() {
+            0 => return (-180.0, 180.0),
+            1 => return self.polars[0].stall_alpha(),
+            _ => {}
+        }
+
+        let (lower, upper, t) = self.mach_blend(mach);
+        let (low_negative, low_positive) = lower.stall_alpha();
+        let (high_negative, high_positive) = upper.stall_alpha();
+
+        (low_negative + (high_negative - low_negative) * t, low_positive + (high_positive - low_positive) * t)
+    }
+
+    // The two polars around the Mach number and how far between them it is, needs at least two polars
+    fn mach_blend(&self, mach: f32) -> (&Polar, &Polar, f32) {
+        // Mach outside the data is held at the closest polar
+        let upper = self.polars.partition_point(|polar| polar.mach <= mach).clamp(1, self.polars.len() - 1);
+        let lower = upper - 1;
+
+        let mach_range = self.polars[upper].mach - self.polars[lower].mach;
+        let t = if mach_range > 0.0 { ((mach - self.polars[lower].mach) / mach_range).clamp(0.0, 1.0) } else { 0.0 };
+
+        (&self.polars[lower], &self.polars[upper], t)
     }
+}
+
+/// Cl and Cd of a flat plate at any alpha (degrees), the normal force splits into lift and drag.
+pub fn flat_plate(alpha: f32) -> (f32, f32) {
+    let alpha = alpha.to_radians();
+    let normal_force = FLAT_PLATE_CD * alpha.sin();
+
+    (normal_force * alpha.cos(), normal_force * alpha.sin() + FLAT_PLATE_CD0)
+}
+
+// Wraps any angle into (-180, 180]
+fn wrap_alpha(alpha: f32) -> f32 {
+    let wrapped = (alpha + 180.0).rem_euclid(360.0) - 180.0;
+    if wrapped == -180.0 { 180.0 } else { wrapped }
+}
 
-    // Sample function to get Cl and Cd based on alpha
+impl Polar {
+    // Alpha outside the table fades from the first/last entry into the flat plate values
     pub fn sample(&self, alpha: f32) -> (f32, f32) {
-        let len = self.data.len();
-        
-        // Get raw float index
-        let float_index = self.alpha_to_float_index(alpha); // see below
-        
-        // Clamp to valid range
-        let float_index = float_index.clamp(0.0, (len - 1) as f32);
-        
-        let lower = float_index.floor() as usize;
-        let upper = (lower + 1).min(len - 1);
-        let t = float_index.fract(); // interpolation factor 0..1
-        
-        let a = &self.data[lower];
-        let b = &self.data[upper];
-        
+        let len = self.polar.len();
+        match len {
+            0 => return (0.0, 0.0),
+            1 => return (self.polar[0].y, self.polar[0].z),
+            _ => {}
+        }
+
+        let alpha = wrap_alpha(alpha);
+        let first = &self.polar[0];
+        let last = &self.polar[len - 1];
+
+        if alpha < first.x {
+            return Self::blend_flat_plate(first, alpha, first.x - alpha);
+        }
+        if alpha > last.x {
+            return Self::blend_flat_plate(last, alpha, alpha - last.x);
+        }
+
+        let upper = self.polar.partition_point(|point| point.x <= alpha).clamp(1, len - 1);
+        let lower = upper - 1;
+
+        let a = &self.polar[lower];
+        let b = &self.polar[upper];
+
+        let range = b.x - a.x;
+        let t = if range > 0.0 { ((alpha - a.x) / range).clamp(0.0, 1.0) } else { 0.0 }; // interpolation factor 0..1
+
         let cl = a.y + (b.y - a.y) * t;
         let cd = a.z + (b.z - a.z) * t;
-        
+
         (cl, cd)
     }
 
-    fn alpha_to_float_index(&self, alpha: f32) -> f32 {
-        let range = self.max_alpha - self.min_alpha;
-        if range == 0.0 {
-            return 0.0;
+    /// Alphas of the lowest and highest Cl in the table.
+    pub fn stall_alpha(&self) -> (f32, f32) {
+        let negative = self.polar.iter().min_by(|a, b| a.y.total_cmp(&b.y));
+        let positive = self.polar.iter().max_by(|a, b| a.y.total_cmp(&b.y));
+
+        match (negative, positive) {
+            (Some(negative), Some(positive)) => (negative.x, positive.x),
+            _ => (-180.0, 180.0),
+        }
+    }
+
+    fn blend_flat_plate(edge: &Vector3<f32>, alpha: f32, past_edge: f32) -> (f32, f32) {
+        let (flat_cl, flat_cd) = flat_plate(alpha);
+        let t = (past_edge / POST_STALL_BLEND).clamp(0.0, 1.0);
+
+        (edge.y + (flat_cl - edge.y) * t, edge.z + (flat_cd - edge.z) * t)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn polar(mach: f32, cl_scale: f32, cd_offset: f32) -> Polar {
+        Polar {
+            mach,
+            polar: vec![
+                Vector3::new(-10.0, -cl_scale, 0.05 + cd_offset),
+                Vector3::new(0.0, 0.0, 0.01 + cd_offset),
+                Vector3::new(4.0, 0.4 * cl_scale, 0.02 + cd_offset),
+                Vector3::new(10.0, 1.0 * cl_scale, 0.05 + cd_offset),
+            ],
         }
-        let normalized_alpha = (alpha - self.min_alpha) / range;
-        normalized_alpha * (self.data.len() as f32 - 1.0)
-        // no .round() — keep the fractional part for interpolation
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn interpolates_unevenly_spaced_alpha() {
+        let air_foil = AirFoil { polars: vec![polar(0.0, 1.0, 0.0)] };
+
+        let (cl, cd) = air_foil.sample(2.0, 0.5);
+        assert!((cl - 0.2).abs() < 1e-6);
+        assert!((cd - 0.015).abs() < 1e-6);
+
+        let (cl, _) = air_foil.sample(7.0, 0.5);
+        assert!((cl - 0.7).abs() < 1e-6);
+
+    }
+
+    #[test]
+    fn extrapolates_to_a_flat_plate() {
+        let air_foil = AirFoil { polars: vec![polar(0.0, 1.0, 0.0)] };
+
+        // Continuous at the table edge, flat plate once past the blend
+        let (edge_cl, _) = air_foil.sample(10.0, 0.5);
+        let (just_past_cl, _) = air_foil.sample(10.1, 0.5);
+        assert!((edge_cl - just_past_cl).abs() < 0.02);
+        assert_eq!(air_foil.sample(45.0, 0.5), flat_plate(45.0));
+        assert_eq!(air_foil.sample(-45.0, 0.5), flat_plate(-45.0));
+
+        // Broadside to the flow: no lift, all drag
+        let (cl, cd) = air_foil.sample(90.0, 0.5);
+        assert!(cl.abs() < 1e-5);
+        assert!(cd > 1.2);
+
+        // Flying backwards lifts the other way, and the angle wraps around
+        let (cl, _) = air_foil.sample(160.0, 0.5);
+        assert!(cl < 0.0);
+        assert_eq!(air_foil.sample(200.0, 0.5), air_foil.sample(-160.0, 0.5));
+    }
+
+    #[test]
+    fn stall_alpha_follows_the_lift_peak() {
+        let mut stalling = polar(0.0, 1.0, 0.0);
+        stalling.polar.push(Vector3::new(14.0, 0.8, 0.1));
+        let air_foil = AirFoil { polars: vec![stalling] };
+
+        assert_eq!(air_foil.stall_alpha(0.5), (-10.0, 10.0));
+
+        // Separated flow keeps the drag and loses lift compared to the attached table
+        let (attached_cl, attached_cd) = air_foil.sample(8.0, 0.5);
+        let (stalled_cl, stalled_cd) = air_foil.sample_stalled(8.0, 0.5);
+        assert!(stalled_cl < attached_cl);
+        assert!(stalled_cd > attached_cd);
+    }
+
+    #[test]
+    fn blends_between_mach_polars() {
+        let air_foil = AirFoil { polars: vec![polar(0.5, 1.0, 0.0), polar(1.0, 2.0, 0.02)] };
+
+        let (cl, cd) = air_foil.sample(4.0, 0.75);
+        assert!((cl - 0.6).abs() < 1e-6);
+        assert!((cd - 0.03).abs() < 1e-6);
+
+        // Held at the closest polar outside the Mach range
+        assert_eq!(air_foil.sample(4.0, 0.1), air_foil.sample(4.0, 0.5));
+        assert_eq!(air_foil.sample(4.0, 3.0), air_foil.sample(4.0, 1.0));
+    }
+
+    #[test]
+    fn reads_both_file_formats() {
+        let plain = AirFoil::new("assets/aero_data/f16-elevators.ron".to_string());
+        assert_eq!(plain.polars.len(), 1);
+        assert_eq!(plain.sample(0.0, 0.2), plain.sample(0.0, 1.5));
+
+        let mach_indexed = AirFoil::new("assets/aero_data/f16-mach.ron".to_string());
+        assert!(mach_indexed.polars.len() > 1);
+        assert!(mach_indexed.polars.windows(2).all(|pair| pair[0].mach < pair[1].mach));
+
+        // Transonic drag rise
+        let (_, subsonic_cd) = mach_indexed.sample(2.0, 0.5);
+        let (_, transonic_cd) = mach_indexed.sample(2.0, 1.0);
+        assert!(transonic_cd > subsonic_cd * 2.0);
+    }
+}
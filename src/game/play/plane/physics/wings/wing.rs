@@ -1,11 +1,15 @@
 use rapier3d::prelude::RigidBody;
-use std::f32::consts::PI;
-use nalgebra::vector;
 
-use crate::{engine::physics::physics::DebugPhysicsMessageType, engine::primitive::manual_vertex::ManualVertex};
+use crate::game::play::plane::aircraft::{ControlMixing, FlapDefinition, LeadingEdgeDefinition, SurfaceDefinition};
+use crate::game::play::plane::plane::PlaneControls;
+use crate::game::play::plane::utils::move_towards;
+use crate::engine::physics::atmosphere::AirData;
 
 use super::airfoil::AirFoil;
 
+// Degrees below the stall angle the flow has to get back to before it reattaches
+const STALL_HYSTERESIS: f32 = 3.0;
+
 pub struct Wing {
     pub label: String,
     pub pressure_center: nalgebra::Vector3<f32>,
@@ -20,9 +24,18 @@ pub struct Wing {
     pub stable: bool,
     pub incidence_angle: f32,
     pub max_force: f32,
+    pub mixing: ControlMixing,
+    pub alpha: f32, // degrees between the airflow and the wing (incidence included, control deflection not)
+    pub stalled: bool,
+    pub flap: Option<FlapDefinition>,
+    pub flap_position: f32, // 0 up, 1 fully down
+    pub leading_edge: Option<LeadingEdgeDefinition>,
+    pub leading_edge_position: f32, // 0 retracted, 1 fully out
+    pub effectiveness: f32, // 1 undamaged down to 0 destroyed, scales the lift (see PlaneDamage)
 }
 
 impl Wing {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(label: String, pressure_center: nalgebra::Vector3<f32>, wing_area: f32, chord: f32, air_foil: AirFoil, normal: nalgebra::Vector3<f32>, is_roll_axis: bool, stable: bool, incidence_angle: f32, max_force: f32) -> Self {
         Self {
             label,
@@ -38,16 +51,108 @@ impl Wing {
             stable,
             incidence_angle,
             max_force,
+            mixing: ControlMixing::default(),
+            alpha: 0.0,
+            stalled: false,
+            flap: None,
+            flap_position: 0.0,
+            leading_edge: None,
+            leading_edge_position: 0.0,
+            effectiveness: 1.0,
+        }
+    }
+
+    pub fn from_definition(definition: &SurfaceDefinition, air_foil: AirFoil) -> Self {
+        let mut wing = Wing::new(definition.label.clone(), definition.pressure_center, definition.area, definition.chord, air_foil, definition.normal, definition.is_roll_axis, definition.stable, definition.incidence_angle, definition.max_force);
+        wing.mixing = definition.mixing.clone();
+        wing.flap = definition.flap.clone();
+        wing.leading_edge = definition.leading_edge.clone();
+        wing
+    }
+
+    /// Sign of the Cl that lifts the wing towards its local up: the lift direction is the span
+    /// crossed with the airflow, so a wing spanning +x lifts up with a negative Cl.
+    fn lift_sign(&self) -> f32 {
+        self.normal.cross(&nalgebra::Vector3::z()).y.signum()
+    }
+
+    /// Flap follows the lever, the leading edge devices the switch or their alpha schedule
+    /// (whichever asks for more), each over its own deploy time.
+    pub fn move_devices(&mut self, controls: &PlaneControls, delta_time: f32) {
+        if let Some(flap) = &self.flap {
+            self.flap_position = move_towards(self.flap_position, controls.flaps.clamp(0.0, 1.0), flap.deploy_time, delta_time);
+        }
+
+        if let Some(leading_edge) = &self.leading_edge {
+            let scheduled = match leading_edge.alpha_schedule {
+                Some((start, full)) if full > start => ((self.alpha * self.lift_sign() - start) / (full - start)).clamp(0.0, 1.0),
+                _ => 0.0,
+            };
+            let target = if controls.leading_edge { 1.0 } else { scheduled };
+            self.leading_edge_position = move_towards(self.leading_edge_position, target, leading_edge.deploy_time, delta_time);
         }
     }
 
-    pub fn physics_force(&mut self, rigidbody: &mut RigidBody) {
+    // Degrees the leading edge devices currently move the stall out
+    fn stall_alpha_increase(&self) -> f32 {
+        match &self.leading_edge {
+            Some(leading_edge) => leading_edge.stall_alpha_increase * self.leading_edge_position,
+            None => 0.0,
+        }
+    }
+
+    /// Leading edge devices stretch the lift curve at its peak: past the clean stall angle the
+    /// airfoil is read `stall_alpha_increase` degrees back, holding the peak Cl in between.
+    fn leading_edge_alpha(&self, aoa_deg: f32, mach: f32) -> f32 {
+        let increase = self.stall_alpha_increase();
+        if increase <= 0.0 {
+            return aoa_deg;
+        }
+
+        let sign = self.lift_sign();
+        let (stall_negative, stall_positive) = self.air_foil.stall_alpha(mach);
+        let peak = if sign < 0.0 { stall_negative } else { stall_positive } * sign;
+        let lifting_alpha = aoa_deg * sign;
+
+        if lifting_alpha > peak {
+            (lifting_alpha - increase).max(peak) * sign
+        } else {
+            aoa_deg
+        }
+    }
+
+    // Extra (lift, drag) coefficients from the devices, the flap only lifts with the flow attached
+    fn device_coefficients(&self) -> (f32, f32) {
+        let (flap_lift, flap_drag) = match &self.flap {
+            Some(flap) => (flap.lift_increment * self.flap_position, flap.drag_increment * self.flap_position),
+            None => (0.0, 0.0),
+        };
+        let leading_edge_drag = match &self.leading_edge {
+            Some(leading_edge) => leading_edge.drag_increment * self.leading_edge_position,
+            None => 0.0,
+        };
+        let flap_lift = if self.stalled { 0.0 } else { flap_lift };
+
+        (self.lift_sign() * flap_lift, flap_drag + leading_edge_drag)
+    }
+
+    /// `wind` is the air mass's velocity at the pressure center, every force below works with the
+    /// velocity relative to it (airspeed) rather than the one over the ground.
+    /// A damaged surface lifts (or steers) `effectiveness` as much but is still all there, so
+    /// it drags as much as ever. A destroyed one has fallen off with its collider and mass
+    /// (see PlaneDamage), its drag and everything else went with it.
+    pub fn physics_force(&mut self, rigidbody: &mut RigidBody, air: &AirData, wind: &nalgebra::Vector3<f32>) {
+        if self.effectiveness <= 0.0 {
+            self.last_lift_force = nalgebra::Vector3::zeros();
+            return;
+        }
+
         let world_pressure_center = rigidbody.rotation() * self.pressure_center
             + rigidbody.translation();
 
         let angular_contribution = rigidbody.angvel()
             .cross(&(rigidbody.rotation() * self.pressure_center));
-        let world_velocity = rigidbody.linvel() + angular_contribution;
+        let world_velocity = rigidbody.linvel() + angular_contribution - wind;
         let local_velocity = rigidbody.rotation().inverse() * world_velocity;
 
         if local_velocity.magnitude() < 0.01 {
@@ -57,10 +162,11 @@ impl Wing {
         let forward_speed = local_velocity.z;
         let vertical_speed = local_velocity.y;
 
-        let max_deflection = if self.is_roll_axis { 25.0 } else { 25.0 };
-        let air_density = 1.225f32;
+        let max_deflection = 25.0;
+        let air_density = air.density;
         let speed_sq = local_velocity.magnitude_squared();
         let dynamic_pressure = 0.5 * air_density * speed_sq;
+        let mach = air.mach(speed_sq.sqrt());
 
         let velocity_dir_world = (rigidbody.rotation() * local_velocity).normalize();
         let span_axis_world = rigidbody.rotation() * self.normal;
@@ -72,25 +178,33 @@ impl Wing {
         
             let yaw_damping = 1000.0; // tune this
             let deflection_deg = self.control_input * max_deflection;
-            let (cl, _cd) = self.air_foil.sample(deflection_deg);
+            let (cl, _cd) = self.air_foil.sample(deflection_deg, mach);
             let control_authority = dynamic_pressure * self.wing_area * cl.abs();
             
             // Damping resists sideslip, control_input steers into it
             let side_force_magnitude = (-sideslip_speed * yaw_damping) + (self.control_input.signum() * control_authority);
             
             let side_axis_world = rigidbody.rotation() * nalgebra::Vector3::x();
-            let side_force = side_axis_world * side_force_magnitude;
+            let side_force = side_axis_world * side_force_magnitude * self.effectiveness;
             
             self.last_lift_force = side_force;
             side_force
         } else {
-            let aoa_deg = vertical_speed.atan2(forward_speed).to_degrees()
-                + self.incidence_angle
-                + self.control_input * (max_deflection as f32);
+            self.alpha = vertical_speed.atan2(forward_speed).to_degrees() + self.incidence_angle;
+            self.update_stall(mach);
+
+            let aoa_deg = self.alpha + self.control_input * max_deflection;
 
-            let (lift_coefficient, drag_coefficient) = self.air_foil.sample(aoa_deg);
+            let (lift_coefficient, drag_coefficient) = if self.stalled {
+                self.air_foil.sample_stalled(aoa_deg, mach)
+            } else {
+                self.air_foil.sample(self.leading_edge_alpha(aoa_deg, mach), mach)
+            };
+            let (device_lift, device_drag) = self.device_coefficients();
+            let lift_coefficient = lift_coefficient + device_lift;
+            let drag_coefficient = drag_coefficient + device_drag;
 
-            let lift_force = lift_dir * (dynamic_pressure * self.wing_area * lift_coefficient);
+            let lift_force = lift_dir * (dynamic_pressure * self.wing_area * lift_coefficient * self.effectiveness);
             let drag_force = rigidbody.rotation() * (-velocity_dir_local * dynamic_pressure * self.wing_area * drag_coefficient);
             let damping_coefficient = 235.0;
             let angular_vel = rigidbody.angvel();
@@ -130,6 +244,24 @@ impl Wing {
         let mag = total_force.magnitude();
         let clamped = if mag > self.max_force { total_force * (self.max_force / mag) } else { total_force };
 
-        rigidbody.add_force_at_point(clamped.into(), world_pressure_center.into(), true);
+        rigidbody.add_force_at_point(clamped, world_pressure_center.into(), true);
+    }
+
+    /// The flow separates once the wing's alpha goes past the airfoil's lift peak and only
+    /// reattaches `STALL_HYSTERESIS` degrees below it. Each wing sees its own alpha (a rolling
+    /// or yawing plane raises it on one side and lowers it on the other), so one wing can
+    /// stall before the other and drop, and keep the plane autorotating into a spin.
+    /// Deployed leading edge devices move the stall out on the lifting side.
+    fn update_stall(&mut self, mach: f32) {
+        let (stall_negative, stall_positive) = self.air_foil.stall_alpha(mach);
+        let increase = self.stall_alpha_increase();
+        let (stall_negative, stall_positive) = if self.lift_sign() < 0.0 {
+            (stall_negative - increase, stall_positive)
+        } else {
+            (stall_negative, stall_positive + increase)
+        };
+        let margin = if self.stalled { STALL_HYSTERESIS } else { 0.0 };
+
+        self.stalled = self.alpha > stall_positive - margin || self.alpha < stall_negative + margin;
     }
-}
\ No newline at end of file
+}
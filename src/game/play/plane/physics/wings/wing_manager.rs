@@ -1,40 +1,49 @@
-use nalgebra::vector;
+use std::collections::HashMap;
+
 use rapier3d::{dynamics::{RigidBody}};
 
-use crate::{game::play::plane::{physics::wings::{airfoil::AirFoil, wing::Wing}, plane::PlaneControls}};
+use crate::{engine::physics::{atmosphere::AirData, wind::Wind}, game::play::plane::{aircraft::SurfaceDefinition, physics::wings::{airfoil::AirFoil, wing::Wing}, plane::PlaneControls}};
 
 pub struct WingManager {
   pub wings: Vec<Wing>,
 }
 
 impl WingManager {
-  pub fn new() -> Self {
-    let naca_2412 = AirFoil::new("assets/aero_data/f16.ron".to_owned());
-    let naca_0012 = AirFoil::new("assets/aero_data/f16-elevators.ron".to_owned());
-
-    let wings = vec![
-      Wing::new("Left wing".to_string(), vector![5.6, 0.0, 1.4], 16.5, 0.0, naca_2412.clone(), vector![1.0,0.0, 0.0], true, false, 4.0, 500_000.0), // left wing (+4° incidence, includes LEX area)
-      Wing::new("Right wing".to_string(), vector![-5.6, 0.0, 1.4], 16.5, 0.0, naca_2412.clone(), vector![1.0, 0.0, 0.0], true, false, 4.0, 500_000.0), // right wing (+4° incidence, includes LEX area)
-      Wing::new("Right elevator wing".to_string(), vector![4.2, 0.0, -7.0], 2.70, 0.0, naca_0012.clone(), vector![1.0, 0.0, 0.0], false, false, -1.5, 120_000.0), // right elevator wing (-5° trim to counter the main wings' +4° incidence pitching the nose up at cruise)
-      Wing::new("Left elevator wing".to_string(), vector![-4.2, 0.0, -7.0], 2.70, 0.0, naca_0012.clone(), vector![1.0, 0.0, 0.0], false, false, -1.5, 120_000.0), // left elevator wing (-5° trim to counter the main wings' +4° incidence pitching the nose up at cruise)
-      Wing::new("Rudder wing".to_string(), vector![0.0, 4.2, -11.2], 1.70, 0.0, naca_0012.clone(), vector![0.0, 1.0, 0.0], false, true, 0.0, 200_000.0) // rudder wing
-    ];
+  pub fn new(surfaces: &[SurfaceDefinition]) -> Self {
+    // Surfaces usually share a handful of airfoil tables, so each file is only read once
+    let mut air_foils: HashMap<String, AirFoil> = HashMap::new();
+
+    let wings = surfaces.iter().map(|surface| {
+      let air_foil = air_foils.entry(surface.airfoil.clone())
+        .or_insert_with(|| AirFoil::new(surface.airfoil.clone()))
+        .clone();
+
+      Wing::from_definition(surface, air_foil)
+    }).collect();
 
     Self { wings }
   }
 
-  pub fn update(&mut self, plane_controls: &PlaneControls, rigidbody: &mut RigidBody) {
+  /// Each wing feels the wind where it sits, so turbulence and gusts can hit one side harder than the other.
+  pub fn update(&mut self, plane_controls: &PlaneControls, rigidbody: &mut RigidBody, air: &AirData, wind: &Wind, time: f32, delta_time: f32) {
     for wing in &mut self.wings {
-      wing.control_input = match wing.label.as_str() {
-          "Left wing"           => (-plane_controls.aileron + plane_controls.trim_roll).clamp(-1.0, 1.0),
-          "Right wing"          => (plane_controls.aileron + plane_controls.trim_roll).clamp(-1.0, 1.0),
-          "Left elevator wing"  => (plane_controls.elevator + plane_controls.trim_pitch).clamp(-1.0, 1.0),
-          "Right elevator wing" => (plane_controls.elevator + plane_controls.trim_pitch).clamp(-1.0, 1.0),
-          "Rudder wing"         => (plane_controls.rudder + plane_controls.trim_yaw).clamp(-1.0, 1.0),
-          _ => 0.0,
-      };
-
-      wing.physics_force(rigidbody);
+      wing.control_input = wing.mixing.apply(plane_controls);
+      wing.move_devices(plane_controls, delta_time);
+
+      let pressure_center = rigidbody.translation() + rigidbody.rotation() * wing.pressure_center;
+      wing.physics_force(rigidbody, air, &wind.sample(&pressure_center, time));
     }
   }
- }
\ No newline at end of file
+
+  /// Average flap position over the wings that have one, 0 if none do.
+  pub fn flap_position(&self) -> f32 {
+    let positions: Vec<f32> = self.wings.iter().filter(|wing| wing.flap.is_some()).map(|wing| wing.flap_position).collect();
+    if positions.is_empty() { 0.0 } else { positions.iter().sum::<f32>() / positions.len() as f32 }
+  }
+
+  /// Average leading edge device position over the wings that have them, 0 if none do.
+  pub fn leading_edge_position(&self) -> f32 {
+    let positions: Vec<f32> = self.wings.iter().filter(|wing| wing.leading_edge.is_some()).map(|wing| wing.leading_edge_position).collect();
+    if positions.is_empty() { 0.0 } else { positions.iter().sum::<f32>() / positions.len() as f32 }
+  }
+ }
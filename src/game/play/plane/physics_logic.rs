@@ -1,39 +1,171 @@
 use std::collections::HashMap;
 use std::sync::mpsc::Sender;
 
-use crate::game::play::plane::physics::wheels::wheel::{Wheel, WheelData};
+use serde::{Deserialize, Serialize};
+
 use crate::game::play::plane::physics::wheels::wheel_manager::WheelManager;
 use crate::game::play::plane::physics::wings::wing_manager::WingManager;
+use crate::game::play::plane::physics::speedbrake::Speedbrake;
 use crate::game::play::plane::plane::{PlaneControls};
 use crate::engine::physics::physics::DebugPhysicsMessageType;
-use crate::engine::physics::physics_handler::{ColliderDebugData, MetadataType, PhysicsData, PhysicsTick, SuspensionDebugData, WingDebugData};
-use rapier3d::prelude::{ColliderSet, QueryPipeline, RigidBodySet};
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::commands::CommandQueue;
+use crate::engine::physics::events::{PhysicsEvent, PhysicsEventKind};
+use crate::engine::physics::physics_handler::{PhysicsData, PhysicsTick};
+use crate::game::play::plane::metadata::{ColliderDebugData, ComponentDamageData, WingDebugData, WingStallData, DeviceData};
+use rapier3d::prelude::{ColliderSet, MassProperties, QueryPipeline, RigidBody, RigidBodyAdditionalMassProps, RigidBodySet};
+use crate::game::play::plane::aircraft::{AircraftDefinition, FuselageDefinition};
 use crate::game::play::plane::flight_system::FlightSystem;
+use crate::game::play::plane::engine::{EngineState, JetEngine};
+use crate::game::play::plane::fly_by_wire::FlyByWire;
+use crate::game::play::plane::damage::{ComponentKind, Damage, PlaneDamage};
+
+/// What a physics snapshot keeps of the plane besides its rigidbody: everything the tick
+/// integrates over time. The fly by wire's integrators start over on load.
+#[derive(Serialize, Deserialize)]
+struct PlaneLogicState {
+    controls: PlaneControls,
+    engine_power: f32,
+    engine: EngineState,
+    gear: Vec<f32>,            // deployed and load last tick, per wheel
+    wheel_loads: Vec<f32>,
+    weight_off_wheels_time: f32,
+    flaps: Vec<f32>,           // flap and leading edge positions, alpha and stall state, per wing
+    leading_edges: Vec<f32>,
+    alpha: Vec<f32>,           // the leading edge schedule reads last tick's
+    stalled: Vec<bool>,
+    speedbrake: Option<f32>,
+    applied_fuel_mass: Option<f32>, // the fuel the saved rigidbody mass includes
+    #[serde(default)]
+    health: Vec<f32>, // per damageable part, see PlaneDamage
+}
 
 pub struct PlanePhysicsLogic {
+    // Key of the plane in the level's physics elements, its PlaneControls are addressed to it too
+    pub entity: String,
+    // Last controls received, held until new ones arrive
+    pub controls: PlaneControls,
     pub wheel_manager: WheelManager,
     pub wing_manager: WingManager,
-    pub renderizable_wheels: HashMap<String, WheelData>,
     pub renderizable_lines: Vec<DebugPhysicsMessageType>,
     pub flight_system: FlightSystem,
     pub debug_rendering_enabled: bool,
+    pub fuselage: FuselageDefinition,
+    pub engine: JetEngine,
+    pub fly_by_wire: Option<FlyByWire>,
+    pub speedbrake: Option<Speedbrake>,
+    pub damage: PlaneDamage,
+    // Empty mass and center of mass from the aircraft definition, the fuel left is added on
+    // top of them (the level only knows which body is the player, not which plane was picked).
+    dry_mass: (f32, nalgebra::Vector3<f32>),
+    fuel_position: nalgebra::Vector3<f32>,
+    // Fuel mass last written to the rigidbody, None until the first tick applies it
+    applied_fuel_mass: Option<f32>,
 }
 
 impl PlanePhysicsLogic {
-    pub fn new() -> Self {
-        let wheel_manager = WheelManager::new();
-        let wing_manager = WingManager::new();
+    pub fn new(definition: &AircraftDefinition, entity: impl Into<String>) -> Self {
+        let wheel_manager = WheelManager::new(&definition.gear);
+        let wing_manager = WingManager::new(&definition.surfaces);
 
         Self {
+            entity: entity.into(),
+            controls: PlaneControls::new(),
             wheel_manager,
             wing_manager,
-            renderizable_wheels: HashMap::new(),
             renderizable_lines: Vec::new(),
             flight_system: FlightSystem::new(),
             debug_rendering_enabled: false,
+            fuselage: definition.fuselage.clone(),
+            engine: JetEngine::new(&definition.engine, definition.fuel.as_ref()),
+            fly_by_wire: definition.fly_by_wire.as_ref().map(FlyByWire::new),
+            speedbrake: definition.speedbrake.as_ref().map(Speedbrake::new),
+            damage: PlaneDamage::new(definition),
+            dry_mass: (definition.mass, definition.center_of_mass),
+            fuel_position: definition.fuel.as_ref().map(|fuel| fuel.position).unwrap_or_default(),
+            applied_fuel_mass: None,
         }
     }
+
+    /// Total mass and center of mass with the fuel that's left, less the parts that fell off.
+    fn loaded_mass(&self) -> (f32, nalgebra::Vector3<f32>) {
+        let (dry_mass, dry_center_of_mass) = self.dry_mass;
+        let fuel_mass = if self.engine.has_fuel_system() { self.engine.state.fuel_mass } else { 0.0 };
+        let (lost_mass, lost_center_of_mass) = self.damage.lost_mass();
+        let mass = dry_mass + fuel_mass - lost_mass;
+
+        (mass, (dry_center_of_mass * dry_mass + self.fuel_position * fuel_mass - lost_center_of_mass * lost_mass) / mass)
+    }
+
+    /// Swaps the level-authored mass for the aircraft's own, scaling the inertia the
+    /// level derived from the colliders by the same ratio so the shape of it is kept.
+    fn apply_mass(rigidbody: &mut RigidBody, mass: f32, center_of_mass: nalgebra::Vector3<f32>) {
+        let current = match rigidbody.mass_properties().additional_local_mprops.as_deref() {
+            Some(RigidBodyAdditionalMassProps::MassProps(props)) => *props,
+            _ => rigidbody.mass_properties().local_mprops,
+        };
+        let ratio = if current.mass() > 0.0 { mass / current.mass() } else { 1.0 };
+
+        rigidbody.set_additional_mass_properties(
+            MassProperties::with_principal_inertia_frame(
+                center_of_mass.into(),
+                mass,
+                current.principal_inertia() * ratio,
+                current.principal_inertia_local_frame,
+            ),
+            true,
+        );
+    }
     
+    /// Takes a hit, the parts it damaged work that much worse from now on. What it destroyed
+    /// shows in the `plane/damage` metadata.
+    pub fn take_damage(&mut self, damage: &Damage) {
+        if !self.damage.apply(damage).is_empty() {
+            // Their mass went with them
+            self.applied_fuel_mass = None;
+        }
+        self.apply_damage();
+    }
+
+    // Disables the colliders of the parts that fell off, for the first tick after they did
+    // and after a snapshot whose colliders were saved with them on
+    fn detach_colliders(&self, collider_set: &mut ColliderSet, physics_data: &PhysicsData) {
+        for point in self.damage.lost_colliders() {
+            let lost = physics_data.collider_handles.iter().find(|handle| collider_set.get(**handle).is_some_and(|collider| {
+                let local_position = collider.position_wrt_parent().copied().unwrap_or_default();
+                collider.is_enabled() && !collider.is_sensor() && collider.shape().contains_point(&local_position, &point.into())
+            }));
+            if let Some(collider) = lost.and_then(|handle| collider_set.get_mut(*handle)) {
+                collider.set_enabled(false);
+            }
+        }
+    }
+
+    // Passes what's left of each part on to the flight model
+    fn apply_damage(&mut self) {
+        for (index, wing) in self.wing_manager.wings.iter_mut().enumerate() {
+            wing.effectiveness = self.damage.effectiveness(ComponentKind::Surface(index));
+        }
+        self.engine.effectiveness = self.damage.effectiveness(ComponentKind::Engine);
+        for (index, wheel) in self.wheel_manager.wheels.iter_mut().enumerate() {
+            wheel.destroyed = self.damage.effectiveness(ComponentKind::Gear(index)) <= 0.0;
+            if wheel.destroyed {
+                wheel.load = 0.0;
+            }
+        }
+    }
+
+    // The airframe's own collisions, the gear only touches the ground through its rays
+    fn impact_damage(&self, events: &[PhysicsEvent], rigidbody: &RigidBody) -> Vec<Damage> {
+        events.iter()
+            .filter(|event| event.kind == PhysicsEventKind::CollisionStarted)
+            .filter_map(|event| {
+                let local_point = rigidbody.position().inverse_transform_point(&event.contact_point?.into());
+                self.damage.impact(local_point.coords, event.impact_speed)
+            })
+            .collect()
+    }
+
     /// Toggle debug rendering on/off
     pub fn toggle_debug_rendering(&mut self) {
         self.debug_rendering_enabled = !self.debug_rendering_enabled;
@@ -41,15 +173,18 @@ impl PlanePhysicsLogic {
     }
 
     /// Configure roll damping for different aircraft types
-    pub fn update(&mut self, plane_controls: &PlaneControls, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, physics_data: &mut PhysicsData, debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, delta_time: f32) {
+    #[allow(clippy::too_many_arguments)]
+    pub fn update(&mut self, plane_controls: &PlaneControls, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, atmosphere: &Atmosphere, physics_data: &mut PhysicsData, time: f32, delta_time: f32) {
         self.renderizable_lines.clear();
-        physics_data.metadata.clear();
+        // Only what the plane reports, other ticks driving it report on it too (e.g. GunLogic).
+        // The debug entries are only filled in while it's on
+        physics_data.metadata.clear_namespace("plane");
 
         // Send collider shapes as metadata so the main thread can render them in sync with the model
         if self.debug_rendering_enabled {
             let mut collider_debug: Vec<ColliderDebugData> = Vec::new();
             for collider_handle in &physics_data.collider_handles {
-                if let Some(collider) = collider_set.get(*collider_handle) {
+                if let Some(collider) = collider_set.get(*collider_handle).filter(|collider| collider.is_enabled()) {
                     if let Some(cuboid) = collider.shape().as_cuboid() {
                         let local_pos = collider.position_wrt_parent()
                             .map(|p| p.translation.vector)
@@ -61,33 +196,64 @@ impl PlanePhysicsLogic {
                     }
                 }
             }
-            physics_data.metadata.insert("colliders".to_string(), MetadataType::Colliders(collider_debug));
+            physics_data.metadata.insert("plane/colliders", collider_debug);
         }
 
 
+        let air = match rigidbody_set.get(physics_data.rigidbody_handle) {
+            Some(rigidbody) => atmosphere.sample(rigidbody.translation().y),
+            None => atmosphere.sample(0.0),
+        };
+        // Wind at the center of mass, for the forces that act on the plane as a whole
+        let wind = match rigidbody_set.get(physics_data.rigidbody_handle) {
+            Some(rigidbody) => atmosphere.wind.sample(&rigidbody.center_of_mass().coords, time),
+            None => nalgebra::Vector3::zeros(),
+        };
+
         if let Some(rigidbody) = rigidbody_set.get_mut(physics_data.rigidbody_handle) {
             rigidbody.reset_forces(true);
             rigidbody.reset_torques(true);
 
+            // Rewriting mass properties every tick is wasteful, burnt fuel only matters once it adds up
+            let fuel_mass = self.engine.state.fuel_mass;
+            let fuel_changed = match self.applied_fuel_mass {
+                Some(applied) => (applied - fuel_mass).abs() >= 1.0,
+                None => true,
+            };
+            if fuel_changed {
+                let (mass, center_of_mass) = self.loaded_mass();
+                Self::apply_mass(rigidbody, mass, center_of_mass);
+                self.applied_fuel_mass = Some(fuel_mass);
+            }
+
             // State calculations
             // NOTE: debug_text!() should be called from the main thread (play.rs), not physics thread
             // Use physics_data.metadata to pass debug values to the main thread if needed
 
             //self.flight_system.calculate_state(rigidbody, delta_time);
-            self.flight_system.update_thrust(rigidbody, delta_time, plane_controls.throttle);
+            let mach = air.mach((rigidbody.linvel() - wind).magnitude());
+            self.engine.update(plane_controls.throttle, &air, mach, delta_time);
+            self.engine.apply_thrust(rigidbody);
 
-            let local_vel = rigidbody.rotation().inverse() * rigidbody.linvel();
+            let local_vel = rigidbody.rotation().inverse() * (rigidbody.linvel() - wind);
             let sideslip_speed = local_vel.x;
-            let air_density = 1.225f32;
-            let fuselage_side_area = 20.0; // m² - approximate F-16 fuselage side profile
-            let fuselage_cd = 1.2;         // bluff body drag coefficient
-            let fuselage_side_force_mag = -0.5 * air_density * sideslip_speed * sideslip_speed.abs() * fuselage_side_area * fuselage_cd;
+            let fuselage_side_force_mag = -0.5 * air.density * sideslip_speed * sideslip_speed.abs() * self.fuselage.side_area * self.fuselage.side_cd;
             let fuselage_side_force = rigidbody.rotation() * nalgebra::Vector3::new(fuselage_side_force_mag, 0.0, 0.0);
             rigidbody.add_force(fuselage_side_force, true);
         }
 
-        let suspension_debug_data = self.wheel_manager.update(physics_data, collider_set, rigidbody_set, query_pipeline);
-        self.wing_manager.update(plane_controls, rigidbody_set.get_mut(physics_data.rigidbody_handle).unwrap());
+        // The flight computer (if any) decides what the surfaces do with the pilot's input
+        let surface_controls = match (&mut self.fly_by_wire, rigidbody_set.get(physics_data.rigidbody_handle)) {
+            (Some(fly_by_wire), Some(rigidbody)) => fly_by_wire.update(plane_controls, rigidbody, delta_time),
+            _ => plane_controls.clone(),
+        };
+
+        let suspension_debug_data = self.wheel_manager.update(plane_controls, physics_data, collider_set, rigidbody_set, query_pipeline, &air, &wind, delta_time);
+        self.wing_manager.update(&surface_controls, rigidbody_set.get_mut(physics_data.rigidbody_handle).unwrap(), &air, &atmosphere.wind, time, delta_time);
+
+        if let (Some(speedbrake), Some(rigidbody)) = (&mut self.speedbrake, rigidbody_set.get_mut(physics_data.rigidbody_handle)) {
+            speedbrake.update(plane_controls.speedbrake, rigidbody, &air, &wind, delta_time);
+        }
 
 
         // Send wing and suspension debug data via metadata for main-thread rendering
@@ -96,24 +262,61 @@ impl PlanePhysicsLogic {
                 pressure_center: w.pressure_center,
                 last_lift_force: w.last_lift_force,
             }).collect();
-            physics_data.metadata.insert("wings".to_string(), MetadataType::Wings(wing_debug));
-            physics_data.metadata.insert("suspensions".to_string(), MetadataType::Suspensions(suspension_debug_data));
+            physics_data.metadata.insert("plane/wings", wing_debug);
+            physics_data.metadata.insert("plane/suspensions", suspension_debug_data);
         }
 
-        physics_data.metadata.insert("wheels".to_string(), MetadataType::Wheels(self.renderizable_wheels.clone()));
+        physics_data.metadata.insert("plane/wheels", self.wheel_manager.renderizable_wheels.clone());
+        physics_data.metadata.insert("plane/engine", self.engine.state.clone());
+        if let Some(fly_by_wire) = &self.fly_by_wire {
+            physics_data.metadata.insert("plane/fly_by_wire", fly_by_wire.state.clone());
+        }
+        physics_data.metadata.insert("plane/devices", DeviceData {
+            flaps: self.wing_manager.flap_position(),
+            leading_edge: self.wing_manager.leading_edge_position(),
+            speedbrake: self.speedbrake.as_ref().map(|speedbrake| speedbrake.position).unwrap_or(0.0),
+        });
+
+        // Rudders aren't lifting surfaces, they never stall
+        let stall_data: Vec<WingStallData> = self.wing_manager.wings.iter().filter(|w| !w.stable).map(|w| WingStallData {
+            label: w.label.clone(),
+            alpha: w.alpha,
+            stalled: w.stalled,
+        }).collect();
+        physics_data.metadata.insert("plane/stall", stall_data);
+
+        if !self.damage.components.is_empty() {
+            let damage_data: Vec<ComponentDamageData> = self.damage.components.iter().map(|component| ComponentDamageData {
+                label: component.label.clone(),
+                health: component.effectiveness(),
+                meshes: component.meshes.clone(),
+            }).collect();
+            physics_data.metadata.insert("plane/damage", damage_data);
+        }
     }
 }
 
 impl PhysicsTick for PlanePhysicsLogic {
-    // The "player" key is a plane-specific convention, so it belongs here rather
-    // than in the generic physics engine module.
-    fn tick(&mut self, controls: &PlaneControls, collider_set: &ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, physics_elements: &mut HashMap<String, Option<PhysicsData>>, debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, delta_time: f32) {
-        match physics_elements.get_mut("player") {
-            Some(Some(physics_data)) => {
-                self.update(controls, collider_set, rigidbody_set, query_pipeline, physics_data, debug_physics_tx, delta_time);
-            },
-            _ => println!("Player not found"),
+    fn tick(&mut self, commands: &mut CommandQueue, collider_set: &mut ColliderSet, rigidbody_set: &mut RigidBodySet, query_pipeline: &QueryPipeline, atmosphere: &Atmosphere, physics_elements: &mut HashMap<String, Option<PhysicsData>>, _debug_physics_tx: &Sender<Vec<DebugPhysicsMessageType>>, time: f64, delta_time: f32) {
+        if let Some(controls) = commands.latest::<PlaneControls>(&self.entity) {
+            self.controls = controls;
         }
+        let hits = commands.take::<Damage>(&self.entity);
+        let events = commands.take::<PhysicsEvent>(&self.entity);
+
+        // Despawned planes sit still until a snapshot brings them back
+        let Some(Some(physics_data)) = physics_elements.get_mut(&self.entity) else { return };
+        let impacts = match rigidbody_set.get(physics_data.rigidbody_handle) {
+            Some(rigidbody) => self.impact_damage(&events, rigidbody),
+            None => Vec::new(),
+        };
+        for damage in hits.iter().chain(&impacts) {
+            self.take_damage(damage);
+        }
+        self.detach_colliders(collider_set, physics_data);
+
+        let controls = self.controls.clone();
+        self.update(&controls, collider_set, rigidbody_set, query_pipeline, atmosphere, physics_data, time as f32, delta_time);
     }
 
     fn toggle_debug_rendering(&mut self) {
@@ -123,4 +326,62 @@ impl PhysicsTick for PlanePhysicsLogic {
     fn debug_lines(&self) -> &[DebugPhysicsMessageType] {
         &self.renderizable_lines
     }
+
+    fn save_state(&self) -> HashMap<String, String> {
+        let state = PlaneLogicState {
+            controls: self.controls.clone(),
+            engine_power: self.engine.power,
+            engine: self.engine.state.clone(),
+            gear: self.wheel_manager.wheels.iter().map(|wheel| wheel.deployed).collect(),
+            wheel_loads: self.wheel_manager.wheels.iter().map(|wheel| wheel.load).collect(),
+            weight_off_wheels_time: self.wheel_manager.weight_off_wheels_time,
+            flaps: self.wing_manager.wings.iter().map(|wing| wing.flap_position).collect(),
+            leading_edges: self.wing_manager.wings.iter().map(|wing| wing.leading_edge_position).collect(),
+            alpha: self.wing_manager.wings.iter().map(|wing| wing.alpha).collect(),
+            stalled: self.wing_manager.wings.iter().map(|wing| wing.stalled).collect(),
+            speedbrake: self.speedbrake.as_ref().map(|speedbrake| speedbrake.position),
+            applied_fuel_mass: self.applied_fuel_mass,
+            health: self.damage.health(),
+        };
+
+        match ron::to_string(&state) {
+            Ok(state) => HashMap::from([(self.entity.clone(), state)]),
+            Err(error) => {
+                eprintln!("Couldn't save the state of '{}': {}", self.entity, error);
+                HashMap::new()
+            },
+        }
+    }
+
+    fn load_state(&mut self, states: &HashMap<String, String>) -> Result<(), String> {
+        let Some(state) = states.get(&self.entity) else {
+            return Ok(());
+        };
+        let state: PlaneLogicState = ron::from_str(state).map_err(|error| format!("Bad state for '{}': {}", self.entity, error))?;
+        if state.gear.len() != self.wheel_manager.wheels.len() || state.flaps.len() != self.wing_manager.wings.len() {
+            return Err(format!("'{}' was saved flying a different aircraft", self.entity));
+        }
+
+        self.controls = state.controls;
+        self.engine.power = state.engine_power;
+        self.engine.state = state.engine;
+        for ((wheel, deployed), load) in self.wheel_manager.wheels.iter_mut().zip(state.gear).zip(state.wheel_loads) {
+            wheel.deployed = deployed;
+            wheel.load = load;
+        }
+        self.wheel_manager.weight_off_wheels_time = state.weight_off_wheels_time;
+        for ((((wing, flap), leading_edge), alpha), stalled) in self.wing_manager.wings.iter_mut().zip(state.flaps).zip(state.leading_edges).zip(state.alpha).zip(state.stalled) {
+            wing.flap_position = flap;
+            wing.leading_edge_position = leading_edge;
+            wing.alpha = alpha;
+            wing.stalled = stalled;
+        }
+        if let (Some(speedbrake), Some(position)) = (self.speedbrake.as_mut(), state.speedbrake) {
+            speedbrake.position = position;
+        }
+        self.applied_fuel_mass = state.applied_fuel_mass;
+        self.damage.set_health(&state.health);
+        self.apply_damage();
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -1,7 +1,12 @@
+use serde::{Deserialize, Serialize};
+
 use crate::engine::input::input::InputSubsystem;
 use crate::engine::input::utils::to_axis;
 
-#[derive(Clone)]
+// Flap lever positions are up, half and full
+const FLAP_NOTCH: f32 = 0.5;
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct PlaneControls {
     pub throttle: f32,
     pub elevator: f32,
@@ -10,11 +15,24 @@ pub struct PlaneControls {
     pub trim_pitch: f32,
     pub trim_roll: f32,
     pub trim_yaw: f32,
+    pub limiter_override: bool, // held, lets a fly by wire plane past its G and AoA limits
+    pub gear_down: bool,
+    pub brake_left: f32,
+    pub brake_right: f32,
+    pub flaps: f32,         // lever, 0 up to 1 fully down
+    pub leading_edge: bool, // leading edge devices out regardless of their alpha schedule
+    pub speedbrake: bool,
+}
+
+impl Default for PlaneControls {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PlaneControls {
     pub fn new() -> Self {
-        Self { throttle: 0.0, elevator: 0.0, aileron: 0.0, rudder: 0.0, trim_pitch: 0.29, trim_roll: 0.0, trim_yaw: 0.0 }
+        Self { throttle: 0.0, elevator: 0.0, aileron: 0.0, rudder: 0.0, trim_pitch: 0.29, trim_roll: 0.0, trim_yaw: 0.0, limiter_override: false, gear_down: true, brake_left: 0.0, brake_right: 0.0, flaps: 0.0, leading_edge: false, speedbrake: false }
     }
 }
 
@@ -22,21 +40,58 @@ pub struct Plane {
     pub controls: PlaneControls,
 }
 
+impl Default for Plane {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Plane {
     pub fn new() -> Self {
-        Self { controls: PlaneControls { throttle: 0.0, elevator: 0.0, aileron: 0.0, rudder: 0.0, trim_pitch: 0.29, trim_roll: 0.0, trim_yaw: 0.0 } }
+        Self { controls: PlaneControls { throttle: 0.0, elevator: 0.0, aileron: 0.0, rudder: 0.0, trim_pitch: 0.29, trim_roll: 0.0, trim_yaw: 0.0, limiter_override: false, gear_down: true, brake_left: 0.0, brake_right: 0.0, flaps: 0.0, leading_edge: false, speedbrake: false } }
     }
 
     pub fn update(&mut self, delta_time: f32, input_subsystem: &InputSubsystem) {
         self.axis_logic(input_subsystem);
         self.throttle_logic(input_subsystem, delta_time);
         self.trim_logic(input_subsystem, delta_time);
+        self.gear_logic(input_subsystem);
+        self.devices_logic(input_subsystem);
     }
 
     pub fn axis_logic(&mut self, input_subsystem: &InputSubsystem) {
         self.controls.elevator = to_axis(input_subsystem.is_pressed("pitch_up"), input_subsystem.is_pressed("pitch_down"));
         self.controls.aileron = to_axis(input_subsystem.is_pressed("roll_left"), input_subsystem.is_pressed("roll_right"));
         self.controls.rudder = to_axis(input_subsystem.is_pressed("rudder_left"), input_subsystem.is_pressed("rudder_right"));
+        self.controls.limiter_override = input_subsystem.is_pressed("limiter_override");
+    }
+
+    pub fn gear_logic(&mut self, input_subsystem: &InputSubsystem) {
+        if input_subsystem.is_pressed("up_wheel") {
+            self.controls.gear_down = false;
+        }
+        if input_subsystem.is_pressed("down_wheel") {
+            self.controls.gear_down = true;
+        }
+
+        let both_brakes = input_subsystem.is_pressed("brakes");
+        self.controls.brake_left = if both_brakes || input_subsystem.is_pressed("brake_left") { 1.0 } else { 0.0 };
+        self.controls.brake_right = if both_brakes || input_subsystem.is_pressed("brake_right") { 1.0 } else { 0.0 };
+    }
+
+    pub fn devices_logic(&mut self, input_subsystem: &InputSubsystem) {
+        if input_subsystem.is_just_pressed("flaps_down") {
+            self.controls.flaps = (self.controls.flaps + FLAP_NOTCH).min(1.0);
+        }
+        if input_subsystem.is_just_pressed("flaps_up") {
+            self.controls.flaps = (self.controls.flaps - FLAP_NOTCH).max(0.0);
+        }
+        if input_subsystem.is_just_pressed("leading_edge") {
+            self.controls.leading_edge = !self.controls.leading_edge;
+        }
+        if input_subsystem.is_just_pressed("speedbrake") {
+            self.controls.speedbrake = !self.controls.speedbrake;
+        }
     }
 
     pub fn throttle_logic(&mut self, input_subsystem: &InputSubsystem, delta_time: f32) {
@@ -0,0 +1,782 @@
+use std::sync::mpsc::{self, Receiver, Sender};
+
+use nalgebra::{UnitQuaternion, Vector3};
+use rapier3d::prelude::{RigidBody, RigidBodyHandle};
+
+use crate::engine::physics::commands::{CommandQueue, EntityCommand};
+use crate::engine::physics::physics::DebugPhysicsMessageType;
+use crate::engine::physics::physics_handler::{Physics, PhysicsTick};
+use crate::engine::physics::physics_resources::{load_atmosphere_from_level, load_joints_from_level, load_physics_from_level, load_physics_settings_from_level};
+use crate::game::play::gun::definition::GunDefinition;
+use crate::game::play::gun::gun_logic::{Gun, GunLogic};
+use crate::game::play::missile::missile_logic::MissileLogic;
+use crate::game::play::plane::aircraft::AircraftDefinition;
+use crate::game::play::plane::physics_logic::PlanePhysicsLogic;
+use crate::game::play::plane::plane::PlaneControls;
+
+/// # Control Timeline
+///
+/// A scripted stand-in for the pilot: a list of keyframes, each one holding its
+/// controls until the next keyframe starts (no interpolation, just like a key press).
+///
+/// ## Example:
+/// ```ignore
+/// let timeline = ControlTimeline::new(cruise.clone())
+///     .at(2.0, PlaneControls { aileron: 1.0, ..cruise });
+/// ```
+#[derive(Clone)]
+pub struct ControlTimeline {
+    keyframes: Vec<(f32, PlaneControls)>,
+}
+
+impl ControlTimeline {
+    pub fn new(initial: PlaneControls) -> Self {
+        Self { keyframes: vec![(0.0, initial)] }
+    }
+
+    /// Adds a keyframe that starts at `time` (seconds since the simulation started).
+    pub fn at(mut self, time: f32, controls: PlaneControls) -> Self {
+        let index = self.keyframes.partition_point(|(start, _)| *start <= time);
+        self.keyframes.insert(index, (time, controls));
+        self
+    }
+
+    pub fn sample(&self, time: f32) -> &PlaneControls {
+        let index = self.keyframes.partition_point(|(start, _)| *start <= time);
+        &self.keyframes[index.saturating_sub(1)].1
+    }
+}
+
+/// The player's rigidbody state right after a physics step.
+#[derive(Debug, Clone)]
+pub struct TrajectorySample {
+    pub time: f32,
+    pub position: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub linvel: Vector3<f32>,
+    pub angvel: Vector3<f32>,
+}
+
+/// # Flight Simulation
+///
+/// Runs the same `Physics` world and `PlanePhysicsLogic` the game uses, but headless:
+/// no window, GPU, thread or wall clock. Every step is exactly the level's fixed step,
+/// so two runs with the same level, aircraft and timeline produce the same trajectory,
+/// which is what the flight model regression tests rely on. The plane's gun and missiles
+/// fly with the same `GunLogic` and `MissileLogic` too, ticked before the plane like in the game.
+pub struct FlightSimulation {
+    pub physics: Physics,
+    pub logic: PlanePhysicsLogic,
+    pub guns: GunLogic,
+    pub missiles: MissileLogic,
+    pub time: f32,
+    commands: Vec<EntityCommand>, // sent for the next step
+    debug_physics_tx: Sender<Vec<DebugPhysicsMessageType>>,
+    _debug_physics_rx: Receiver<Vec<DebugPhysicsMessageType>>,
+}
+
+impl FlightSimulation {
+    pub fn new(level_path: &str, definition: &AircraftDefinition) -> Self {
+        let mut physics = Physics::new();
+        physics.apply_settings(&load_physics_settings_from_level(level_path.to_string()));
+        physics.atmosphere = load_atmosphere_from_level(level_path.to_string());
+        load_physics_from_level(level_path.to_string(), &mut physics.collider_set, &mut physics.rigidbody_set, &mut physics.physics_elements)
+            .expect("The level physics failed to load");
+        physics.add_joints(&load_joints_from_level(level_path.to_string())).expect("The level joints failed to load");
+
+        // Nothing reads the debug lines here, the receiver is only kept so sends don't fail
+        let (debug_physics_tx, _debug_physics_rx) = mpsc::channel();
+
+        let guns = definition.gun.iter()
+            .map(|mount| Gun::new("player", GunDefinition::load(&mount.gun).expect("The gun failed to load"), mount.position, mount.ammo))
+            .collect();
+
+        Self {
+            physics,
+            logic: PlanePhysicsLogic::new(definition, "player"),
+            guns: GunLogic::new(guns),
+            missiles: MissileLogic::new(),
+            time: 0.0,
+            commands: Vec::new(),
+            debug_physics_tx,
+            _debug_physics_rx,
+        }
+    }
+
+    pub fn player(&self) -> &RigidBody {
+        &self.physics.rigidbody_set[self.player_handle()]
+    }
+
+    /// Moves the player somewhere else than where the level spawns it (e.g. on the runway).
+    pub fn place_player(&mut self, position: Vector3<f32>, linvel: Vector3<f32>) {
+        let handle = self.player_handle();
+        let rigidbody = &mut self.physics.rigidbody_set[handle];
+
+        rigidbody.set_translation(position, true);
+        rigidbody.set_linvel(linvel, true);
+        rigidbody.set_angvel(Vector3::zeros(), true);
+    }
+
+    /// Queues a command for the next step, like the main thread sending it to the physics thread.
+    pub fn send(&mut self, command: EntityCommand) {
+        self.commands.push(command);
+    }
+
+    /// Applies the plane forces for `controls` and advances the world by one fixed step.
+    pub fn step(&mut self, controls: &PlaneControls) -> TrajectorySample {
+        let (time, delta_time) = (self.physics.time, self.physics.integration_parameters.dt);
+
+        // Same path the physics thread takes: the controls are a command addressed to the plane
+        let mut commands = CommandQueue::new();
+        commands.push(EntityCommand::new(self.logic.entity.clone(), controls.clone()));
+        for command in self.commands.drain(..) {
+            commands.push(command);
+        }
+        self.physics.deliver_events(&mut commands);
+        self.guns.tick(&mut commands, &mut self.physics.collider_set, &mut self.physics.rigidbody_set, &self.physics.query_pipeline, &self.physics.atmosphere, &mut self.physics.physics_elements, &self.debug_physics_tx, time, delta_time);
+        self.missiles.tick(&mut commands, &mut self.physics.collider_set, &mut self.physics.rigidbody_set, &self.physics.query_pipeline, &self.physics.atmosphere, &mut self.physics.physics_elements, &self.debug_physics_tx, time, delta_time);
+        self.logic.tick(&mut commands, &mut self.physics.collider_set, &mut self.physics.rigidbody_set, &self.physics.query_pipeline, &self.physics.atmosphere, &mut self.physics.physics_elements, &self.debug_physics_tx, time, delta_time);
+        self.physics.step();
+        self.time += delta_time;
+
+        let player = self.player();
+        TrajectorySample {
+            time: self.time,
+            position: *player.translation(),
+            rotation: *player.rotation(),
+            linvel: *player.linvel(),
+            angvel: *player.angvel(),
+        }
+    }
+
+    /// Steps until `duration` seconds have been simulated, recording every step.
+    pub fn run(&mut self, timeline: &ControlTimeline, duration: f32) -> Vec<TrajectorySample> {
+        let steps = (duration / self.physics.integration_parameters.dt).round() as usize;
+
+        (0..steps).map(|_| {
+            let controls = timeline.sample(self.time).clone();
+            self.step(&controls)
+        }).collect()
+    }
+
+    fn player_handle(&self) -> RigidBodyHandle {
+        self.physics.physics_elements.get(&self.logic.entity)
+            .and_then(|data| data.as_ref())
+            .expect("The level has no physics enabled \"player\"")
+            .rigidbody_handle
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::physics::wind::{AirVolume, Turbulence, Wind};
+    use crate::engine::physics::events::PhysicsEventKind;
+    use crate::engine::physics::snapshot::PhysicsSnapshot;
+    use crate::engine::game_nodes::game_object::{self, ColliderType, GameObject, MetaData, RigidBodyData, Transform};
+    use nalgebra::Point3;
+    use crate::game::play::gun::gun_logic::{GunData, GunTrigger};
+    use crate::game::play::missile::definition::MissileDefinition;
+    use crate::game::play::missile::missile_logic::{MissileData, MissileLaunch};
+    use crate::game::play::plane::damage::Damage;
+
+    const LEVEL: &str = "assets/scenes/test_chamber";
+
+    // Throttle/trim the F-16 holds level flight with at the test chamber spawn (6000m, 200m/s)
+    fn cruise() -> PlaneControls {
+        PlaneControls { throttle: 0.55, trim_pitch: 0.333, gear_down: false, ..PlaneControls::new() }
+    }
+
+    // The flight model regressions fly the bare airframe, the control laws have their own tests
+    fn f16() -> FlightSimulation {
+        let mut definition = AircraftDefinition::load("f16").unwrap();
+        definition.fly_by_wire = None;
+        FlightSimulation::new(LEVEL, &definition)
+    }
+
+    fn f16_fly_by_wire() -> FlightSimulation {
+        let definition = AircraftDefinition::load("f16").unwrap();
+        FlightSimulation::new(LEVEL, &definition)
+    }
+
+    // A level object with only physics, the F-16 model just so it has one
+    fn physics_object(id: &str, position: Vector3<f32>, rigidbody: RigidBodyData, collider: ColliderType) -> GameObject {
+        GameObject {
+            id: id.to_string(),
+            model: "F16/f16.gltf".to_string(),
+            transform: Transform { position, rotation: UnitQuaternion::identity(), scale: Vector3::new(1.0, 1.0, 1.0) },
+            children: vec![],
+            metadata: MetaData {
+                physics: Some(game_object::Physics { rigidbody, colliders: vec![collider], material: None }),
+                cameras: None,
+                lighting: None,
+            },
+        }
+    }
+
+    fn body(is_static: bool, initial_velocity: Vector3<f32>, ccd: bool) -> RigidBodyData {
+        RigidBodyData {
+            is_static,
+            mass: 100.0,
+            center_of_mass: Vector3::zeros(),
+            initial_velocity,
+            initial_angular_velocity: Vector3::zeros(),
+            linear_damping: 0.0,
+            angular_damping: 0.0,
+            ccd,
+            gravity_scale: 1.0,
+        }
+    }
+
+    // Degrees between the nose and the flight path, in the plane of symmetry
+    fn body_alpha(sample: &TrajectorySample) -> f32 {
+        let local_velocity = sample.rotation.inverse() * sample.linvel;
+        (-local_velocity.y).atan2(local_velocity.z).to_degrees()
+    }
+
+    #[test]
+    fn timeline_holds_each_keyframe_until_the_next() {
+        let timeline = ControlTimeline::new(cruise())
+            .at(2.0, PlaneControls { aileron: 1.0, ..cruise() })
+            .at(1.0, PlaneControls { aileron: -1.0, ..cruise() });
+
+        assert_eq!(timeline.sample(0.5).aileron, 0.0);
+        assert_eq!(timeline.sample(1.0).aileron, -1.0);
+        assert_eq!(timeline.sample(1.9).aileron, -1.0);
+        assert_eq!(timeline.sample(30.0).aileron, 1.0);
+    }
+
+    #[test]
+    fn same_inputs_produce_the_same_trajectory() {
+        let timeline = ControlTimeline::new(cruise()).at(1.0, PlaneControls { aileron: 1.0, elevator: 0.5, ..cruise() });
+
+        let first = f16().run(&timeline, 3.0);
+        let second = f16().run(&timeline, 3.0);
+
+        assert_eq!(first.len(), second.len());
+        for (a, b) in first.iter().zip(&second) {
+            assert_eq!(a.position, b.position);
+            assert_eq!(a.rotation, b.rotation);
+        }
+    }
+
+    #[test]
+    fn level_cruise_holds_altitude() {
+        let mut simulation = f16();
+        let start_altitude = simulation.player().translation().y;
+
+        let trajectory = simulation.run(&ControlTimeline::new(cruise()), 15.0);
+
+        let max_deviation = trajectory.iter().map(|sample| (sample.position.y - start_altitude).abs()).fold(0.0, f32::max);
+        let final_speed = trajectory.last().unwrap().linvel.norm();
+
+        assert!(max_deviation < 100.0, "altitude drifted {}m from {}m", max_deviation, start_altitude);
+        assert!((150.0..300.0).contains(&final_speed), "cruise speed ended at {}m/s", final_speed);
+    }
+
+    #[test]
+    fn full_aileron_roll_rate_within_bounds() {
+        let timeline = ControlTimeline::new(cruise()).at(1.0, PlaneControls { aileron: 1.0, ..cruise() });
+        let trajectory = f16().run(&timeline, 3.0);
+
+        // Roll rate about the body's forward (z) axis, once the input has been held for a moment
+        let peak_roll_rate = trajectory.iter()
+            .filter(|sample| sample.time > 1.25)
+            .map(|sample| (sample.rotation.inverse() * sample.angvel).z.abs())
+            .fold(0.0, f32::max);
+
+        // The F-16 rolls at 240-280deg/s (4.2-4.9rad/s) at this speed, a model that leaves
+        // the window has changed the ailerons' lift or the roll damping
+        assert!((4.0..5.0).contains(&peak_roll_rate), "peak roll rate was {}rad/s", peak_roll_rate);
+    }
+
+    #[test]
+    fn takeoff_roll_distance_is_stable() {
+        let mut simulation = f16();
+        simulation.place_player(Vector3::new(0.0, 3.85, 0.0), Vector3::zeros());
+
+        let takeoff = PlaneControls { throttle: 1.0, elevator: 0.5, ..PlaneControls::new() };
+        let trajectory = simulation.run(&ControlTimeline::new(takeoff), 15.0);
+
+        let liftoff = trajectory.iter()
+            .find(|sample| sample.position.y > 3.85 + 1.5)
+            .expect("the plane never left the runway");
+
+        assert!((480.0..600.0).contains(&liftoff.position.z), "lifted off after {}m", liftoff.position.z);
+    }
+
+    #[test]
+    fn rolling_into_the_stall_drops_the_lower_wing() {
+        // Rolls with `aileron` while pulling into the stall, centering the stick once a wing lets
+        // go and pushing out of it. Returns the wing that stalled first, the alphas it stalled and
+        // reattached at and the roll rates while it was the only one stalled.
+        let stall = |aileron: f32| {
+            let mut simulation = f16();
+            let position = *simulation.player().translation();
+            simulation.place_player(position, Vector3::new(0.0, 0.0, 100.0));
+
+            let mut first_stall: Option<(String, f32)> = None;
+            let mut reattached = None;
+            let mut roll_rates = vec![];
+            for step in 0..(8.0 / Physics::FIXED_TIMESTEP) as usize {
+                let time = step as f32 * Physics::FIXED_TIMESTEP;
+                let controls = PlaneControls {
+                    throttle: 0.3,
+                    elevator: if time < 2.1 { 1.0 } else { -1.0 },
+                    aileron: if time > 1.5 && first_stall.is_none() { aileron } else { 0.0 },
+                    ..PlaneControls::new()
+                };
+                let sample = simulation.step(&controls);
+
+                let main_wings: Vec<_> = simulation.logic.wing_manager.wings.iter().filter(|wing| wing.is_roll_axis).collect();
+                match &first_stall {
+                    None => first_stall = main_wings.iter().find(|wing| wing.stalled).map(|wing| (wing.label.clone(), wing.alpha.abs())),
+                    Some((label, _)) => {
+                        let wing = main_wings.iter().find(|wing| &wing.label == label).unwrap();
+                        if main_wings.iter().filter(|wing| wing.stalled).count() == 1 && wing.stalled {
+                            roll_rates.push((sample.rotation.inverse() * sample.angvel).z);
+                        }
+                        if reattached.is_none() && !wing.stalled {
+                            reattached = Some(wing.alpha.abs());
+                        }
+                    },
+                }
+            }
+            let (label, stalled_at) = first_stall.expect("neither wing stalled");
+            (label, stalled_at, reattached.expect("the wing never reattached"), roll_rates)
+        };
+
+        // Nothing tells the wings apart but the roll: the one going down meets the air at a
+        // higher alpha, stalls first and keeps the plane rolling towards it
+        for (aileron, lower_wing) in [(0.5, "Right wing"), (-0.5, "Left wing")] {
+            let (label, stalled_at, reattached_at, roll_rates) = stall(aileron);
+            assert_eq!(label, lower_wing);
+            assert!(!roll_rates.is_empty(), "the {} never stalled alone", label);
+            assert!(roll_rates.iter().all(|rate| rate * aileron > 0.0), "the {} stalled but the roll reversed: {:?}", label, roll_rates);
+
+            // Once the flow separates it only reattaches a few degrees below where it let go
+            assert!(reattached_at < stalled_at - 2.5, "the {} stalled at {}deg and reattached at {}deg", label, stalled_at, reattached_at);
+        }
+    }
+
+    #[test]
+    fn fly_by_wire_tracks_the_commanded_roll_rate() {
+        let mut simulation = f16_fly_by_wire();
+        let max_roll_rate = simulation.logic.fly_by_wire.as_ref().unwrap().definition.max_roll_rate;
+
+        let timeline = ControlTimeline::new(cruise()).at(1.0, PlaneControls { aileron: 0.5, ..cruise() });
+        let trajectory = simulation.run(&timeline, 3.0);
+
+        for sample in trajectory.iter().filter(|sample| sample.time > 1.5) {
+            let roll_rate = (sample.rotation.inverse() * sample.angvel).z;
+            assert!((roll_rate - 0.5 * max_roll_rate).abs() < 0.1 * max_roll_rate, "rolling at {}rad/s at {}s", roll_rate, sample.time);
+        }
+    }
+
+    #[test]
+    fn fly_by_wire_limits_aoa_unless_overridden() {
+        let pull = PlaneControls { elevator: 1.0, ..cruise() };
+        let overridden = PlaneControls { limiter_override: true, ..pull.clone() };
+
+        let limited = f16_fly_by_wire().run(&ControlTimeline::new(cruise()).at(1.0, pull), 5.0);
+        let unlimited = f16_fly_by_wire().run(&ControlTimeline::new(cruise()).at(1.0, overridden), 5.0);
+
+        let aoa_limit = AircraftDefinition::load("f16").unwrap().fly_by_wire.unwrap().aoa_limit;
+        let max_alpha = |trajectory: &[TrajectorySample]| trajectory.iter().map(body_alpha).fold(f32::MIN, f32::max);
+
+        assert!(max_alpha(&limited) < aoa_limit + 1.0, "limited alpha reached {}°", max_alpha(&limited));
+        assert!(max_alpha(&unlimited) > aoa_limit + 10.0, "overridden alpha only reached {}°", max_alpha(&unlimited));
+    }
+
+    #[test]
+    fn fly_by_wire_limits_g() {
+        let mut definition = AircraftDefinition::load("f16").unwrap();
+        let fly_by_wire = definition.fly_by_wire.as_mut().unwrap();
+        // Low enough for the F-16 to reach it at the test chamber speed before the AoA limit
+        fly_by_wire.g_limit = 2.0;
+        fly_by_wire.aoa_limit = 25.0;
+        let mut simulation = FlightSimulation::new(LEVEL, &definition);
+
+        let timeline = ControlTimeline::new(cruise()).at(1.5, PlaneControls { elevator: 1.0, ..cruise() });
+        let mut max_load_factor = f32::MIN;
+        while simulation.time < 5.0 {
+            let controls = timeline.sample(simulation.time).clone();
+            simulation.step(&controls);
+            if simulation.time > 2.0 {
+                max_load_factor = max_load_factor.max(simulation.logic.fly_by_wire.as_ref().unwrap().state.load_factor);
+            }
+        }
+
+        assert!((1.7..2.3).contains(&max_load_factor), "pulled {}G", max_load_factor);
+    }
+    #[test]
+    fn gear_retracts_in_the_air_but_not_on_the_ground() {
+        let mut simulation = f16();
+        simulation.run(&ControlTimeline::new(cruise()), 2.0);
+        assert!(simulation.logic.wheel_manager.wheels.iter().all(|wheel| wheel.deployed > 0.0 && wheel.deployed < 1.0));
+        simulation.run(&ControlTimeline::new(cruise()), 3.0);
+        assert!(simulation.logic.wheel_manager.wheels.iter().all(|wheel| wheel.deployed == 0.0));
+
+        let mut simulation = f16();
+        simulation.place_player(Vector3::new(0.0, 3.85, 0.0), Vector3::zeros());
+        simulation.run(&ControlTimeline::new(PlaneControls { gear_down: false, ..PlaneControls::new() }), 5.0);
+        assert!(simulation.logic.wheel_manager.on_ground());
+        assert!(simulation.logic.wheel_manager.wheels.iter().all(|wheel| wheel.deployed == 1.0));
+    }
+
+    #[test]
+    fn hitting_the_ground_reports_a_collision_with_its_impact_speed() {
+        let collisions = |position: Vector3<f32>, linvel: Vector3<f32>, gear_down: bool| {
+            let mut simulation = f16();
+            simulation.place_player(position, linvel);
+            for wheel in simulation.logic.wheel_manager.wheels.iter_mut() {
+                wheel.deployed = if gear_down { 1.0 } else { 0.0 };
+            }
+
+            let mut events = Vec::new();
+            for _ in 0..240 {
+                simulation.step(&PlaneControls::new());
+                events.extend(simulation.physics.events.iter().filter(|event| event.kind == PhysicsEventKind::CollisionStarted).cloned());
+            }
+            events
+        };
+
+        // Sitting on the gear the airframe never touches anything
+        assert!(collisions(Vector3::new(0.0, 3.85, 0.0), Vector3::zeros(), true).is_empty());
+
+        // Gear up, nothing to soften the belly landing
+        let events = collisions(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -20.0, 0.0), false);
+        let impact = events.first().expect("no collision reported");
+        assert_eq!(impact.other("player"), Some("world"));
+        assert!((15.0..25.0).contains(&impact.impact_speed), "hit at {}m/s", impact.impact_speed);
+        assert!(impact.contact_point.is_some_and(|point| point.y.abs() < 0.5));
+    }
+
+    #[test]
+    fn losing_a_wing_rolls_the_plane_towards_it() {
+        let mut simulation = f16();
+        simulation.run(&ControlTimeline::new(cruise()), 3.0);
+        let mass = simulation.player().mass();
+        let left_wing = simulation.logic.damage.components.iter().find(|component| component.label == "Left wing").unwrap().clone();
+        simulation.logic.take_damage(&Damage { amount: left_wing.max_health, point: left_wing.position, radius: 0.0 });
+        assert_eq!(simulation.logic.damage.effectiveness(left_wing.kind), 0.0);
+
+        let trajectory = simulation.run(&ControlTimeline::new(cruise()), 2.0);
+
+        // It fell off with its collider and mass, give or take the collider's own and the fuel burnt meanwhile
+        let colliders = &simulation.physics.physics_elements["player"].as_ref().unwrap().collider_handles;
+        assert_eq!(colliders.iter().filter(|handle| simulation.physics.collider_set[**handle].is_enabled()).count(), colliders.len() - 1);
+        assert!((mass - simulation.player().mass() - left_wing.mass).abs() < 20.0, "lost {}kg", mass - simulation.player().mass());
+
+        // The left wing is on +x, with no lift it drops
+        let wingtip = trajectory.last().unwrap().rotation * Vector3::x();
+        assert!(wingtip.y < -0.3, "the left wing only dropped to {}", wingtip.y);
+    }
+
+    #[test]
+    fn a_missile_chases_down_a_crossing_target_it_sees_ahead() {
+        // Fired at whatever's ahead, returns what it went after, how close it got and whether it went off
+        let engagement = |bandit_position: Vector3<f32>| {
+            let mut simulation = f16();
+            simulation.run(&ControlTimeline::new(cruise()), 1.0);
+            let player = simulation.player();
+            let (position, rotation, linvel) = (*player.translation(), *player.rotation(), *player.linvel());
+
+            let bandit = RigidBodyData { gravity_scale: 0.0, ..body(false, Vector3::new(150.0, 0.0, 150.0), false) };
+            simulation.physics.spawn(&physics_object("bandit", position + bandit_position, bandit, ColliderType::Ball { radius: 5.0, position: (0.0, 0.0, 0.0) })).unwrap();
+
+            let definition = MissileDefinition::load("aim9").unwrap();
+            let pylon = definition.game_object("missile", position + rotation * Vector3::new(7.5, -0.6, 1.5), rotation, linvel);
+            simulation.physics.spawn(&pylon).unwrap();
+            simulation.send(EntityCommand::new("missile", MissileLaunch { missile: "aim9".to_string(), definition, launcher: "player".to_string(), pylon: Vector3::new(7.5, -0.6, 1.5), target: None }));
+
+            let (mut target, mut miss_distance, mut detonated) = (None, f32::MAX, false);
+            for _ in 0..(20.0 / simulation.physics.integration_parameters.dt) as usize {
+                simulation.step(&cruise());
+                let missile = simulation.physics.physics_elements["missile"].as_ref().unwrap();
+                let bandit = simulation.physics.physics_elements["bandit"].as_ref().unwrap();
+                let distance = (simulation.physics.rigidbody_set[missile.rigidbody_handle].translation() - simulation.physics.rigidbody_set[bandit.rigidbody_handle].translation()).norm();
+                miss_distance = miss_distance.min(distance);
+
+                let Some(data) = missile.metadata.get::<MissileData>("missile/status") else { continue };
+                target = target.or(data.target.clone());
+                if data.detonated {
+                    detonated = true;
+                    break;
+                }
+            }
+            (target, miss_distance, detonated)
+        };
+
+        // 2km out and crossing, well within the seeker's view
+        let (target, miss_distance, detonated) = engagement(Vector3::new(-200.0, 50.0, 2000.0));
+        assert_eq!(target.as_deref(), Some("bandit"));
+        assert!(detonated && miss_distance < 12.0, "missed by {}m", miss_distance);
+
+        // Behind the launcher it's never seen
+        let (target, _, _) = engagement(Vector3::new(0.0, 0.0, -1000.0));
+        assert_eq!(target, None);
+    }
+
+    #[test]
+    fn any_entity_can_fire_a_missile_from_where_the_physics_has_it() {
+        let mut simulation = f16();
+        simulation.run(&ControlTimeline::new(cruise()), 1.0);
+        let player = *simulation.player().translation();
+
+        // A bandit 1.5km behind the player, closing in
+        let bandit = RigidBodyData { gravity_scale: 0.0, ..body(false, Vector3::new(0.0, 0.0, 250.0), false) };
+        simulation.physics.spawn(&physics_object("bandit", player - Vector3::new(0.0, 0.0, 1500.0), bandit, ColliderType::Ball { radius: 5.0, position: (0.0, 0.0, 0.0) })).unwrap();
+        simulation.run(&ControlTimeline::new(cruise()), 0.5);
+
+        // Spawned nowhere near it, like a launcher that's drawn a few frames behind
+        let definition = MissileDefinition::load("aim9").unwrap();
+        simulation.physics.spawn(&definition.game_object("bandit_missile_0", player + Vector3::new(0.0, 500.0, 0.0), UnitQuaternion::identity(), Vector3::zeros())).unwrap();
+        let pylon = Vector3::new(2.0, -0.5, 0.0);
+        simulation.send(EntityCommand::new("bandit_missile_0", MissileLaunch { missile: "aim9".to_string(), definition, launcher: "bandit".to_string(), pylon, target: Some("player".to_string()) }));
+        simulation.step(&cruise());
+
+        let handle = |simulation: &FlightSimulation, entity: &str| simulation.physics.physics_elements[entity].as_ref().unwrap().rigidbody_handle;
+        let bandit = &simulation.physics.rigidbody_set[handle(&simulation, "bandit")];
+        let missile = &simulation.physics.rigidbody_set[handle(&simulation, "bandit_missile_0")];
+        assert!((missile.translation() - (bandit.translation() + pylon)).norm() < 5.0, "launched {}m from the pylon", (missile.translation() - bandit.translation() - pylon).norm());
+        assert!((missile.linvel() - bandit.linvel()).norm() < 30.0);
+
+        // And it goes after the player
+        for _ in 0..(15.0 / simulation.physics.integration_parameters.dt) as usize {
+            simulation.step(&cruise());
+            let missile = simulation.physics.physics_elements["bandit_missile_0"].as_ref().unwrap();
+            if missile.metadata.get::<MissileData>("missile/status").is_some_and(|data| data.detonated) {
+                break;
+            }
+        }
+        assert!(simulation.logic.damage.components.iter().any(|component| component.health < component.max_health), "the player wasn't hit");
+    }
+
+    #[test]
+    fn a_missile_weighs_what_it_has_left_to_burn() {
+        let mut simulation = f16();
+        simulation.run(&ControlTimeline::new(cruise()), 1.0);
+        let player = simulation.player();
+        let (position, rotation, linvel) = (*player.translation(), *player.rotation(), *player.linvel());
+
+        let definition = MissileDefinition::load("aim9").unwrap();
+        let (launch_mass, burnt_out_mass, burn_time) = (definition.launch_mass, definition.mass_at(f32::MAX), definition.motor.burn_time());
+        simulation.physics.spawn(&definition.game_object("missile", position + rotation * Vector3::new(7.5, -0.6, 1.5), rotation, linvel)).unwrap();
+        simulation.send(EntityCommand::new("missile", MissileLaunch { missile: "aim9".to_string(), definition, launcher: "player".to_string(), pylon: Vector3::new(7.5, -0.6, 1.5), target: None }));
+
+        let mass = |simulation: &FlightSimulation| simulation.physics.rigidbody_set[simulation.physics.physics_elements["missile"].as_ref().unwrap().rigidbody_handle].mass();
+        simulation.step(&cruise());
+        assert!((mass(&simulation) - launch_mass).abs() < 0.5, "weighed {}kg off the rail", mass(&simulation));
+
+        // Nothing on top of the motor's mass, not even the collider's
+        simulation.run(&ControlTimeline::new(cruise()), burn_time + 1.0);
+        assert!((mass(&simulation) - burnt_out_mass).abs() < 1e-3, "weighed {}kg burnt out, not {}kg", mass(&simulation), burnt_out_mass);
+    }
+
+    #[test]
+    fn gun_rounds_hit_what_the_nose_is_on() {
+        let mut simulation = f16();
+        let mount = AircraftDefinition::load("f16").unwrap().gun.unwrap();
+        simulation.run(&ControlTimeline::new(cruise()), 1.0);
+        let player = simulation.player();
+
+        // Keeping station 600m down the gun line
+        let bandit = RigidBodyData { gravity_scale: 0.0, ..body(false, *player.linvel(), false) };
+        let position = (player.position() * Point3::from(mount.position + Vector3::new(0.0, 0.0, 600.0))).coords;
+        simulation.physics.spawn(&physics_object("bandit", position, bandit, ColliderType::Ball { radius: 5.0, position: (0.0, 0.0, 0.0) })).unwrap();
+
+        // Short enough for the nose to stay on it
+        simulation.send(EntityCommand::new("player", GunTrigger { pulled: true }));
+        simulation.run(&ControlTimeline::new(cruise()), 0.25);
+        simulation.send(EntityCommand::new("player", GunTrigger { pulled: false }));
+        simulation.run(&ControlTimeline::new(cruise()), 1.0);
+
+        let Some(gun) = simulation.physics.physics_elements["player"].as_ref().unwrap().metadata.get::<GunData>("gun/status") else { panic!("no gun reported") };
+        // 6000 rounds a minute for a quarter of a second
+        let fired = mount.ammo - gun.ammo;
+        assert!((24..=26).contains(&fired), "fired {} rounds", fired);
+        assert!(!gun.firing);
+        assert!(gun.hits > fired / 2, "{} of {} rounds hit", gun.hits, fired);
+    }
+
+    #[test]
+    fn a_belly_landing_damages_the_parts_that_hit() {
+        let mut simulation = f16();
+        simulation.place_player(Vector3::new(0.0, 5.0, 0.0), Vector3::new(0.0, -20.0, 0.0));
+        for wheel in simulation.logic.wheel_manager.wheels.iter_mut() {
+            wheel.deployed = 0.0;
+        }
+        simulation.run(&ControlTimeline::new(PlaneControls::new()), 1.0);
+
+        assert!(simulation.logic.damage.components.iter().any(|component| component.health < component.max_health), "nothing was damaged");
+    }
+
+    #[test]
+    fn a_restored_snapshot_flies_exactly_like_the_saved_world() {
+        let path = std::env::temp_dir().join(format!("pankarta_snapshot_test_{}.ron", std::process::id())).to_string_lossy().to_string();
+        let maneuver = ControlTimeline::new(cruise()).at(1.0, PlaneControls { elevator: 0.4, aileron: -0.3, throttle: 1.0, ..cruise() });
+
+        let mut simulation = f16();
+        simulation.run(&maneuver, 2.0);
+        simulation.physics.snapshot(&simulation.logic).save(&path).unwrap();
+        let saved_time = simulation.time;
+        let expected = simulation.run(&maneuver, 2.0).pop().unwrap();
+
+        // A fresh world from the level, then the file put on top of it
+        let mut restored = f16();
+        let snapshot = PhysicsSnapshot::load(&path).unwrap();
+        restored.physics.restore(snapshot, &mut restored.logic);
+        restored.time = saved_time;
+        let replayed = restored.run(&maneuver, 2.0).pop().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(replayed.position, expected.position);
+        assert_eq!(replayed.rotation, expected.rotation);
+        assert_eq!(restored.logic.engine.state.fuel_mass, simulation.logic.engine.state.fuel_mass);
+    }
+
+    #[test]
+    fn brakes_stop_the_plane() {
+        let roll_out = |brake: f32| {
+            let mut simulation = f16();
+            simulation.place_player(Vector3::new(0.0, 3.85, 0.0), Vector3::new(0.0, 0.0, 30.0));
+            let controls = PlaneControls { brake_left: brake, brake_right: brake, ..PlaneControls::new() };
+            simulation.run(&ControlTimeline::new(controls), 10.0).last().unwrap().clone()
+        };
+
+        let braked = roll_out(1.0);
+        let unbraked = roll_out(0.0);
+
+        assert!(braked.linvel.norm() < 0.5, "still rolling at {}m/s", braked.linvel.norm());
+        assert!(braked.position.z < 150.0, "stopped after {}m", braked.position.z);
+        assert!(unbraked.position.z > 250.0, "rolled {}m without brakes", unbraked.position.z);
+    }
+
+    #[test]
+    fn nose_wheel_steering_and_differential_brakes_turn_the_plane() {
+        let heading_after = |controls: PlaneControls| {
+            let mut simulation = f16();
+            simulation.place_player(Vector3::new(0.0, 3.85, 0.0), Vector3::new(0.0, 0.0, 15.0));
+            let end = simulation.run(&ControlTimeline::new(controls), 5.0).last().unwrap().clone();
+            end.rotation * Vector3::z()
+        };
+
+        // Body x points left, a nose turning right ends up heading towards -x
+        assert!(heading_after(PlaneControls { rudder: 1.0, ..PlaneControls::new() }).x < -0.05);
+        assert!(heading_after(PlaneControls { rudder: -1.0, ..PlaneControls::new() }).x > 0.05);
+        assert!(heading_after(PlaneControls { brake_left: 1.0, ..PlaneControls::new() }).x > 0.05);
+        assert!(heading_after(PlaneControls { brake_right: 1.0, ..PlaneControls::new() }).x < -0.05);
+    }
+
+    #[test]
+    fn flaps_add_lift() {
+        let climb = |flaps: f32| {
+            let mut simulation = f16();
+            simulation.run(&ControlTimeline::new(PlaneControls { flaps, ..cruise() }), 6.0).last().unwrap().position.y
+        };
+
+        let clean = climb(0.0);
+        let flapped = climb(1.0);
+        assert!(flapped > clean + 20.0, "flaps down ended at {}m, clean at {}m", flapped, clean);
+    }
+
+    #[test]
+    fn speedbrake_slows_the_plane() {
+        // Per kg: kinetic plus potential, the plane trades some height back for speed once it slows
+        let energy_after = |speedbrake: bool| {
+            let mut simulation = f16();
+            let end = simulation.run(&ControlTimeline::new(PlaneControls { speedbrake, ..cruise() }), 10.0).last().unwrap().clone();
+            0.5 * end.linvel.norm_squared() + 9.81 * end.position.y
+        };
+
+        let clean = energy_after(false);
+        let braked = energy_after(true);
+        assert!(braked < clean - 1500.0, "speedbrake out {}J/kg, in {}J/kg", braked, clean);
+    }
+
+    #[test]
+    fn leading_edge_devices_follow_alpha_and_delay_the_stall() {
+        let mut simulation = f16_fly_by_wire();
+        simulation.run(&ControlTimeline::new(cruise()), 3.0);
+        assert!(simulation.logic.wing_manager.leading_edge_position() < 0.2);
+        simulation.run(&ControlTimeline::new(PlaneControls { elevator: 1.0, ..cruise() }), 3.0);
+        assert!(simulation.logic.wing_manager.leading_edge_position() > 0.3);
+
+        // Same pull as the wing drop test, slats out on the switch vs a wing without them
+        let first_stall = |leading_edge: bool| {
+            let mut definition = AircraftDefinition::load("f16").unwrap();
+            definition.fly_by_wire = None;
+            if !leading_edge {
+                definition.surfaces.iter_mut().for_each(|surface| surface.leading_edge = None);
+            }
+            let mut simulation = FlightSimulation::new(LEVEL, &definition);
+            let position = *simulation.player().translation();
+            simulation.place_player(position, Vector3::new(0.0, 0.0, 100.0));
+
+            let pull = PlaneControls { throttle: 0.3, elevator: 1.0, leading_edge, ..PlaneControls::new() };
+            for _ in 0..(10 * 120) {
+                let sample = simulation.step(&pull);
+                if simulation.logic.wing_manager.wings.iter().any(|wing| wing.is_roll_axis && wing.stalled) {
+                    return Some(sample.time);
+                }
+            }
+            None
+        };
+
+        let clean = first_stall(false).expect("the clean wing never stalled");
+        let slats = first_stall(true).expect("the wing with slats out never stalled");
+        assert!(slats > clean + 0.3, "stalled after {}s with slats, {}s without", slats, clean);
+    }
+
+    #[test]
+    fn moving_with_the_air_mass_flies_like_calm_air() {
+        let wind = Vector3::new(20.0, 0.0, -15.0);
+        let calm = f16().run(&ControlTimeline::new(cruise()), 10.0);
+
+        let mut windy = f16();
+        windy.physics.atmosphere.wind = Wind { velocity: wind, ..Default::default() };
+        let position = *windy.player().translation();
+        let linvel = *windy.player().linvel();
+        windy.place_player(position, linvel + wind);
+        let windy = windy.run(&ControlTimeline::new(cruise()), 10.0);
+
+        let (calm, windy) = (calm.last().unwrap(), windy.last().unwrap());
+        assert!((windy.position.y - calm.position.y).abs() < 0.5, "altitude {}m in the wind, {}m calm", windy.position.y, calm.position.y);
+        assert!((windy.position - calm.position - wind * windy.time).xz().magnitude() < 1.0);
+    }
+
+    #[test]
+    fn thermal_lifts_the_plane() {
+        let altitude_after = |wind: Wind| {
+            let mut simulation = f16();
+            simulation.physics.atmosphere.wind = wind;
+            simulation.run(&ControlTimeline::new(cruise()), 5.0).last().unwrap().position.y
+        };
+
+        let thermal = Wind {
+            volumes: vec![AirVolume::Thermal { center: Vector3::new(0.0, 0.0, 500.0), radius: 1000.0, top: 10_000.0, updraft: 10.0 }],
+            ..Default::default()
+        };
+
+        let calm = altitude_after(Wind::default());
+        let lifted = altitude_after(thermal);
+        assert!(lifted > calm + 20.0, "{}m in the thermal, {}m calm", lifted, calm);
+    }
+
+    #[test]
+    fn turbulence_bumps_the_plane_the_same_way_every_run() {
+        let bumpy = || {
+            let mut simulation = f16();
+            simulation.physics.atmosphere.wind = Wind { turbulence: Some(Turbulence { intensity: 3.0, scale: 200.0, seed: 1 }), ..Default::default() };
+            simulation.run(&ControlTimeline::new(cruise()), 5.0).last().unwrap().clone()
+        };
+
+        let calm = f16().run(&ControlTimeline::new(cruise()), 5.0).last().unwrap().clone();
+        let (first, second) = (bumpy(), bumpy());
+
+        assert_eq!(first.position, second.position);
+        assert_eq!(first.rotation, second.rotation);
+        assert!(first.rotation.angle_to(&calm.rotation) > 0.01);
+    }
+}
@@ -22,4 +22,16 @@ pub fn scale_6(value: Vector3<f32>, pos_x: f32, neg_x: f32, pos_y: f32, neg_y: f
     }
 
     result
-}
\ No newline at end of file
+}
+
+/// Moves a retractable part (gear leg, flap, speedbrake...) from `position` towards `target`,
+/// both from 0 to 1, a full travel taking `travel_time` seconds.
+pub fn move_towards(position: f32, target: f32, travel_time: f32, delta_time: f32) -> f32 {
+    let step = if travel_time > 0.0 { delta_time / travel_time } else { 1.0 };
+
+    if position < target {
+        (position + step).min(target)
+    } else {
+        (position - step).max(target)
+    }
+}
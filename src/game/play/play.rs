@@ -1,18 +1,27 @@
-use std::{collections::HashMap, f32::consts::PI, hash::Hash, time::{Duration, Instant}};
+use std::{collections::HashMap, f32::consts::PI, time::{Duration, Instant}};
 
-use glyphon::{cosmic_text::Align, Color, FontSystem};
+use glyphon::Color;
 use nalgebra::{vector, Point3, Quaternion, UnitQuaternion, Vector3};
 use rand::{rngs::ThreadRng, Rng};
-use rapier3d::prelude::RigidBody;
-use sdl2::{controller::GameController};
-use crate::{app::{App, AppState}, engine::audio::subtitles::Subtitle, engine::input::{input::InputSubsystem, utils::to_axis}, engine::physics::physics_handler::{MetadataType, PhysicsData, PhysicsTick, RenderMessage}, engine::primitive::manual_vertex::ManualVertex, engine::rendering::{camera::CameraRenderizable, ui::ui::Ui}, engine::scene_manager::scene::{FrameContext, Scene}, transform::Transform, engine::ui::{ui_node::{UiNode, UiNodeContent, Visibility}, ui_transform::UiTransform}, engine::utils::lerps::{lerp, lerp_quaternion}};
-use super::{event_handling::EventSystem, plane::{physics_logic::PlanePhysicsLogic, plane::Plane}};
+use sdl2::rect::Point;
+use crate::{app::App, engine::audio::subtitles::Subtitle, engine::input::input::InputSubsystem, engine::physics::physics_handler::{PhysicsTick, PhysicsTickGroup, RenderMessage}, engine::primitive::manual_vertex::ManualVertex, engine::rendering::{camera::CameraRenderizable, ui::ui::Ui}, engine::scene_manager::scene::{FrameContext, Scene}, transform::Transform, engine::ui::ui_node::{UiNode, UiNodeContent}, engine::utils::lerps::{lerp, lerp_quaternion}};
+use super::{event_handling::EventSystem, gun::{ballistics, definition::GunDefinition, gun_logic::{Gun, GunData, GunLogic, GunTrigger}}, missile::{launcher::MissileLauncher, missile_logic::MissileLogic}, plane::{aircraft::{AircraftDefinition, GunMountDefinition}, engine::EngineState, fly_by_wire::FlyByWireState, metadata::{ColliderDebugData, ComponentDamageData, DeviceData, SuspensionDebugData, WingDebugData, WingStallData}, physics::wheels::wheel::WheelData, physics_logic::PlanePhysicsLogic, plane::Plane}};
 use std::sync::mpsc::Sender;
-use crate::game::play::plane::plane::PlaneControls;
-use crate::resources::{apply_environment, load_level};
+use crate::engine::physics::commands::EntityCommand;
+use crate::engine::physics::physics_handler::PhysicsCommand;
+use crate::engine::physics::events::{PhysicsEvent, PhysicsEventKind};
+use crate::resources::{apply_environment, despawn_game_object, load_level, spawn_game_object, teleport_game_object};
+use crate::engine::physics::atmosphere::Atmosphere;
+use crate::engine::physics::physics_resources::load_atmosphere_from_level;
 use crate::engine::rendering::enviroment::environment::{Environment, SkyboxFaces};
-use crate::engine::tooling::debug_console;
-use crate::debug_text;
+
+// Key of the player's plane in the level, both for its physics body and the commands sent to it
+const PLAYER: &str = "player";
+// Closing speed (m/s) above which the airframe itself (not the gear) hitting something is a crash
+const CRASH_SPEED: f32 = 10.0;
+// How far off the nose (degrees) and how far away (m) a bandit can be locked
+const LOCK_CONE: f32 = 30.0;
+const LOCK_RANGE: f32 = 10000.0;
 
 // Add a way of setting timing that can be agnostic to real time (or that will not be affected by the player pausing)
 pub enum CameraState {
@@ -26,6 +35,7 @@ pub enum CameraState {
 pub struct Bandit {
     tag: String,
     locked: bool,
+    linvel: Vector3<f32>, // last reported by the physics, the pipper leads it
 }
 
 pub struct CameraData {
@@ -69,9 +79,17 @@ pub struct PlaneSystems {
     stall: bool,
     pub flight_data: FlightData,
     pub afterburner_value: f32,
+    pub engine: EngineState,
+    pub gear_position: f32, // 0 up, 1 down, averaged over the legs
     pub base_rotations: BaseRotations,
-    pub flap_ratio: f32,
+    pub flap_ratio: f32,         // 0 up, 1 fully down
+    pub speedbrake_ratio: f32,   // 0 closed, 1 fully open
     pub previous_velocity: Option<Vector3<f32>>,
+    pub crashed: bool,
+    pub damage: Vec<ComponentDamageData>, // health of the parts that can be damaged
+    pub gun: Option<GunData>,
+    pub fly_by_wire: Option<FlyByWireState>, // None for aircraft without a flight computer
+    pub trigger_pulled: bool, // last GunTrigger sent, it's only sent again once it changes
 }
 
 pub struct GameLogic { // here we define the data we use on our script
@@ -85,6 +103,9 @@ pub struct GameLogic { // here we define the data we use on our script
     rng: ThreadRng,
     pub game_time: f64,
     pub plane: Plane,
+    pub launcher: MissileLauncher, // the player's missiles
+    pub gun: Option<(GunMountDefinition, GunDefinition)>, // the player's, for the pipper
+    pub atmosphere: Atmosphere, // the level's, the pipper works out the drag on the rounds with it
 } 
 
 impl GameLogic {
@@ -209,30 +230,42 @@ impl GameLogic {
         let fellow = Bandit {
             tag: "fellow_aviator".to_owned(),
             locked: true,
+            linvel: Vector3::zeros(),
         };
 
         let tower = Bandit {
             tag: "tower".to_owned(),
             locked: false,
+            linvel: Vector3::zeros(),
         };
 
         let tower2 = Bandit {
             tag: "tower2".to_owned(),
             locked: false,
+            linvel: Vector3::zeros(),
         };
 
         let crane = Bandit {
             tag: "crane".to_owned(),
             locked: false,
+            linvel: Vector3::zeros(),
         };
 
         let plane_systems = PlaneSystems {
             bandits: vec![tower, tower2, crane, fellow],
             stall: false,
             afterburner_value: 0.0,
+            engine: EngineState::default(),
+            gear_position: 1.0,
             base_rotations: BaseRotations { left_aleron: None, right_aleron: None },
             flap_ratio: 0.0,
+            speedbrake_ratio: 0.0,
             previous_velocity: None,
+            crashed: false,
+            damage: Vec::new(),
+            gun: None,
+            fly_by_wire: None,
+            trigger_pulled: false,
             flight_data: FlightData { altimeter: 0.0, speedometer: 0.0, g_meter: 1.0 }
         };
 
@@ -241,6 +274,7 @@ impl GameLogic {
         let mut blinking_alerts: HashMap<String, BlinkingAlert> = HashMap::new();
         blinking_alerts.insert("altitude".to_owned(), BlinkingAlert { alert_state: false, time_alert: 0.0 });
         blinking_alerts.insert("stall".to_owned(), BlinkingAlert { alert_state: false, time_alert: 0.0 });
+        blinking_alerts.insert("engine".to_owned(), BlinkingAlert { alert_state: false, time_alert: 0.0 });
 
         let gravity = vector![0.0, -9.81, 0.0];
 
@@ -262,6 +296,22 @@ impl GameLogic {
             nz: "skybox/nz.png".to_owned(),
         }));
 
+        let (pylons, gun) = match AircraftDefinition::load(&app.selected_aircraft) {
+            Ok(definition) => (definition.pylons, definition.gun),
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                (Vec::new(), None)
+            },
+        };
+        let gun = gun.and_then(|mount| match GunDefinition::load(&mount.gun) {
+            Ok(definition) => Some((mount, definition)),
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                None
+            },
+        });
+        let atmosphere = app.scene_openned.clone().map(load_atmosphere_from_level).unwrap_or_default();
+
         Self {
             camera_data,
             blinking_alerts,
@@ -273,11 +323,14 @@ impl GameLogic {
             subtitle_data,
             game_time: 0.0,
             plane: Plane::new(),
+            launcher: MissileLauncher::new(PLAYER, &pylons),
+            gun,
+            atmosphere,
         }
     }
 
     // this is called every frame
-    pub fn update(&mut self, app: &mut App, input_subsystem: &InputSubsystem, plane_control_tx: Option<&Sender<PlaneControls>>, physics_data: &HashMap<String, RenderMessage>) {
+    pub fn update(&mut self, app: &mut App, input_subsystem: &InputSubsystem, command_tx: Option<&Sender<EntityCommand>>, physics_data: &HashMap<String, RenderMessage>, physics_events: &[PhysicsEvent]) {
         self.game_time += app.time.delta_time as f64;
 
         if input_subsystem.is_just_pressed("test") {
@@ -286,13 +339,24 @@ impl GameLogic {
 
         // Debug console output (press F2 to show/hide)
         self.plane.update(app.time.delta_time, input_subsystem);
-        if let Some(plane_control_tx) = plane_control_tx {
-            let _ = plane_control_tx.send(self.plane.controls.clone());
+        if let Some(command_tx) = command_tx {
+            let _ = command_tx.send(EntityCommand::state(PLAYER, self.plane.controls.clone()));
+            let pulled = input_subsystem.is_pressed("fire_gun") && !self.plane_systems.crashed;
+            if pulled != self.plane_systems.trigger_pulled && command_tx.send(EntityCommand::state(PLAYER, GunTrigger { pulled })).is_ok() {
+                self.plane_systems.trigger_pulled = pulled;
+            }
         }
 
+        let damage = self.plane_systems.damage.clone();
         self.plane_movement(app, app.time.delta_time, physics_data);
+        // The parts that were still there last frame and aren't anymore
+        for component in self.plane_systems.damage.iter().filter(|component| component.health <= 0.0) {
+            if damage.iter().any(|previous| previous.label == component.label && previous.health > 0.0) {
+                self.subtitle_data.add_text(&format!("LOST {}", component.label.to_uppercase()), 3000, app);
+            }
+        }
         if let Some(event_system) = &mut self.event_system {
-            event_system.handle_events(self.game_time, app, &mut self.subtitle_data);
+            event_system.handle_events(self.game_time, physics_events, app, &mut self.subtitle_data);
         }
         self.subtitle_data.update(app);
         self.camera_control(app, app.time.delta_time, input_subsystem);
@@ -307,6 +371,29 @@ impl GameLogic {
         if let Some(data) = physics_data_renderizable {
             self.plane_systems.flight_data.speedometer = data.linvel.magnitude() * 1.94384;
 
+            if let Some(engine) = data.metadata.get::<EngineState>("plane/engine") {
+                self.plane_systems.engine = engine.clone();
+            }
+
+            if let Some(wings) = data.metadata.get::<Vec<WingStallData>>("plane/stall") {
+                self.plane_systems.stall = wings.iter().any(|wing| wing.stalled);
+            }
+
+            if let Some(devices) = data.metadata.get::<DeviceData>("plane/devices") {
+                self.plane_systems.flap_ratio = devices.flaps;
+                self.plane_systems.speedbrake_ratio = devices.speedbrake;
+            }
+
+            if let Some(components) = data.metadata.get::<Vec<ComponentDamageData>>("plane/damage") {
+                self.plane_systems.damage = components.clone();
+            }
+
+            if let Some(gun) = data.metadata.get::<GunData>("gun/status") {
+                self.plane_systems.gun = Some(gun.clone());
+            }
+
+            self.plane_systems.fly_by_wire = data.metadata.get::<FlyByWireState>("plane/fly_by_wire").cloned();
+
             // G-meter: project felt acceleration onto the plane's local up axis
             match &self.plane_systems.previous_velocity {
                 Some(prev_vel) if *prev_vel != data.linvel => {
@@ -328,24 +415,20 @@ impl GameLogic {
 
         // elevators
         if let Some(meshes) = plane_model.model.mesh_lists.get_mut("opaque") {
-            match physics_data_renderizable {
-                Some(physics_data_renderizable) => {
-                    if let Some(wheels) = physics_data_renderizable.metadata.get("wheels") {
-                        match &wheels {
-                            MetadataType::Wheels(wheels) => {
-                                for (index, wheel) in wheels.iter() {
-                                    if let Some(wheel_mesh) = &mut meshes.get_mut(index.as_str()) {
-                                        let local_pos = &wheel.local_position;
-                                        wheel_mesh.transform.position = Vector3::new(local_pos.x / plane.instance.transform.scale.x, local_pos.y / plane.instance.transform.scale.y, local_pos.z / plane.instance.transform.scale.z);
-                                        wheel_mesh.update_transform(&app.renderer.queue);
-                                    }
-                                }
-                            }
-                            _ => {}
+            if let Some(physics_data_renderizable) = physics_data_renderizable {
+                if let Some(wheels) = physics_data_renderizable.metadata.get::<HashMap<String, WheelData>>("plane/wheels") {
+                    if !wheels.is_empty() {
+                        self.plane_systems.gear_position = wheels.values().map(|wheel| wheel.deployed).sum::<f32>() / wheels.len() as f32;
+                    }
+
+                    for (index, wheel) in wheels.iter() {
+                        if let Some(wheel_mesh) = &mut meshes.get_mut(index.as_str()) {
+                            let local_pos = &wheel.local_position;
+                            wheel_mesh.transform.position = Vector3::new(local_pos.x / plane.instance.transform.scale.x, local_pos.y / plane.instance.transform.scale.y, local_pos.z / plane.instance.transform.scale.z);
+                            wheel_mesh.update_transform(&app.renderer.queue);
                         }
                     }
-                },
-                None => {}
+                }
             }
 
             if let Some(elevator) = meshes.get_mut("left_elevator") {
@@ -375,10 +458,13 @@ impl GameLogic {
             r_wing.change_transform(&app.renderer.queue, r_wing_transform);
             */
 
+            // The flaperons droop together with the flaps the physics is flying with, on top of the roll
+            // (a positive rotation about x lifts the trailing edge)
+            let flaperon_droop = -0.35 * self.plane_systems.flap_ratio;
             if let Some(aleron) = meshes.get_mut("left_aleron") {
                 match self.plane_systems.base_rotations.left_aleron {
                     Some(base_rotation) => {
-                        let dependent = UnitQuaternion::from_quaternion(base_rotation.clone()) * UnitQuaternion::from_axis_angle(&Vector3::x_axis() ,0.5 * -self.plane.controls.aileron);
+                        let dependent = UnitQuaternion::from_quaternion(base_rotation) * UnitQuaternion::from_axis_angle(&Vector3::x_axis() ,0.5 * -self.plane.controls.aileron + flaperon_droop);
                         let aleron_rotation = lerp_quaternion(aleron.transform.rotation,  *dependent, app.time.delta_time * 7.0);
                         let aleron_transform = Transform::new(aleron.transform.position, aleron_rotation, aleron.transform.scale);
                         aleron.change_transform(&app.renderer.queue, aleron_transform);
@@ -392,7 +478,7 @@ impl GameLogic {
             if let Some(aleron) = meshes.get_mut("right_aleron") {
                 match self.plane_systems.base_rotations.right_aleron {
                     Some(base_rotation) => {
-                        let dependent = UnitQuaternion::from_quaternion(base_rotation.clone()) * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 0.5 * self.plane.controls.aileron);
+                        let dependent = UnitQuaternion::from_quaternion(base_rotation) * UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 0.5 * self.plane.controls.aileron + flaperon_droop);
                         let aleron_rotation = lerp_quaternion(aleron.transform.rotation,  *dependent, app.time.delta_time * 7.0);
                         let aleron_transform = Transform::new(aleron.transform.position, aleron_rotation, aleron.transform.scale);
                         aleron.change_transform(&app.renderer.queue, aleron_transform);
@@ -422,8 +508,10 @@ impl GameLogic {
 
         if let Some(meshes) = plane_model.model.mesh_lists.get_mut("transparent") {
             if let Some(afterburner) = meshes.get_mut("Afterburner") {
-                if self.plane.controls.throttle > 0.0 {
-                    self.plane_systems.afterburner_value =  lerp(self.plane_systems.afterburner_value, self.plane.controls.throttle + self.rng.gen_range(-0.5..0.5), app.time.delta_time * 20.0);
+                // The flame follows the afterburner stages the physics is actually burning, not the throttle lever
+                let afterburner_stage = self.plane_systems.engine.afterburner;
+                if afterburner_stage > 0.01 {
+                    self.plane_systems.afterburner_value =  lerp(self.plane_systems.afterburner_value, afterburner_stage + self.rng.gen_range(-0.5..0.5), app.time.delta_time * 20.0);
                 } else {
                     self.plane_systems.afterburner_value = lerp(self.plane_systems.afterburner_value, 0.0, delta_time * 2.0)
                 }
@@ -432,10 +520,24 @@ impl GameLogic {
             } 
         }
 
+        // Destroyed parts have fallen off, the ones a snapshot brought back are put back on
+        for component in &self.plane_systems.damage {
+            for mesh_name in &component.meshes {
+                for meshes in plane_model.model.mesh_lists.values_mut() {
+                    if let Some(mesh) = meshes.get_mut(mesh_name.as_str()) {
+                        let scale = if component.health <= 0.0 { Vector3::zeros() } else { mesh.base_transform.scale };
+                        if mesh.transform.scale != scale && (component.health <= 0.0 || mesh.transform.scale == Vector3::zeros()) {
+                            mesh.change_transform(&app.renderer.queue, Transform::new(mesh.transform.position, mesh.transform.rotation, scale));
+                        }
+                    }
+                }
+            }
+        }
+
         // Render collider debug wireframes using the model's visual transform
         if app.render_physics.visible {
             if let Some(physics_data_renderizable) = physics_data_renderizable {
-                if let Some(MetadataType::Colliders(colliders)) = physics_data_renderizable.metadata.get("colliders") {
+                if let Some(colliders) = physics_data_renderizable.metadata.get::<Vec<ColliderDebugData>>("plane/colliders") {
                     let plane = app.renderizable_instances.get("player").unwrap();
                     let pos = &plane.instance.transform.position;
                     let rot = &plane.instance.transform.rotation;
@@ -471,7 +573,7 @@ impl GameLogic {
                 }
 
                 // Render wing debug lines (axes + lift force) using visual transform
-                if let Some(MetadataType::Wings(wings)) = physics_data_renderizable.metadata.get("wings") {
+                if let Some(wings) = physics_data_renderizable.metadata.get::<Vec<WingDebugData>>("plane/wings") {
                     let plane = app.renderizable_instances.get("player").unwrap();
                     let pos = &plane.instance.transform.position;
                     let rot = &plane.instance.transform.rotation;
@@ -507,7 +609,7 @@ impl GameLogic {
                 }
 
                 // Render suspension debug lines using visual transform
-                if let Some(MetadataType::Suspensions(suspensions)) = physics_data_renderizable.metadata.get("suspensions") {
+                if let Some(suspensions) = physics_data_renderizable.metadata.get::<Vec<SuspensionDebugData>>("plane/suspensions") {
                     let plane = app.renderizable_instances.get("player").unwrap();
                     let pos = &plane.instance.transform.position;
                     let rot = &plane.instance.transform.rotation;
@@ -536,7 +638,7 @@ impl GameLogic {
                     (target_pos, look_at, player.instance.transform.rotation * *Vector3::y_axis())
                 },
                 CameraState::Cockpit => {
-                    let (base_pos, default_fov) = if let Some(cameras) = &player.instance.metadata.cameras {
+                    let (base_pos, _default_fov) = if let Some(cameras) = &player.instance.metadata.cameras {
                         if let Some(cam) = cameras.get("cockpit") {
                             (player.instance.transform.rotation * cam.position, cam.fov)
                         } else {
@@ -631,7 +733,7 @@ impl GameLogic {
                     app.camera.projection.fovy = self.camera_data.free_current_fov;
 
                     let sens = input_subsystem.mouse.get_sensitivity();
-                    self.camera_data.free_yaw = self.camera_data.free_yaw - input_subsystem.mouse.get_rel_x() as f32 * sens.0;
+                    self.camera_data.free_yaw -= input_subsystem.mouse.get_rel_x() as f32 * sens.0;
                     self.camera_data.free_pitch = (self.camera_data.free_pitch + input_subsystem.mouse.get_rel_y() as f32 * sens.1).clamp(-89.0, 89.0);
 
                     let rotation_y = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), self.camera_data.free_yaw.to_radians());
@@ -708,18 +810,6 @@ impl GameLogic {
         }
     }
 
-    // i do this so my "ui_controller" is smaller
-    fn update_text_label(&mut self, ui_tagged_elements: &mut HashMap<String, UiNode>, tag: &str, text: &str, font_system: &mut FontSystem) {
-        if let Some(node) = ui_tagged_elements.get_mut(tag) {
-            match &mut node.content {
-                UiNodeContent::Text(label) => {
-                    label.set_text(font_system, &text, true);
-                },
-                _ => {}
-            }
-        }
-    }
-
     fn format_duration(seconds: f64) -> String {
         let duration = Duration::from_secs_f64(seconds);
 
@@ -747,7 +837,77 @@ impl GameLogic {
             }
 
             if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/power").and_then(|n| n.as_label_mut()) {
-                label.set_text(&mut app.ui.text.font_system, &format!("Power: {}%", (self.plane.controls.throttle * 100.0).round()), true);
+                let engine = &self.plane_systems.engine;
+                let afterburner = if engine.afterburner > 0.01 { " AB" } else { "" };
+                label.set_text(&mut app.ui.text.font_system, &format!("Power: {}% RPM: {:.0}%{}", (self.plane.controls.throttle * 100.0).round(), engine.rpm * 100.0, afterburner), true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/fuel").and_then(|n| n.as_label_mut()) {
+                let fuel_mass = self.plane_systems.engine.fuel_mass;
+                let text = if fuel_mass.is_finite() { format!("Fuel: {:.0}kg", fuel_mass) } else { "Fuel: --".to_owned() };
+                label.set_text(&mut app.ui.text.font_system, &text, true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/gear").and_then(|n| n.as_label_mut()) {
+                let gear = match self.plane_systems.gear_position {
+                    position if position >= 1.0 => "DOWN",
+                    position if position <= 0.0 => "UP",
+                    _ => "TRANSIT",
+                };
+                let brakes = if self.plane.controls.brake_left > 0.0 || self.plane.controls.brake_right > 0.0 { " BRAKES" } else { "" };
+                label.set_text(&mut app.ui.text.font_system, &format!("Gear: {}{}", gear, brakes), true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/flaps").and_then(|n| n.as_label_mut()) {
+                let speedbrake = if self.plane_systems.speedbrake_ratio > 0.0 { " SPD BRK" } else { "" };
+                label.set_text(&mut app.ui.text.font_system, &format!("Flaps: {:.0}%{}", self.plane_systems.flap_ratio * 100.0, speedbrake), true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/damage").and_then(|n| n.as_label_mut()) {
+                let damaged: Vec<String> = self.plane_systems.damage.iter()
+                    .filter(|component| component.health < 1.0)
+                    .map(|component| format!("{} {:.0}%", component.label, component.health * 100.0))
+                    .collect();
+                let text = if damaged.is_empty() { "Damage: none".to_owned() } else { format!("Damage: {}", damaged.join(", ")) };
+                label.set_text(&mut app.ui.text.font_system, &text, true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/missiles").and_then(|n| n.as_label_mut()) {
+                let next = self.launcher.pylons.iter().find(|pylon| pylon.loaded).map_or("-", |pylon| pylon.definition.name.as_str());
+                label.set_text(&mut app.ui.text.font_system, &format!("Missiles: {} ({})", self.launcher.loaded(), next), true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/gun").and_then(|n| n.as_label_mut()) {
+                let text = match &self.plane_systems.gun {
+                    Some(gun) => format!("Gun: {}{}", gun.ammo, if gun.firing { " FIRING" } else { "" }),
+                    None => "Gun: -".to_owned(),
+                };
+                label.set_text(&mut app.ui.text.font_system, &text, true);
+            }
+
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/fly_by_wire").and_then(|n| n.as_label_mut()) {
+                let text = match &self.plane_systems.fly_by_wire {
+                    Some(state) if state.engaged => format!(
+                        "FBW: {:.1}G of {:.1}G{}{}",
+                        state.load_factor,
+                        state.commanded_g,
+                        if state.limiting { " LIMIT" } else { "" },
+                        if state.limiters_overridden { " OVRD" } else { "" },
+                    ),
+                    Some(_) => "FBW: STBY".to_owned(),
+                    None => "FBW: -".to_owned(),
+                };
+                label.set_text(&mut app.ui.text.font_system, &text, true);
+            }
+
+            let target = self.locked_bandit().and_then(|bandit| {
+                let player = app.renderizable_instances.get(PLAYER)?;
+                let bandit_instance = app.renderizable_instances.get(&bandit.tag)?;
+                let distance = (bandit_instance.instance.transform.position - player.instance.transform.position).norm();
+                Some(format!("Target: {} {:.1}km", bandit.tag, distance / 1000.0))
+            });
+            if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "data_box/target").and_then(|n| n.as_label_mut()) {
+                label.set_text(&mut app.ui.text.font_system, target.as_deref().unwrap_or("Target: none"), true);
             }
 
             if let Some(label) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "altitude").and_then(|n| n.as_label_mut()) {
@@ -762,11 +922,11 @@ impl GameLogic {
             
             let text_compass = if rotation >= 355.0 || rotation <= 5.0 {
                 "N".to_owned()
-            } else if rotation >= 175.0 && rotation <= 185.0{
+            } else if (175.0..=185.0).contains(&rotation){
                 "S".to_owned()
-            } else if rotation >= 85.0 && rotation <= 95.0 {
+            } else if (85.0..=95.0).contains(&rotation) {
                 "E".to_owned()
-            } else if rotation >= 265.0 && rotation <= 275.0 {
+            } else if (265.0..=275.0).contains(&rotation) {
                 "O".to_owned()
             } else {
                 rotation.round().to_string() + "°"
@@ -782,30 +942,16 @@ impl GameLogic {
                     if let Some(player) = app.renderizable_instances.get("player") {
                         let pos = player.instance.transform.position;
                         let vel_point = Point3::from(pos + velocity.normalize() * 100.0);
-                        if let Some(screen_pos) = app.camera.world_to_screen(vel_point, app.renderer.config.width, app.renderer.config.height) {
-                            if let Some(marker) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "velocity_marker") {
-                                marker.transform.x = screen_pos.x as f32 - marker.transform.width / 2.0;
-                                marker.transform.y = screen_pos.y as f32 - marker.transform.height / 2.0;
-                                marker.transform.rect.left = marker.transform.x;
-                                marker.transform.rect.top = marker.transform.y;
-                                marker.transform.rect.right = marker.transform.x + marker.transform.width;
-                                marker.transform.rect.bottom = marker.transform.y + marker.transform.height;
-                                if let UiNodeContent::Text(label) = &mut marker.content {
-                                    label.color = Color::rgba(0, 255, 75, 255);
-                                }
-                            }
-                        } else {
-                            // Off screen — hide marker
-                            if let Some(marker) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "velocity_marker") {
-                                if let UiNodeContent::Text(label) = &mut marker.content {
-                                    label.color = Color::rgba(0, 255, 75, 0);
-                                }
-                            }
-                        }
+                        let screen_pos = app.camera.world_to_screen(vel_point, app.renderer.config.width, app.renderer.config.height);
+                        Self::place_marker(app, "velocity_marker", screen_pos);
                     }
                 }
             }
 
+            // Lead computing pipper on the locked bandit, while it's within the gun's reach
+            let pipper = self.pipper(app);
+            Self::place_marker(app, "pipper", pipper);
+
             if let Some(altitude_alert) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "altitude_alert") {
                 self.blinking_alert("altitude".to_owned(), altitude_alert, self.plane_systems.flight_data.altimeter < 1000.0, delta_time);
             }
@@ -814,17 +960,128 @@ impl GameLogic {
                 self.blinking_alert("stall".to_owned(), stall_alert, self.plane_systems.stall, delta_time);
             }
 
+            if let Some(engine_alert) = Ui::get_ui_node(&mut app.ui.renderizable_elements, "engine_alert") {
+                self.blinking_alert("engine".to_owned(), engine_alert, self.plane_systems.engine.flamed_out, delta_time);
+            }
+
             app.ui.has_changed = true; // Mark UI as changed so it gets processed
             app.throttling.last_ui_update = Instant::now();
         }
     }
 
+    // The wheels are raycasts, so any of the plane's colliders touching something hard enough
+    // means the airframe itself hit it
+    fn handle_collisions(&mut self, app: &mut App, physics_events: &[PhysicsEvent]) {
+        for event in physics_events {
+            if self.plane_systems.crashed || event.kind != PhysicsEventKind::CollisionStarted || !event.involves(PLAYER) || event.impact_speed < CRASH_SPEED {
+                continue;
+            }
+
+            self.plane_systems.crashed = true;
+            let obstacle = event.other(PLAYER).unwrap_or("terrain");
+            self.subtitle_data.add_text(&format!("CRASHED INTO {} AT {:.0} M/S", obstacle.to_uppercase(), event.impact_speed), 5000, app);
+        }
+    }
+
+    // Locks the bandit closest to the nose within LOCK_CONE and LOCK_RANGE, none if there isn't one
+    fn lock_target(&mut self, app: &App) {
+        let Some(player) = app.renderizable_instances.get(PLAYER) else { return };
+        let (position, nose) = (player.instance.transform.position, player.instance.transform.rotation * Vector3::z());
+
+        let closest = self.plane_systems.bandits.iter().enumerate()
+            .filter_map(|(index, bandit)| {
+                let to_bandit = app.renderizable_instances.get(&bandit.tag)?.instance.transform.position - position;
+                let angle = nose.angle(&to_bandit);
+                (to_bandit.norm() <= LOCK_RANGE && angle <= LOCK_CONE.to_radians()).then_some((index, angle))
+            })
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(index, _)| index);
+
+        for (index, bandit) in self.plane_systems.bandits.iter_mut().enumerate() {
+            bandit.locked = closest == Some(index);
+        }
+    }
+
+    fn locked_bandit(&self) -> Option<&Bandit> {
+        self.plane_systems.bandits.iter().find(|bandit| bandit.locked)
+    }
+
+    // Where on screen the gun's pipper goes for the locked bandit, see ballistics::pipper
+    fn pipper(&self, app: &App) -> Option<Point> {
+        let (mount, gun) = self.gun.as_ref()?;
+        let bandit = self.locked_bandit()?;
+        let player = app.renderizable_instances.get(PLAYER)?;
+        let target = app.renderizable_instances.get(&bandit.tag)?.instance.transform.position;
+        let shooter_velocity = self.plane_systems.previous_velocity?;
+
+        let rotation = player.instance.transform.rotation;
+        let muzzle = player.instance.transform.position + rotation * mount.position;
+        let drag_factor = gun.drag_factor(self.atmosphere.sample(muzzle.y).density);
+        let (point, time) = ballistics::pipper(&(target - muzzle), &bandit.linvel, &shooter_velocity, &(rotation * Vector3::z()), gun.muzzle_velocity, drag_factor);
+        if time > gun.max_time_of_flight {
+            return None;
+        }
+
+        app.camera.world_to_screen(Point3::from(muzzle + point), app.renderer.config.width, app.renderer.config.height)
+    }
+
+    // Centers a HUD marker on `screen_position`, hidden while there's none (e.g. off screen)
+    fn place_marker(app: &mut App, tag: &str, screen_position: Option<Point>) {
+        let Some(marker) = Ui::get_ui_node(&mut app.ui.renderizable_elements, tag) else { return };
+
+        if let Some(screen_position) = screen_position {
+            marker.transform.x = screen_position.x as f32 - marker.transform.width / 2.0;
+            marker.transform.y = screen_position.y as f32 - marker.transform.height / 2.0;
+            marker.transform.rect.left = marker.transform.x;
+            marker.transform.rect.top = marker.transform.y;
+            marker.transform.rect.right = marker.transform.x + marker.transform.width;
+            marker.transform.rect.bottom = marker.transform.y + marker.transform.height;
+        }
+        if let UiNodeContent::Text(label) = &mut marker.content {
+            label.color = Color::rgba(0, 255, 75, if screen_position.is_some() { 255 } else { 0 });
+        }
+    }
+
+    // Puts the plane back where the level spawns it, at its initial speed
+    fn respawn(&mut self, app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>) {
+        let Some(player) = app.renderizable_instances.get(PLAYER) else { return };
+        let spawn = player.renderizable_transform;
+        let linvel = player.instance.metadata.physics.as_ref().map_or(Vector3::zeros(), |physics| physics.rigidbody.initial_velocity);
+
+        match teleport_game_object(app, physics_tx, PLAYER, &spawn, linvel) {
+            Ok(()) => {
+                self.plane_systems.crashed = false;
+                self.launcher.rearm();
+                if let Some(event_system) = &mut self.event_system {
+                    event_system.reset_triggers();
+                }
+            },
+            Err(error) => eprintln!("Couldn't respawn: {}", error),
+        }
+    }
+
+    // The level places the player, it's drawn with the model of the aircraft picked in the selection screen
+    fn fly_selected_aircraft(app: &mut App) -> Result<(), String> {
+        let definition = AircraftDefinition::load(&app.selected_aircraft)?;
+        let mut player = app.renderizable_instances.get(PLAYER).ok_or("The level has no player")?.instance.clone();
+        if player.model == definition.model {
+            return Ok(());
+        }
+
+        // Swapped before the physics thread starts, so it isn't told about it
+        despawn_game_object(app, None, PLAYER)?;
+        app.despawned_instances.remove(PLAYER);
+        player.model = definition.model;
+        player.transform.scale = Vector3::repeat(definition.model_scale);
+        spawn_game_object(app, None, player)
+    }
+
     fn blinking_alert(&mut self, blinking_alert: String ,blinkable: &mut UiNode, condition: bool, delta_time: f32) {
         let blinking_alert = self.blinking_alerts.get_mut(&blinking_alert).unwrap();
 
         blinking_alert.time_alert += delta_time;
         if condition {
-            if blinking_alert.alert_state == false {
+            if !blinking_alert.alert_state {
                 if blinking_alert.time_alert > 0.5 {
                     blinking_alert.time_alert = 0.0;
                     blinking_alert.alert_state = true;
@@ -841,18 +1098,15 @@ impl GameLogic {
         }
 
         
-            match &mut blinkable.content {
-                UiNodeContent::Text(label) => {
-                    if blinking_alert.alert_state {
-                        blinkable.visibility.border_color = [1.0, 0.0, 0.0, 1.0];
-                        label.color = Color::rgba(255, 0, 0, 255);
+            if let UiNodeContent::Text(label) = &mut blinkable.content {
+                if blinking_alert.alert_state {
+                    blinkable.visibility.border_color = [1.0, 0.0, 0.0, 1.0];
+                    label.color = Color::rgba(255, 0, 0, 255);
 
-                    } else {
-                        blinkable.visibility.border_color = [0.0, 0.0, 0.0, 0.0];
-                        label.color = Color::rgba(0, 0, 0, 0);
-                    }
-                },
-                _ => {}
+                } else {
+                    blinkable.visibility.border_color = [0.0, 0.0, 0.0, 0.0];
+                    label.color = Color::rgba(0, 0, 0, 0);
+                }
             }
         
     }
@@ -861,7 +1115,7 @@ impl GameLogic {
         (x - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
     }
 
-    fn next_camera(&mut self, camera: &mut CameraRenderizable) {
+    fn next_camera(&mut self, _camera: &mut CameraRenderizable) {
         match self.camera_data.camera_state {
             CameraState::Normal => {
                 self.camera_data.camera_state = CameraState::Free;
@@ -877,18 +1131,74 @@ impl GameLogic {
 impl Scene for GameLogic {
     fn reset(&mut self, app: &mut App) {
         load_level(app, "./assets/scenes/test_chamber".to_owned());
+        if let Err(error) = GameLogic::fly_selected_aircraft(app) {
+            eprintln!("Couldn't load the selected aircraft's model: {}", error);
+        }
         app.ui.load_ui("./assets/ui/game_ui.ron", app.renderer.config.width, app.renderer.config.height);
         *self = GameLogic::new(app);
     }
 
     fn tick(&mut self, app: &mut App, ctx: &mut FrameContext) {
-        self.update(app, ctx.input_subsystem, ctx.plane_control_tx, ctx.physics_data);
+        self.update(app, ctx.input_subsystem, ctx.command_tx, ctx.physics_data, ctx.physics_events);
+        self.handle_collisions(app, ctx.physics_events);
+
+        // The trigger a loaded snapshot comes back with is let go (see GunLogic::load_state)
+        if let Some(changes) = ctx.world_changes {
+            self.launcher.reconcile(app, changes);
+            self.plane_systems.trigger_pulled = false;
+        }
+
+        if ctx.input_subsystem.is_just_pressed("respawn") {
+            self.respawn(app, ctx.physics_tx);
+        }
+
+        for bandit in self.plane_systems.bandits.iter_mut() {
+            if let Some(data) = ctx.physics_data.get(&bandit.tag) {
+                bandit.linvel = data.linvel;
+            }
+        }
+        if ctx.input_subsystem.is_just_pressed("lock_target") {
+            self.lock_target(app);
+        }
+
+        if ctx.input_subsystem.is_just_pressed("fire_missile") && !self.plane_systems.crashed {
+            let target = self.locked_bandit().map(|bandit| bandit.tag.clone());
+            match self.launcher.fire(app, ctx.physics_tx, ctx.command_tx, target) {
+                Ok(seeker) => self.subtitle_data.add_text(seeker.brevity(), 2000, app),
+                Err(error) => println!("{}", error),
+            }
+        }
+        self.launcher.update(app, ctx.physics_tx, ctx.physics_data);
     }
 
     fn physics(&self, app: &App) -> Option<(String, Box<dyn PhysicsTick + Send>)> {
         // reset() (via load_level) already set this to whatever level it just
         // loaded - reuse it instead of keeping a second, separately-typed copy.
         let level_path = app.scene_openned.clone()?;
-        Some((level_path, Box::new(PlanePhysicsLogic::new())))
+
+        match AircraftDefinition::load(&app.selected_aircraft) {
+            // Guns and missiles go first, the damage they push is taken by the plane in the same step
+            Ok(definition) => {
+                let guns = definition.gun.iter()
+                    .filter_map(|mount| match GunDefinition::load(&mount.gun) {
+                        Ok(gun) => Some(Gun::new(PLAYER, gun, mount.position, mount.ammo)),
+                        Err(error) => {
+                            eprintln!("Error: {}", error);
+                            None
+                        },
+                    })
+                    .collect();
+
+                Some((level_path, Box::new(PhysicsTickGroup::new(vec![
+                    Box::new(GunLogic::new(guns)),
+                    Box::new(MissileLogic::new()),
+                    Box::new(PlanePhysicsLogic::new(&definition, PLAYER)),
+                ]))))
+            },
+            Err(error) => {
+                eprintln!("Error: {}", error);
+                None
+            },
+        }
     }
 }
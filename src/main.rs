@@ -1,3 +1,6 @@
+// Every directory has a file of the same name for its main type (engine/physics/physics.rs...)
+#![allow(clippy::module_inception)]
+
 use std::collections::HashMap;
 
 use app::App;
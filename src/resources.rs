@@ -1,12 +1,14 @@
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, sync::mpsc::Sender};
 use gltf::{image,  Gltf};
-use nalgebra::{vector, Quaternion, Unit, Vector3};
-use rapier3d::prelude::{ColliderBuilder, RigidBodyBuilder};
+use nalgebra::{Quaternion, Vector3};
 use ron::from_str;
 use tokio::task;
 use wgpu::{util::DeviceExt, Buffer, Device};
 
-use crate::{app::App, engine::game_nodes::{game_object::GameObject, scene::Scene}, engine::rendering::{enviroment::environment::Environment, enviroment::skybox_renderer::SkyboxRender, instance_management::{InstanceData, InstanceRaw, ModelDataInstance}, models::model::{self, Mesh, Model, ModelVertex}, models::textures::Texture}, transform::Transform};
+use crate::engine::physics::physics_handler::{PhysicsCommand, WorldChanges};
+use crate::engine::rendering::enviroment::terrain_renderer::TerrainRender;
+use crate::engine::terrain::{heightmap::{Heightmap, TerrainSettings}, terrain_mesh::TerrainMesh};
+use crate::{app::App, engine::game_nodes::{game_object::{self, GameObject}, scene::Scene}, engine::rendering::{enviroment::environment::Environment, enviroment::skybox_renderer::SkyboxRender, instance_management::{InstanceData, InstanceRaw, ModelDataInstance}, models::model::{self, Mesh, Model, ModelVertex}, models::textures::Texture}, transform::Transform};
 
 pub async fn load_binary(file_name: &str) -> anyhow::Result<Vec<u8>> {
     let path = std::path::Path::new(env!("OUT_DIR"))
@@ -228,7 +230,7 @@ pub async fn load_model_gltf(file_name: &str, device: &wgpu::Device, queue: &wgp
 
     for scene in gltf.scenes() {
         for node in scene.nodes() {
-            traverse_node(node, &buffer_data, device, queue, transform_bind_group_layout, &mut mesh_lists, file_name, None, default_material_index)?;
+            traverse_node(node, &buffer_data, device, transform_bind_group_layout, &mut mesh_lists, file_name, None, default_material_index)?;
         }
     }
 
@@ -238,7 +240,8 @@ pub async fn load_model_gltf(file_name: &str, device: &wgpu::Device, queue: &wgp
     })
 }
 
-fn traverse_node(node: gltf::Node<'_>, buffer_data: &[Vec<u8>], device: &wgpu::Device, queue: &wgpu::Queue, transform_bind_group_layout: &wgpu::BindGroupLayout, mesh_lists: &mut HashMap<String, HashMap<String, Mesh>>, file_name: &str, parent_transform: Option<([f32; 3], [f32; 4], [f32; 3])>, default_material_index: usize) -> anyhow::Result<()> {
+#[allow(clippy::too_many_arguments)]
+fn traverse_node(node: gltf::Node<'_>, buffer_data: &[Vec<u8>], device: &wgpu::Device, transform_bind_group_layout: &wgpu::BindGroupLayout, mesh_lists: &mut HashMap<String, HashMap<String, Mesh>>, file_name: &str, parent_transform: Option<([f32; 3], [f32; 4], [f32; 3])>, default_material_index: usize) -> anyhow::Result<()> {
         let mesh = node.mesh().expect("Got mesh");
         let primitives = mesh.primitives();
         primitives.for_each(|primitive| {
@@ -305,14 +308,14 @@ fn traverse_node(node: gltf::Node<'_>, buffer_data: &[Vec<u8>], device: &wgpu::D
 
             let transform_matrix = transform.to_matrix_bufferable();
             let transform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some(&format!("Transform Buffer")),
+                label: Some("Transform Buffer"),
                 contents: bytemuck::cast_slice(&[transform_matrix]),
                 usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
             });
 
             let transform_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
                 label: Some("transform bind group"),
-                layout: &transform_bind_group_layout,
+                layout: transform_bind_group_layout,
                 entries: &[
                     wgpu::BindGroupEntry {
                         binding: 0,
@@ -343,7 +346,7 @@ fn traverse_node(node: gltf::Node<'_>, buffer_data: &[Vec<u8>], device: &wgpu::D
             
         });
     for child in node.children() {
-        traverse_node(child, buffer_data, device, queue, transform_bind_group_layout, mesh_lists, file_name, Some(node.transform().decomposed()), default_material_index)?;
+        traverse_node(child, buffer_data, device, transform_bind_group_layout, mesh_lists, file_name, Some(node.transform().decomposed()), default_material_index)?;
     }
 
     Ok(())
@@ -370,13 +373,20 @@ pub fn load_level(app: &mut App, mut level_path: String) {
 
     // i get the json data
     app.renderizable_instances = HashMap::new();
+    app.despawned_instances = HashMap::new();
 
-    for (_key, model) in &mut app.game_models {
+    for model in app.game_models.values_mut() {
         model.instance_count = 0;
     }
 
-    let instances_data_to_load = load_instances(level_path);
-    match instances_data_to_load {
+    let scene = load_scene(level_path);
+
+    app.terrain = match scene.as_ref().and_then(|scene| scene.terrain.as_ref()) {
+        Some(settings) => load_terrain(app, settings).map_err(|error| eprintln!("The terrain was not loaded: {}", error)).ok(),
+        None => None,
+    };
+
+    match scene.map(|scene| scene.children) {
         Some(instances) => {
             // models to load
             let mut models: Vec<String> = vec![];
@@ -413,7 +423,7 @@ pub fn load_level(app: &mut App, mut level_path: String) {
                             let model = task::block_in_place( || {
                                 tokio::runtime::Runtime::new()
                                     .unwrap()
-                                    .block_on(load_model_gltf(&model_name, &app.renderer.device, &app.renderer.queue, &Mesh::create_bind_group_layout(&app.renderer.device)))
+                                    .block_on(load_model_gltf(model_name, &app.renderer.device, &app.renderer.queue, &Mesh::create_bind_group_layout(&app.renderer.device)))
                             });
 
                             match model {
@@ -433,7 +443,7 @@ pub fn load_level(app: &mut App, mut level_path: String) {
                     }
 
                     // println!("loaded data: {}", ids[i]);
-                    app.renderizable_instances.insert(ids[i].clone(), InstanceData { renderizable_transform: instance_data.transform.clone(), instance: (**instance_data).clone(), model_ref: model_name.clone() });
+                    app.renderizable_instances.insert(ids[i].clone(), InstanceData { renderizable_transform: instance_data.transform, instance: (**instance_data).clone(), model_ref: model_name.clone() });
                 }
             }
         },
@@ -441,6 +451,140 @@ pub fn load_level(app: &mut App, mut level_path: String) {
     }
 }
 
+/// The render side of a level's terrain, its collider is built with the rest of the physics.
+fn load_terrain(app: &App, settings: &TerrainSettings) -> Result<TerrainRender, String> {
+    let heightmap = Heightmap::load(settings)?;
+    let mesh = TerrainMesh::new(&heightmap, settings)?;
+
+    let load_image = |file_name: &str| {
+        let data = task::block_in_place(|| {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(load_binary(file_name))
+        }).map_err(|error| format!("Couldn't read '{}': {}", file_name, error))?;
+        ::image::load_from_memory(&data).map_err(|error| format!("'{}' isn't an image: {}", file_name, error))
+    };
+
+    if settings.layers.len() > 4 {
+        eprintln!("The terrain only blends 4 layers, the rest of {:?} are left out", settings.layers);
+    }
+    let splat_map = settings.splat_map.as_deref().map(load_image).transpose()?;
+    let layers = settings.layers.iter().take(4).map(|layer| load_image(layer)).collect::<Result<Vec<_>, _>>()?;
+
+    TerrainRender::new(&app.renderer, &app.camera, &app.light, mesh, settings, splat_map, layers)
+        .map_err(|error| format!("Couldn't upload the terrain: {}", error))
+}
+
+/// Adds a game object to the running level: drawn from this frame on and, when the scene
+/// runs a physics thread (`physics_tx`), simulated from its next step as well.
+pub fn spawn_game_object(app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>, game_object: GameObject) -> Result<(), String> {
+    if app.renderizable_instances.contains_key(&game_object.id) {
+        return Err(format!("'{}' already exists", game_object.id));
+    }
+
+    if !app.game_models.contains_key(&game_object.model) {
+        let model = task::block_in_place( || {
+            tokio::runtime::Runtime::new()
+                .unwrap()
+                .block_on(load_model_gltf(&game_object.model, &app.renderer.device, &app.renderer.queue, &Mesh::create_bind_group_layout(&app.renderer.device)))
+        }).map_err(|error| format!("The element was not loaded as an instance: {}", error))?;
+
+        let instance_buffer = create_instance_buffer(&vec![], &app.renderer.device, app.camera.camera.position.coords);
+        app.game_models.insert(game_object.model.clone(), ModelDataInstance { model, instance_count: 0, instance_buffer });
+    }
+
+    if let Some(physics_tx) = physics_tx {
+        if game_object.metadata.physics.is_some() {
+            physics_tx.send(PhysicsCommand::Spawn(Box::new(game_object.clone()))).map_err(|error| error.to_string())?;
+        }
+    }
+
+    let model_name = game_object.model.clone();
+    app.renderizable_instances.insert(game_object.id.clone(), InstanceData { renderizable_transform: game_object.transform, instance: game_object, model_ref: model_name.clone() });
+    resize_instance_buffer(app, &model_name);
+    Ok(())
+}
+
+/// Removes a game object from the running level, and from the physics world if it's in one.
+pub fn despawn_game_object(app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>, id: &str) -> Result<(), String> {
+    let removed = app.renderizable_instances.remove(id).ok_or_else(|| format!("There is no '{}' to despawn", id))?;
+
+    if let Some(physics_tx) = physics_tx {
+        if removed.instance.metadata.physics.is_some() {
+            physics_tx.send(PhysicsCommand::Despawn(id.to_string())).map_err(|error| error.to_string())?;
+        }
+    }
+
+    let model_ref = removed.model_ref.clone();
+    if removed.instance.metadata.physics.is_some() {
+        app.despawned_instances.insert(id.to_string(), removed);
+    }
+    resize_instance_buffer(app, &model_ref);
+    Ok(())
+}
+
+/// Matches the running level to a physics world that replaced the old one (see
+/// PhysicsCommand::LoadSnapshot): what it took out stops being drawn, and what it brought
+/// back that was despawned earlier is drawn again. Anything else it brought has nothing to
+/// draw it with, so it's taken out of the world.
+pub fn reconcile_physics_world(app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>, changes: &WorldChanges) {
+    for id in &changes.removed {
+        if app.renderizable_instances.contains_key(id) {
+            if let Err(error) = despawn_game_object(app, None, id) {
+                eprintln!("{}", error);
+            }
+        }
+    }
+
+    for entity in &changes.added {
+        if app.renderizable_instances.contains_key(entity) {
+            continue;
+        }
+        let restored = match app.despawned_instances.remove(entity) {
+            Some(renderizable) => spawn_game_object(app, None, renderizable.instance),
+            None => Err(format!("'{}' isn't in the level", entity)),
+        };
+        if let Err(error) = restored {
+            eprintln!("Dropping '{}' from the loaded physics: {}", entity, error);
+            if let Some(physics_tx) = physics_tx {
+                let _ = physics_tx.send(PhysicsCommand::Despawn(entity.clone()));
+            }
+        }
+    }
+}
+
+/// Moves a game object to `transform` (its scale is kept), setting its velocity to `linvel`.
+pub fn teleport_game_object(app: &mut App, physics_tx: Option<&Sender<PhysicsCommand>>, id: &str, transform: &game_object::Transform, linvel: Vector3<f32>) -> Result<(), String> {
+    let renderizable = app.renderizable_instances.get_mut(id).ok_or_else(|| format!("There is no '{}' to teleport", id))?;
+    renderizable.instance.transform.position = transform.position;
+    renderizable.instance.transform.rotation = transform.rotation;
+
+    if let Some(physics_tx) = physics_tx {
+        if renderizable.instance.metadata.physics.is_some() {
+            let command = PhysicsCommand::Teleport { entity: id.to_string(), position: transform.position, rotation: transform.rotation, linvel };
+            physics_tx.send(command).map_err(|error| error.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+// The instance buffers are sized for the instances they were created with, so they're
+// rebuilt whenever a model gains or loses one (the frame loop rewrites their contents anyway)
+fn resize_instance_buffer(app: &mut App, model_name: &str) {
+    let instances: Vec<&GameObject> = app.renderizable_instances.values()
+        .filter(|renderizable| renderizable.model_ref == model_name)
+        .map(|renderizable| &renderizable.instance)
+        .collect();
+    let instance_buffer = create_instance_buffer(&instances, &app.renderer.device, app.camera.camera.position.coords);
+    let instance_count = instances.len() as u32;
+
+    if let Some(model_data) = app.game_models.get_mut(model_name) {
+        model_data.instance_buffer = instance_buffer;
+        model_data.instance_count = instance_count;
+    }
+}
+
 /// Applies a scene's declared environment (flat color or skybox cubemap), replacing
 /// whatever the app currently has. Call from `Scene::reset`, same as `load_level`.
 pub fn apply_environment(app: &mut App, environment: Environment) {
@@ -488,19 +632,21 @@ pub fn create_instance_buffer(instances: &Vec<&GameObject>, device: &Device, cam
 }
 
 pub fn load_instances(path: String) -> Option<Vec<GameObject>> {
-    match std::fs::read_to_string(path) {
-        Ok(file_contents) => {
-            match from_str::<Scene>(&file_contents) {
-                Ok(level) => {
-                    return Some(level.children);
-                },
-                Err(e) => {
-                    // Handle the error if deserialization fails
-                    eprintln!("Error deserializing RON: {}", e);
-                }
+    load_scene(path).map(|level| level.children)
+}
+
+/// The whole scene file, for the level-wide settings that aren't part of any game object (e.g. its atmosphere).
+pub fn load_scene(path: String) -> Option<Scene> {
+    if let Ok(file_contents) = std::fs::read_to_string(path) {
+        match from_str::<Scene>(&file_contents) {
+            Ok(level) => {
+                return Some(level);
+            },
+            Err(e) => {
+                // Handle the error if deserialization fails
+                eprintln!("Error deserializing RON: {}", e);
             }
-        },
-        _ => {}
+        }
     }
-    return None
+    None
 }
@@ -16,7 +16,7 @@ impl Transform {
         }
     }
 
-    pub fn to_matrix_bufferable(&self) -> [[f32; 4]; 4] {
+    pub fn to_matrix_bufferable(self) -> [[f32; 4]; 4] {
         let translation = Matrix4::new_translation(&self.position);
         let rotation = UnitQuaternion::from_quaternion(self.rotation).to_homogeneous();
         let scale = Matrix4::new_nonuniform_scaling(&self.scale);